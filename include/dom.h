@@ -0,0 +1,171 @@
+#include "http.h"
+
+typedef enum {
+    /* response of a request, by index of its ws_request */
+    WS_SOURCE_REQUEST,
+    /* messages of a stream, by index of its ws_subscription */
+    WS_SOURCE_STREAM,
+    /* form controls, by index of the writable variable */
+    WS_SOURCE_INPUT,
+    /* current path, index is always 0 */
+    WS_SOURCE_ROUTE
+} ws_source_kind;
+
+typedef struct {
+    ws_source_kind kind;
+    int index;
+} ws_source;
+
+typedef enum {
+    /* text node of an inserted value */
+    WS_BINDING_TEXT,
+    /* attribute whose value contains inserted values */
+    WS_BINDING_ATTRIBUTE,
+    /* branch of a condition, loop, call or request with states, rendered as a whole */
+    WS_BINDING_REGION
+} ws_binding_kind;
+
+/**
+ * Node of a template depending on data which changes while the page is shown.
+ * The host finds the bound nodes by their anchor in the rendered page. Once a source changes,
+ * it sets the text of text nodes, recomputes attributes from their parts and replaces the
+ * HTML of regions. Other nodes are left untouched, so focus, selection and scroll position are kept.
+ * Nodes within a region that is replaced are not patched themselves.
+ */
+typedef struct {
+    ws_binding_kind kind;
+    /* "<template>:<index>", text nodes and regions are enclosed in the comments <!--ws:anchor--> and
+       <!--/ws:anchor-->, elements list the anchors of their bound attributes in data-ws-anchor="a b" */
+    const char* anchor;
+    /* name of the attribute, null for text nodes and regions */
+    const char* attribute;
+    /* parts of the attribute value, concatenated */
+    const ws_value* parts;
+    int part_count;
+    /* index of the region containing the node, -1 if there is none */
+    int parent;
+    const ws_source* sources;
+    int source_count;
+} ws_binding;
+
+typedef enum {
+    /* calls a handler of the host */
+    WS_ACTION_CALL,
+    /* sends a request */
+    WS_ACTION_REQUEST
+} ws_action_kind;
+
+/**
+ * Listener of the element whose tag contains {ON ...}. The host renders the attribute
+ * data-ws-on-<event> into the tag and attaches a listener for the event to the element.
+ * Within the action, the variable "form" holds the fields of the form the element belongs to.
+ */
+typedef struct {
+    /* name of the DOM event, e.g. "click" */
+    const char* event;
+    ws_action_kind action;
+    /* WS_ACTION_CALL: name of the handler and its argument, WS_NONE if there is none */
+    const char* handler;
+    ws_value argument;
+    /* WS_ACTION_REQUEST: index of the ws_request, which is sent once per event */
+    int request;
+} ws_listener;
+
+/**
+ * Implemented by the JavaScript host: calls the handler registered under its name.
+ * The built-in handler "refresh" refreshes the requests bound to the variable named by the argument
+ * (see ws_refresh), or all requests of the page if there is no argument.
+ * The built-in handler "navigate" navigates to the path given by the argument (see ws_navigate).
+ */
+extern void ws_call(const char* handler, ws_value argument);
+
+/**
+ * Form control bound to a variable using {BIND ...}. The host renders the attribute data-ws-bind and the
+ * current value into the control. Whenever the event occurs, it writes the value of the control to the target
+ * and patches the nodes depending on the variable (WS_SOURCE_INPUT). Requests sending the variable,
+ * e.g. {POST user TO ...}, serialize its current value.
+ */
+typedef struct {
+    /* name of the DOM event, e.g. "input" */
+    const char* event;
+    /* path of the value, e.g. "user.email" */
+    const char* target;
+    /* index of the root variable among the writable variables of the template */
+    int variable;
+} ws_input;
+
+typedef enum {
+    /* value is neither missing nor blank, checkboxes must be checked */
+    WS_RULE_REQUIRED,
+    WS_RULE_EMAIL,
+    /* bound is the minimum or maximum number of characters */
+    WS_RULE_MIN_LENGTH,
+    WS_RULE_MAX_LENGTH,
+    /* the whole value matches the regular expression, like ^(?:pattern)$ */
+    WS_RULE_PATTERN,
+    /* bound is the minimum or maximum number */
+    WS_RULE_MIN,
+    WS_RULE_MAX
+} ws_rule_kind;
+
+typedef struct {
+    ws_rule_kind kind;
+    double bound;
+    /* WS_RULE_PATTERN: regular expression, null otherwise */
+    const char* pattern;
+    /* message shown if the rule is violated */
+    const char* message;
+} ws_rule;
+
+/**
+ * Rules of a value given by {VALIDATE ...}. Before sending a request with a body (POST, PUT or PATCH),
+ * the host checks the validations of the page and does not send the request while a rule is violated.
+ * Values that are missing or blank only violate WS_RULE_REQUIRED.
+ * The error variable holds the message of the first violated rule once the value has been written by a form control
+ * or a request has been blocked, null otherwise. After blocking a request, the host patches the nodes depending on
+ * the sources of the validated values, so their messages are shown.
+ */
+typedef struct {
+    /* path of the value, e.g. "user.email" */
+    const char* target;
+    /* rules in order of declaration, the first violated one gives the message */
+    const ws_rule* rules;
+    int rule_count;
+    /* name of the error variable, null if there is none */
+    const char* error_variable;
+} ws_validation;
+
+/**
+ * Route of a template given by {ROUTE ...}. Where the route is placed, the host renders the template of the first route
+ * of the template matching the current path, or the fallback if none matches. Segments of the path starting with ':'
+ * match any segment, which is passed to the routed template as the parameter of the same name. Segments are
+ * percent-decoded before they are compared. Query and fragment of the current path are ignored.
+ * Links are only intercepted if a route other than the fallback matches their path.
+ * If the template is part of another module, the host loads the module first (see ws_load_chunk).
+ */
+typedef struct {
+    /* pattern, e.g. "/users/:id", null for the fallback */
+    const char* path;
+    /* id of the routed template */
+    const char* template;
+    /* module containing the template, null if it is part of the core module */
+    const char* chunk;
+    /* names of the parameters, in order of the path */
+    const char** params;
+    int param_count;
+} ws_route;
+
+/**
+ * Implemented by the JavaScript host: navigates to the path without reloading the page.
+ * The host pushes the path onto the history (history.pushState) and patches the nodes depending on WS_SOURCE_ROUTE.
+ * It navigates the same way when links to paths of the own origin are clicked, instead of following them,
+ * and when the user goes back or forward in the history (popstate), without pushing the path again.
+ */
+extern void ws_navigate(const char* path);
+
+/**
+ * Implemented by the JavaScript host: loads the module of routed templates with the given name (<chunk>.wasm),
+ * linking it against the core module. Modules are loaded once, the first time one of their routes matches.
+ * Until the module has been loaded, the route renders nothing. Afterwards the host patches the nodes depending on WS_SOURCE_ROUTE.
+ */
+extern void ws_load_chunk(const char* chunk);
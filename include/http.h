@@ -0,0 +1,128 @@
+#include "strings.h"
+
+typedef enum {
+    WS_HTTP_GET,
+    WS_HTTP_POST,
+    WS_HTTP_PUT,
+    WS_HTTP_PATCH,
+    WS_HTTP_DELETE
+} ws_http_method;
+
+static const char* const WS_HTTP_METHOD_NAMES[] = { "GET", "POST", "PUT", "PATCH", "DELETE" };
+
+typedef enum {
+    /* nothing, e.g. a request without body */
+    WS_NONE,
+    /* text as it is */
+    WS_VALUE,
+    /* path of a variable, e.g. "session.token" or "user?.orders[0]['first name']":
+       names are separated by '.', indices and other keys are written in brackets.
+       Names and quoted keys read members of objects, indices read items of arrays ("items.0" is no item).
+       Segments after "?." are null if the value before them is null; reading any other segment of null
+       (or of a value of the wrong kind) is an error, which the host reports and treats as null.
+       The compiler rejects paths reading such segments where the schema allows null */
+    WS_VARIABLE,
+    /* id of a form, whose fields are serialized */
+    WS_FORM
+} ws_value_kind;
+
+typedef struct {
+    ws_value_kind kind;
+    const char* text;
+} ws_value;
+
+/* branches of a request besides its content */
+#define WS_STATE_LOADING 1
+#define WS_STATE_EMPTY 2
+#define WS_STATE_ERROR 4
+
+/* header or query parameter */
+typedef struct {
+    const char* name;
+    ws_value value;
+} ws_param;
+
+typedef struct {
+    ws_http_method method;
+    ws_value url;
+    ws_value body;
+    const ws_param* query;
+    int query_count;
+    const ws_param* headers;
+    int header_count;
+    /* send cookies and authorization to other origins */
+    bool credentials;
+    /* milliseconds a successful response is reused without asking the server, 0 if it is not cached */
+    int cache_ms;
+    /* milliseconds after which the request is sent again, 0 if it is not refreshed */
+    int refresh_ms;
+    /* variable the response is bound to, (void*)0 if the response is dropped */
+    const char* target;
+    /* WS_STATE_* flags of the branches the template declares for the request */
+    int states;
+    /* variable declared by the error branch, null if there is none */
+    const char* error_variable;
+    /* index of the WS_BINDING_REGION enclosing the content and the branches, -1 if the request is rendered along
+       with a surrounding region (e.g. inside of a loop) or has no branches. The host renders the loading branch into
+       the region while the request is pending and replaces it by the content, the empty or the error branch once
+       the response has arrived */
+    int region;
+    /* markup of the branches, concatenated like the parts of bound attributes: WS_VALUE parts are HTML,
+       WS_VARIABLE parts are inserted as escaped text. Values bound to text nodes are enclosed in the comments of
+       their anchor. Null if the branch is empty or not declared */
+    const ws_value* loading;
+    int loading_count;
+    const ws_value* empty;
+    int empty_count;
+    const ws_value* error;
+    int error_count;
+    /* indices of the requests of the template whose responses are needed to build this request */
+    const int* dependencies;
+    int dependency_count;
+    /* index of the ws_listener sending the request once per event, -1 for requests sent by the page */
+    int listener;
+} ws_request;
+
+/* base url of the selected environment, generated by the compiler */
+extern const char* WS_BASE_URL;
+
+/**
+ * Implemented by the JavaScript host: resolves the values of the request,
+ * sends it and binds the response to its target.
+ * Urls starting with '/' are prefixed by WS_BASE_URL, names and resolved values of the query are
+ * percent-encoded (RFC 3986) and appended using '?', or '&' if the url already has a query.
+ * Requests are sent as soon as the requests they depend on have responded, independent requests concurrently.
+ * Identical requests in flight are sent once. Cached responses are reused for cache_ms,
+ * afterwards (and for requests without cache_ms) responses having an ETag are revalidated using If-None-Match.
+ * Each request renders its own content once its response arrives, without waiting for the other requests.
+ */
+extern void ws_http_send(const ws_request* request);
+
+/**
+ * Implemented by the JavaScript host: sends the requests bound to variable again, bypassing the cache,
+ * and patches only the nodes depending on them (ws_binding with a WS_SOURCE_REQUEST of the requests) once the
+ * new responses arrive. Requests with refresh_ms are refreshed the same way when their interval has passed.
+ */
+extern void ws_refresh(const char* variable);
+
+typedef enum {
+    /* ws:// and wss:// urls, urls starting with '/' use WS_BASE_URL with ws instead of http */
+    WS_STREAM_WEBSOCKET,
+    /* server-sent events */
+    WS_STREAM_EVENT_SOURCE
+} ws_stream_transport;
+
+typedef struct {
+    ws_stream_transport transport;
+    ws_value url;
+    /* variable the messages are bound to */
+    const char* target;
+    /* the variable holds an array of all messages instead of the latest one */
+    bool append;
+} ws_subscription;
+
+/**
+ * Implemented by the JavaScript host: opens the stream unless it is already open,
+ * binds every JSON message to the target and re-renders only the parts of the page using it
+ */
+extern void ws_subscribe(const ws_subscription* subscription);
@@ -0,0 +1,462 @@
+use std::collections::{BTreeSet, HashMap, LinkedList};
+
+use crate::compiler::model::{AttributePart, Binding, BindingKind, CoreModel, Data, EventAction, Expression, Source, TemplateOperation};
+
+type Sources = BTreeSet<Source>;
+
+/**
+ * Position within the markup of a template, as far as it matters for bindings
+ */
+enum Markup {
+	Text,
+	/// name of a tag, after `<`
+	Open(String),
+	/// inside of a tag, outside of attribute values
+	Tag,
+	/// name of an attribute
+	Name(String),
+	/// between name and value of an attribute
+	Equals(String),
+	/// value of an attribute, enclosed in `quote` unless it is unquoted
+	Value {
+		name: String,
+		quote: Option<char>,
+		/// parts before the current text
+		parts: Vec<AttributePart>,
+		text: String,
+		binding: Option<usize>
+	},
+	/// inside of `<!-- -->`, counting the dashes read last
+	Comment(usize),
+	/// contents of `<script>` and `<style>`, until the closing tag of the element
+	Raw(String)
+}
+
+/// # Finds the nodes of each template depending on data which changes while the page is shown
+/// Sources are the responses of requests and the messages of streams, variables set from them
+/// depend on them as well. The client patches the bound nodes when their sources change:
+/// * text nodes of inserted values, e.g. `<p>{user.name}</p>`
+/// * attributes containing inserted values, e.g. `<a href="/users/{user.id}">`
+/// * regions whose structure changes, i.e. conditions, loops, calls and requests with states.
+///   Nodes inside of loops and calls are rendered again with their region, they are not bound themselves.
+///
+/// Values inside of comments, scripts and styles are not bound. The client finds the bound nodes by their
+/// anchor (see `Binding::anchor`): text nodes and regions are enclosed in comments, elements get the
+/// attribute `data-ws-anchor` added to their tag.
+///
+/// Runs after the scheduler, which numbers requests and subscriptions.
+pub struct BinderComponent {
+	/// sources of the variables by scope, innermost last
+	scopes: Vec<HashMap<String, Sources>>,
+	bindings: Vec<Binding>,
+	markup: Markup,
+	/// writable variables of the current template
+	writable: Vec<String>,
+	/// id of the current template
+	template: String,
+	/// name of the current tag, lowercase
+	element: String,
+	/// bound attributes of the current tag
+	anchors: Vec<usize>
+}
+
+impl BinderComponent {
+	pub fn new() -> BinderComponent {
+		Self {
+			scopes: Vec::new(),
+			bindings: Vec::new(),
+			markup: Markup::Text,
+			writable: Vec::new(),
+			template: String::new(),
+			element: String::new(),
+			anchors: Vec::new()
+		}
+	}
+
+	/// Assigns `bindings` of all templates and the `binding` of their operations
+	pub fn bind(&mut self, model: &mut CoreModel) {
+		for template in model.templates.iter_mut() {
+			self.scopes = vec![HashMap::new()];
+			self.markup = Markup::Text;
+			self.template = template.id.clone();
+			self.anchors.clear();
+			self.writable = template.writable.iter().map(|variable| variable.id.clone()).collect();
+			self.bind_operations(&mut template.operations, None, true);
+			self.read_markup(">");
+			template.bindings = std::mem::take(&mut self.bindings);
+		}
+	}
+
+	/// # Binds operations
+	/// * `parent` - region containing the operations
+	/// * `tracked` - whether the operations may be bound, which they are not inside of loops and calls
+	///
+	/// Returns all sources the operations use.
+	fn bind_operations(&mut self, operations: &mut LinkedList<TemplateOperation>, parent: Option<usize>, tracked: bool) -> Sources {
+		let mut sources = Sources::new();
+		for operation in operations.iter_mut() {
+			sources.extend(self.bind_operation(operation, parent, tracked));
+		}
+		sources
+	}
+
+	/// Binds operations in a scope of their own
+	fn bind_scoped(&mut self, operations: &mut LinkedList<TemplateOperation>, variables: HashMap<String, Sources>, parent: Option<usize>, tracked: bool) -> Sources {
+		self.scopes.push(variables);
+		let sources = self.bind_operations(operations, parent, tracked);
+		self.scopes.pop();
+		sources
+	}
+
+	fn bind_operation(&mut self, operation: &mut TemplateOperation, parent: Option<usize>, tracked: bool) -> Sources {
+		match operation {
+			TemplateOperation::Print(print) => {
+				if let Data::Value(text) = &mut print.content {
+					*text = self.read_markup(text);
+				}
+				Sources::new()
+			},
+			TemplateOperation::Insert(insert) => {
+				let sources = self.sources(&insert.content);
+				// values inside of loops and calls still take part in the markup
+				let bound = if tracked { sources.clone() } else { Sources::new() };
+				insert.binding = self.bind_insert(&insert.content, &bound, parent);
+				sources
+			},
+			TemplateOperation::Set(set) => {
+				let sources = self.expression_sources(&set.value);
+				self.scopes.last_mut().unwrap().insert(set.target.id.clone(), sources.clone());
+				sources
+			},
+			TemplateOperation::If(conditional) => {
+				let mut sources = self.expression_sources(&conditional.condition);
+				if self.in_text(tracked) && !sources.is_empty() {
+					conditional.binding = Some(self.add_binding(BindingKind::Region, &sources, parent));
+				}
+				let parent = conditional.binding.or(parent);
+				sources.extend(self.bind_scoped(&mut conditional.operations, HashMap::new(), parent, tracked));
+				sources.extend(self.bind_scoped(&mut conditional.alternative, HashMap::new(), parent, tracked));
+				sources
+			},
+			TemplateOperation::Loop(repetition) => {
+				let region = self.in_text(tracked);
+				let mut sources = self.sources(&repetition.items);
+				let mut variables = HashMap::new();
+				variables.insert(repetition.item.id.clone(), sources.clone());
+				sources.extend(self.bind_scoped(&mut repetition.operations, variables, None, false));
+				if region && !sources.is_empty() {
+					repetition.binding = Some(self.add_binding(BindingKind::Region, &sources, parent));
+				}
+				sources
+			},
+			TemplateOperation::Block(block) => self.bind_scoped(&mut block.operations, HashMap::new(), parent, tracked),
+			TemplateOperation::Call(call) => {
+				let region = self.in_text(tracked);
+				let mut sources: Sources = call.params.iter().flat_map(|param| self.sources(&param.value)).collect();
+				for slot in call.slots.iter_mut() {
+					sources.extend(self.bind_scoped(&mut slot.operations, HashMap::new(), None, false));
+				}
+				if region && !sources.is_empty() {
+					call.binding = Some(self.add_binding(BindingKind::Region, &sources, parent));
+				}
+				sources
+			},
+			TemplateOperation::Fetch(fetch) => {
+				let mut sources: Sources = fetch.dependencies.iter().map(|index| Source::Request(*index)).collect();
+				sources.insert(Source::Request(fetch.index));
+				if let Some(target) = &fetch.target {
+					self.scopes.last_mut().unwrap().insert(target.id.clone(), Sources::from([Source::Request(fetch.index)]));
+				}
+				if self.in_text(tracked) && fetch.has_states() {
+					fetch.binding = Some(self.add_binding(BindingKind::Region, &sources, parent));
+				}
+
+				let parent = fetch.binding.or(parent);
+				sources.extend(self.bind_operations(&mut fetch.operations, parent, tracked));
+				if let Some(loading) = &mut fetch.loading {
+					sources.extend(self.bind_scoped(loading, HashMap::new(), parent, tracked));
+				}
+				if let Some(empty) = &mut fetch.empty {
+					sources.extend(self.bind_scoped(empty, HashMap::new(), parent, tracked));
+				}
+				if let Some(error) = &mut fetch.error {
+					let mut variables = HashMap::new();
+					variables.insert(error.variable.id.clone(), Sources::from([Source::Request(fetch.index)]));
+					sources.extend(self.bind_scoped(&mut error.operations, variables, parent, tracked));
+				}
+				sources
+			},
+			TemplateOperation::Subscribe(subscribe) => {
+				self.scopes.last_mut().unwrap().insert(subscribe.target.id.clone(), Sources::from([Source::Stream(subscribe.index)]));
+				Sources::new()
+			},
+			TemplateOperation::On(listener) => {
+				if let EventAction::Request(fetch) = &listener.action {
+					if let Some(target) = &fetch.target {
+						self.scopes.last_mut().unwrap().insert(target.id.clone(), Sources::from([Source::Request(fetch.index)]));
+					}
+				}
+				Sources::new()
+			},
+			// the message changes along with the validated value
+			TemplateOperation::Validate(validate) => {
+				if let Some(error) = &validate.error {
+					let sources = self.sources(&Data::Variable(validate.target.clone(), None));
+					self.scopes.last_mut().unwrap().insert(error.id.clone(), sources);
+				}
+				Sources::new()
+			},
+			// the routed template is rendered again as a whole once the client navigates
+			TemplateOperation::Route(route) => {
+				let sources = Sources::from([Source::Route]);
+				if self.in_text(tracked) {
+					route.binding = Some(self.add_binding(BindingKind::Region, &sources, parent));
+				}
+				sources
+			},
+			TemplateOperation::Bind(_)|TemplateOperation::Slot(_) => Sources::new()
+		}
+	}
+
+	/// Whether a region may start here, regions are enclosed in comments and thus bound between tags only
+	fn in_text(&self, tracked: bool) -> bool {
+		tracked && matches!(self.markup, Markup::Text)
+	}
+
+	fn add_binding(&mut self, kind: BindingKind, sources: &Sources, parent: Option<usize>) -> usize {
+		self.bindings.push(Binding { kind, sources: sources.iter().copied().collect(), parent });
+		self.bindings.len() - 1
+	}
+
+	/// Binds the text node or attribute an inserted value ends up in, values inside of tags are not bound
+	fn bind_insert(&mut self, content: &Data, sources: &Sources, parent: Option<usize>) -> Option<usize> {
+		match &mut self.markup {
+			Markup::Text if !sources.is_empty() => Some(self.add_binding(BindingKind::Text, sources, parent)),
+			// unquoted value
+			Markup::Equals(name) => {
+				let name = std::mem::take(name);
+				self.markup = Markup::Value { name, quote: None, parts: Vec::new(), text: String::new(), binding: None };
+				self.bind_insert(content, sources, parent)
+			},
+			Markup::Value { name, parts, text, binding, .. } => {
+				if !text.is_empty() {
+					parts.push(AttributePart::Text(std::mem::take(text)));
+				}
+				parts.push(AttributePart::Value(content.clone()));
+				match binding {
+					Some(index) => {
+						let index = *index;
+						let bound = &mut self.bindings[index].sources;
+						bound.extend(sources.iter().copied());
+						bound.sort();
+						bound.dedup();
+						Some(index)
+					},
+					None if !sources.is_empty() => {
+						let kind = BindingKind::Attribute { name: name.clone(), parts: Vec::new() };
+						*binding = Some(self.bindings.len());
+						let index = self.add_binding(kind, sources, parent);
+						self.anchors.push(index);
+						Some(index)
+					},
+					None => None
+				}
+			},
+			_ => None
+		}
+	}
+
+	/// # Follows markup of plain text
+	/// Once the value of a bound attribute ends, its parts are stored in the binding.
+	/// Returns the text with the anchors of the bound attributes added to the tags they end.
+	fn read_markup(&mut self, text: &str) -> String {
+		let mut output = String::with_capacity(text.len());
+		for c in text.chars() {
+			let markup = std::mem::replace(&mut self.markup, Markup::Text);
+			self.markup = match markup {
+				Markup::Text if c == '<' => Markup::Open(String::new()),
+				Markup::Text => Markup::Text,
+				Markup::Open(name) if c == '>' => {
+					self.element = name.to_lowercase();
+					self.end_tag(&mut output)
+				},
+				Markup::Open(name) if c.is_whitespace() || (c == '/' && !name.is_empty()) => {
+					self.element = name.to_lowercase();
+					Markup::Tag
+				},
+				Markup::Open(mut name) => {
+					name.push(c);
+					if name == "!--" { Markup::Comment(0) } else { Markup::Open(name) }
+				},
+				Markup::Comment(dashes) if c == '>' && dashes >= 2 => Markup::Text,
+				Markup::Comment(dashes) => Markup::Comment(if c == '-' { dashes + 1 } else { 0 }),
+				Markup::Raw(mut tail) => {
+					tail.push(c.to_ascii_lowercase());
+					let closing = format!("</{}", self.element);
+					if tail.ends_with(&closing) {
+						Markup::Open(closing[1..].to_string())
+					} else {
+						// only the end of the contents can start the closing tag
+						while tail.len() > closing.len() {
+							tail.remove(0);
+						}
+						Markup::Raw(tail)
+					}
+				},
+				Markup::Tag if c == '>' => self.end_tag(&mut output),
+				Markup::Tag if c.is_whitespace() || c == '/' => Markup::Tag,
+				Markup::Tag => Markup::Name(c.to_string()),
+				Markup::Name(_) if c == '>' => self.end_tag(&mut output),
+				Markup::Name(name) if c == '=' => Markup::Equals(name),
+				Markup::Name(_) if c.is_whitespace() => Markup::Tag,
+				Markup::Name(name) => Markup::Name(name + &c.to_string()),
+				Markup::Equals(_) if c == '>' => self.end_tag(&mut output),
+				Markup::Equals(name) if c == '"' || c == '\'' => Markup::Value { name, quote: Some(c), parts: Vec::new(), text: String::new(), binding: None },
+				Markup::Equals(name) if c.is_whitespace() => Markup::Equals(name),
+				Markup::Equals(name) => Markup::Value { name, quote: None, parts: Vec::new(), text: c.to_string(), binding: None },
+				Markup::Value { quote: Some(quote), parts, text, binding, .. } if c == quote => {
+					self.finish_attribute(parts, text, binding);
+					Markup::Tag
+				},
+				Markup::Value { quote: None, parts, text, binding, .. } if c.is_whitespace() || c == '>' => {
+					self.finish_attribute(parts, text, binding);
+					if c == '>' { self.end_tag(&mut output) } else { Markup::Tag }
+				},
+				Markup::Value { name, quote, parts, mut text, binding } => {
+					text.push(c);
+					Markup::Value { name, quote, parts, text, binding }
+				}
+			};
+			output.push(c);
+		}
+		output
+	}
+
+	/// Adds the anchors of the bound attributes before the end of the tag, the contents of scripts and styles are not markup
+	fn end_tag(&mut self, output: &mut String) -> Markup {
+		if !self.anchors.is_empty() {
+			let anchors: Vec<String> = std::mem::take(&mut self.anchors).into_iter().map(|index| Binding::anchor(&self.template, index)).collect();
+			let closing = if output.ends_with('/') { output.pop() } else { None };
+			output.push_str(&format!(" data-ws-anchor=\"{}\"", anchors.join(" ")));
+			output.extend(closing);
+		}
+		match self.element.as_str() {
+			"script"|"style" => Markup::Raw(String::new()),
+			_ => Markup::Text
+		}
+	}
+
+	fn finish_attribute(&mut self, mut parts: Vec<AttributePart>, text: String, binding: Option<usize>) {
+		if let Some(index) = binding {
+			if !text.is_empty() {
+				parts.push(AttributePart::Text(text));
+			}
+			if let BindingKind::Attribute { parts: bound, .. } = &mut self.bindings[index].kind {
+				*bound = parts;
+			}
+		}
+	}
+
+	fn expression_sources(&self, expression: &Expression) -> Sources {
+		expression.all_operands().flat_map(|operand| self.sources(&operand.data)).collect()
+	}
+
+	/// Sources the data is derived from, literals and parameters have none.
+	/// Writable variables are changed by form controls as well.
+	fn sources(&self, data: &Data) -> Sources {
+		match data {
+			Data::Variable(variable, _) => {
+				let mut sources = self.scopes.iter().rev()
+					.find_map(|scope| scope.get(&variable.id))
+					.cloned()
+					.unwrap_or_default();
+				if let Some(index) = self.writable.iter().position(|writable| *writable == variable.id) {
+					sources.insert(Source::Input(index));
+				}
+				sources
+			},
+			_ => Sources::new()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::LinkedList;
+
+	use crate::compiler::{build_model, model::{AttributePart, BindingKind, Data, Source, Template, TemplateOperation}};
+
+	fn bind(source: &'static str) -> Template {
+		let mut sources = LinkedList::new();
+		sources.push_back(("index.ws".to_string(), source));
+		build_model(sources, &mut LinkedList::new()).ok().unwrap().templates.pop_front().unwrap()
+	}
+
+	/// Kinds of the bindings, attributes are written as `name=part+part`
+	fn kinds(template: &Template) -> Vec<String> {
+		template.bindings.iter().map(|binding| match &binding.kind {
+			BindingKind::Text => "text".to_string(),
+			BindingKind::Region => "region".to_string(),
+			BindingKind::Attribute { name, parts } => format!("{}={}", name, parts.iter().map(|part| match part {
+				AttributePart::Text(text) => format!("'{}'", text),
+				AttributePart::Value(Data::Variable(variable, _)) => variable.to_string(),
+				AttributePart::Value(_) => "?".to_string()
+			}).collect::<Vec<String>>().join("+"))
+		}).collect()
+	}
+
+	#[test]
+	fn bind_text_and_attributes() {
+		let template = bind("{SET title = 'Users'}<h1 class=\"title\">{title}</h1>{GET user FROM '/api/user'}{SUBSCRIBE status FROM 'ws://localhost/status'}\
+			<a class=\"link\" href=\"/users/{user.id}?v={user.version}\" title={status}>{user.name}</a>{SET count = user.tags | length}<p>{count}</p>");
+
+		assert_eq!(kinds(&template), vec!["href='/users/'+user.id+'?v='+user.version", "title=status", "text", "text"]);
+		assert_eq!(template.bindings[0].sources, vec![Source::Request(0)]);
+		assert_eq!(template.bindings[1].sources, vec![Source::Stream(0)]);
+		assert_eq!(template.bindings[3].sources, vec![Source::Request(0)]);
+		assert!(template.bindings.iter().all(|binding| binding.parent.is_none()));
+	}
+
+	#[test]
+	fn bind_regions() {
+		let template = bind("{GET user FROM '/api/user'}{GET posts FROM '/api/posts' PARAMS user=user.id}\
+			<ul>{FOR post IN posts}<li>{post.title}</li>{END}</ul>\
+			{IF user.admin}<b>{user.name}</b>{END}{IF 'x'}<i>{user.name}</i>{END}\
+			{GET stats FROM '/api/stats'}<p>{stats.count}</p>{LOADING}...{END}");
+
+		assert_eq!(kinds(&template), vec!["region", "region", "text", "text", "region", "text"]);
+		// items of the loop are not bound themselves
+		assert_eq!(template.bindings[0].sources, vec![Source::Request(1)]);
+		// nodes of branches and states are part of their region
+		assert_eq!(template.bindings[2].parent, Some(1));
+		assert_eq!(template.bindings[3].parent, None);
+		assert_eq!(template.bindings[4].sources, vec![Source::Request(2)]);
+		assert_eq!(template.bindings[5].parent, Some(4));
+	}
+
+	#[test]
+	fn skip_comments_and_scripts() {
+		let template = bind("{GET user FROM '/api/user'}<!-- <a href=\"{user.id}\"> -->{user.id}<!-- --- -->\
+			<script>let tag = '<b title=\"' + {user.name} + '\">';</script><style>p {'{'} content: '{user.name}' }</STYLE>\
+			<div>{IF user.admin}<p>admin</p>{END}</div><script type=\"module\">{IF user.admin}x{END}</script>{user.name}");
+
+		// values inside of comments, scripts and styles are not nodes of their own
+		assert_eq!(kinds(&template), vec!["text", "region", "text"]);
+	}
+
+	#[test]
+	fn anchor_bound_attributes() {
+		let template = bind("{GET user FROM '/api/user'}<a class=\"link\" href=\"/users/{user.id}\" title={user.name}>{user.name}</a>\
+			<img src=\"{user.avatar}\"/><p class=\"static\">x</p>");
+		let markup: String = template.operations.iter().filter_map(|operation| match operation {
+			TemplateOperation::Print(print) => match &print.content {
+				Data::Value(text) => Some(text.as_str()),
+				_ => None
+			},
+			_ => None
+		}).collect();
+
+		assert_eq!(markup, "<a class=\"link\" href=\"/users/\" title= data-ws-anchor=\"index.ws:0 index.ws:1\"></a>\
+			<img src=\"\" data-ws-anchor=\"index.ws:3\"/><p class=\"static\">x</p>");
+	}
+}
@@ -0,0 +1,471 @@
+use std::collections::{HashMap, LinkedList};
+
+use crate::{compiler::{CompilationError, CompilationState, model::{Comparison, CoreModel, Data, EventAction, Expression, FetchOperation, Filter, Operand, PathKey, RequestBody, SchemaReference, TemplateOperation, VariableReference}, symbols::is_similar}, json::Json, schema::{PathError, Property, Schema}, strings::stringview::StringView};
+
+/// # Checks paths into fetched data against the shape of the data
+/// Requests describe their response using `SCHEMA 'path'` (a JSON Schema) or `SAMPLE 'path'` (sample data), e.g.
+/// ```text
+/// {GET user FROM '/api/user' SCHEMA './user.schema.json'}<p>{user.name}</p>
+/// ```
+/// Every path into the response must exist, e.g. `user.nmae` is reported at `nmae`, and only arrays
+/// can be looped over. Variables set from or looping over the response are checked as well.
+/// Data of requests without schema, parameters and streams may have any shape.
+///
+/// Runs after the linker, so inheritance has been resolved.
+pub struct CheckerComponent<'f> {
+	state: CompilationState,
+	/// contents of the files referenced by requests, by id
+	files: HashMap<String, &'f str>,
+	/// schemas of the variables by scope, innermost last
+	scopes: Vec<HashMap<String, Schema>>
+}
+
+impl<'f> CheckerComponent<'f> {
+	/// * `files` - id (path) and content of the schemas and samples referenced by requests
+	pub fn new(files: LinkedList<(String, &'f str)>) -> CheckerComponent<'f> {
+		Self {
+			state: CompilationState::new(),
+			files: files.into_iter().collect(),
+			scopes: Vec::new()
+		}
+	}
+
+	/// Checking stops at the first error
+	pub fn check(&mut self, model: &CoreModel) {
+		for template in model.templates.iter() {
+			self.scopes = vec![HashMap::new()];
+			if let Err(err) = self.check_operations(&template.operations) {
+				self.state.error(err);
+				return;
+			}
+		}
+	}
+
+	pub fn finish(self) -> Result<(), CompilationError> {
+		self.state.finish(())
+	}
+
+	fn check_operations(&mut self, operations: &LinkedList<TemplateOperation>) -> Result<(), CompilationError> {
+		for operation in operations {
+			self.check_operation(operation)?;
+		}
+		Ok(())
+	}
+
+	/// Operations of blocks, branches and loops define variables in a scope of their own
+	fn check_scoped(&mut self, operations: &LinkedList<TemplateOperation>, variables: HashMap<String, Schema>) -> Result<(), CompilationError> {
+		self.scopes.push(variables);
+		let result = self.check_operations(operations);
+		self.scopes.pop();
+		result
+	}
+
+	fn check_operation(&mut self, operation: &TemplateOperation) -> Result<(), CompilationError> {
+		match operation {
+			TemplateOperation::Print(print) => { self.schema(&print.content)?; },
+			TemplateOperation::Insert(insert) => { self.schema(&insert.content)?; },
+			TemplateOperation::Set(set) => {
+				let schema = self.expression_schema(&set.value)?;
+				self.define(&set.target.id, schema);
+			},
+			TemplateOperation::If(conditional) => {
+				self.expression_schema(&conditional.condition)?;
+				self.check_scoped(&conditional.operations, HashMap::new())?;
+				self.check_scoped(&conditional.alternative, HashMap::new())?;
+			},
+			TemplateOperation::Loop(repetition) => {
+				let items = self.schema(&repetition.items)?;
+				let item = match items.items() {
+					Some(item) => item,
+					None => return Err(CheckerComponent::not_an_array(&repetition.items, &items))
+				};
+				self.check_scoped(&repetition.operations, HashMap::from([(repetition.item.id.clone(), item)]))?;
+			},
+			TemplateOperation::Block(block) => self.check_scoped(&block.operations, HashMap::new())?,
+			TemplateOperation::Call(call) => {
+				for param in call.params.iter() {
+					self.schema(&param.value)?;
+				}
+				for slot in call.slots.iter() {
+					self.check_scoped(&slot.operations, HashMap::new())?;
+				}
+			},
+			TemplateOperation::Fetch(fetch) => self.check_fetch(fetch)?,
+			TemplateOperation::Subscribe(subscribe) => self.define(&subscribe.target.id, Schema::Any),
+			TemplateOperation::On(listener) => match &listener.action {
+				EventAction::Call { argument: Some(argument), .. } => { self.schema(argument)?; },
+				EventAction::Request(fetch) => self.check_fetch(fetch)?,
+				EventAction::Call { argument: None, .. } => ()
+			},
+			TemplateOperation::Validate(validate) => if let Some(error) = &validate.error {
+				self.define(&error.id, Schema::union(vec![Schema::String, Schema::Null]));
+			},
+			TemplateOperation::Bind(_)|TemplateOperation::Route(_)|TemplateOperation::Slot(_) => ()
+		}
+		Ok(())
+	}
+
+	fn check_fetch(&mut self, fetch: &FetchOperation) -> Result<(), CompilationError> {
+		self.schema(&fetch.from)?;
+		for param in fetch.query.iter() {
+			self.schema(&param.value)?;
+		}
+		for header in fetch.headers.iter() {
+			self.schema(&header.value)?;
+		}
+		if let Some(RequestBody::Data(data)) = &fetch.body {
+			self.schema(data)?;
+		}
+
+		// the response is bound in the current scope, like the parser defines the target
+		if let Some(target) = &fetch.target {
+			let schema = match &fetch.schema {
+				Some(reference) => load_schema(&self.files, reference)?,
+				None => Schema::Any
+			};
+			self.define(&target.id, schema);
+		}
+		self.check_operations(&fetch.operations)?;
+		if let Some(loading) = &fetch.loading {
+			self.check_scoped(loading, HashMap::new())?;
+		}
+		if let Some(empty) = &fetch.empty {
+			self.check_scoped(empty, HashMap::new())?;
+		}
+		if let Some(error) = &fetch.error {
+			let schema = Schema::Object { properties: vec![
+				Property { name: "status".to_string(), schema: Schema::Integer, required: true },
+				Property { name: "message".to_string(), schema: Schema::String, required: true }
+			], closed: true };
+			self.check_scoped(&error.operations, HashMap::from([(error.variable.id.clone(), schema)]))?;
+		}
+		Ok(())
+	}
+
+	fn define(&mut self, id: &str, schema: Schema) {
+		self.scopes.last_mut().unwrap().insert(id.to_string(), schema);
+	}
+
+	/// Comparisons are booleans, their sides are checked by `check_comparison`
+	fn expression_schema(&self, expression: &Expression) -> Result<Schema, CompilationError> {
+		let schema = self.sum_schema(&expression.operands)?;
+		match &expression.comparison {
+			Some((comparison, operands)) => {
+				let other = self.sum_schema(operands)?;
+				CheckerComponent::check_comparison(*comparison, (&expression.operands, &schema), (operands, &other))?;
+				Ok(Schema::Boolean)
+			},
+			None => Ok(schema)
+		}
+	}
+
+	/// Values joined by `+` are added if all of them are numbers, otherwise they are text.
+	/// Filters compute text or (`length`) a number.
+	fn sum_schema(&self, operands: &LinkedList<Operand>) -> Result<Schema, CompilationError> {
+		let mut schemas = Vec::new();
+		for operand in operands.iter() {
+			let schema = self.schema(&operand.data)?;
+			schemas.push(match operand.filters.back() {
+				Some(Filter::Length) => Schema::Integer,
+				Some(_) => Schema::String,
+				None => schema
+			});
+		}
+		Ok(match schemas.len() {
+			1 => schemas.pop().unwrap(),
+			_ if schemas.iter().all(|schema| matches!(schema, Schema::Number|Schema::Integer)) => Schema::Number,
+			_ if schemas.iter().all(|schema| matches!(schema, Schema::Number|Schema::Integer|Schema::Any)) => Schema::Any,
+			_ => Schema::String
+		})
+	}
+
+	/// # Checks that the sides of a comparison can be compared
+	/// Orders only hold between numbers. Equality holds between values of the same kind (see `compared_kinds`),
+	/// arrays and objects are never equal. Sides of unknown shape, e.g. literals and parameters, may be compared with anything.
+	fn check_comparison(comparison: Comparison, left: (&LinkedList<Operand>, &Schema), right: (&LinkedList<Operand>, &Schema)) -> Result<(), CompilationError> {
+		for (side, (operands, schema)) in [("left", left), ("right", right)] {
+			if comparison.is_order() && !may_be_number(schema) {
+				return Err(CheckerComponent::invalid_comparison(operands,
+					format!("{} side is {} {}, only numbers can be ordered", side, article(&schema.kind()), schema.kind()),
+					"compare numbers, e.g. the length of text or arrays using '| length'"));
+			}
+			if compared_kinds(schema).is_empty() {
+				return Err(CheckerComponent::invalid_comparison(operands,
+					format!("{} side is {} {}, only numbers, text, booleans and null can be compared", side, article(&schema.kind()), schema.kind()),
+					"compare a field of the data or its length using '| length'"));
+			}
+		}
+		let kinds = compared_kinds(left.1);
+		if !compared_kinds(right.1).iter().any(|kind| kinds.contains(kind)) {
+			return Err(CheckerComponent::invalid_comparison(right.0,
+				format!("right side is {} {} and never equals the left side, which is {} {}", article(&right.1.kind()), right.1.kind(), article(&left.1.kind()), left.1.kind()),
+				"compare values of the same kind"));
+		}
+		Ok(())
+	}
+
+	/// # Follows the path of the data into the schema of its variable
+	/// Literals are text, variables without schema (e.g. parameters) may have any shape.
+	/// Values which might be null are only entered with `?.`, which makes the rest of the path null as well.
+	fn schema(&self, data: &Data) -> Result<Schema, CompilationError> {
+		let (variable, view) = match data {
+			Data::Variable(variable, view) => (variable, view),
+			_ => return Ok(Schema::Any)
+		};
+		let mut schema = match self.scopes.iter().rev().find_map(|scope| scope.get(&variable.id)) {
+			Some(schema) => schema.clone(),
+			None => return Ok(Schema::Any)
+		};
+
+		let spans = view.as_ref().map(|view| VariableReference::spans(view.cut())).unwrap_or_default();
+		for (index, segment) in variable.path.iter().enumerate() {
+			let view = view.as_ref().zip(spans.get(index)).and_then(|(view, span)| view.substring(span.start, span.end - 1).ok());
+			let parent = VariableReference { id: variable.id.clone(), path: variable.path[..index].to_vec() }.to_string();
+			let nullable = schema.is_nullable();
+			if nullable && !segment.optional {
+				return Err(CheckerComponent::nullable_path(variable, index, view));
+			}
+			if schema == Schema::Null {
+				return Ok(Schema::Null);
+			}
+			schema = match &segment.key {
+				PathKey::Property(name) => match schema.field(name) {
+					Ok(field) => field,
+					Err(reason) => return Err(CheckerComponent::invalid_path(&parent, name, reason, view))
+				},
+				PathKey::Index(index) => match schema.items() {
+					Some(items) => items,
+					None => return Err(
+						CompilationError {
+							name: "ERR_NOT_AN_ARRAY".to_string(),
+							desc: "cannot check data path".to_string(),
+							cause: format!("'{}' is {} {} and has no item {}", parent, article(&schema.kind()), schema.kind(), index),
+							suggestion: format!("remove '[{}]' from the path", index),
+							position: None,
+							view
+						}
+					)
+				}
+			};
+			if nullable {
+				schema = Schema::union(vec![schema, Schema::Null]);
+			}
+		}
+		Ok(schema)
+	}
+
+	fn nullable_path(variable: &VariableReference, index: usize, view: Option<StringView>) -> CompilationError {
+		let parent = VariableReference { id: variable.id.clone(), path: variable.path[..index].to_vec() };
+		let mut optional = VariableReference { id: variable.id.clone(), path: variable.path[..=index].to_vec() };
+		optional.path[index].optional = true;
+		CompilationError {
+			name: "ERR_NULLABLE_PATH".to_string(),
+			desc: "cannot check data path".to_string(),
+			cause: format!("'{}' might be null, its segments cannot be read without '?.'", parent),
+			suggestion: format!("write '{}' to get null if '{}' is null", optional, parent),
+			position: None,
+			view
+		}
+	}
+
+	fn invalid_path(parent: &str, segment: &str, reason: PathError, view: Option<StringView>) -> CompilationError {
+		let (name, cause, suggestion) = match reason {
+			PathError::UnknownField(fields) => {
+				let similar: Vec<&str> = fields.iter().map(String::as_str).filter(|field| is_similar(segment, field)).collect();
+				let suggestion = if !similar.is_empty() {
+					format!("did you mean '{}'?", similar.join("' or '"))
+				} else if fields.is_empty() {
+					format!("'{}' is an empty object, add '{}' to its schema", parent, segment)
+				} else {
+					format!("use one of the fields: {}", fields.join(", "))
+				};
+				("ERR_UNKNOWN_FIELD", format!("'{}' has no field '{}'", parent, segment), suggestion)
+			},
+			PathError::NoFields(kind) => ("ERR_NOT_AN_OBJECT", format!("'{}' is {} {} and has no field '{}'", parent, article(&kind), kind, segment), format!("remove '.{}' from the path", segment))
+		};
+		CompilationError {
+			name: name.to_string(),
+			desc: "cannot check data path".to_string(),
+			cause,
+			suggestion,
+			position: None,
+			view
+		}
+	}
+
+	/// Marks the first variable of the side, literals have no view
+	fn invalid_comparison(operands: &LinkedList<Operand>, cause: String, suggestion: &str) -> CompilationError {
+		CompilationError {
+			name: "ERR_INVALID_COMPARISON".to_string(),
+			desc: "cannot check condition".to_string(),
+			cause,
+			suggestion: suggestion.to_string(),
+			position: None,
+			view: operands.iter().find_map(|operand| match &operand.data {
+				Data::Variable(_, view) => view.clone(),
+				_ => None
+			})
+		}
+	}
+
+	fn not_an_array(items: &Data, schema: &Schema) -> CompilationError {
+		let (path, view) = match items {
+			Data::Variable(variable, view) => (variable.to_string(), view.clone()),
+			_ => (String::new(), None)
+		};
+		CompilationError {
+			name: "ERR_NOT_AN_ARRAY".to_string(),
+			desc: "cannot check loop".to_string(),
+			cause: format!("'{}' is {} {}, only arrays can be looped over", path, article(&schema.kind()), schema.kind()),
+			suggestion: "loop over an array of the data or use '{IF ...}' instead".to_string(),
+			position: None,
+			view
+		}
+	}
+}
+
+/// Reads the schema of a response from its file, `files` holds the contents of the files by id
+pub fn load_schema(files: &HashMap<String, &str>, reference: &SchemaReference) -> Result<Schema, CompilationError> {
+	let kind = if reference.sample { "sample" } else { "schema" };
+	let content = match files.get(&reference.id) {
+		Some(content) => content,
+		None => return Err(
+			CompilationError {
+				name: "ERR_MISSING_SCHEMA".to_string(),
+				desc: "cannot read schema".to_string(),
+				cause: format!("{} '{}' has not been found", kind, reference.id),
+				suggestion: format!("check the path or add '{}' to the checked files", reference.id),
+				position: None,
+				view: Some(reference.view.clone())
+			}
+		)
+	};
+	let schema = Json::parse(content).and_then(|json| match reference.sample {
+		true => Ok(Schema::from_sample(&json)),
+		false => Schema::parse(&json)
+	});
+	schema.map_err(|cause| CompilationError {
+		name: "ERR_INVALID_SCHEMA".to_string(),
+		desc: "cannot read schema".to_string(),
+		cause: format!("{} '{}' is invalid: {}", kind, reference.id, cause),
+		suggestion: format!("fix the {} in '{}'", kind, reference.id),
+		position: None,
+		view: Some(reference.view.clone())
+	})
+}
+
+/// Whether the data might be a number, data of unknown shape might be anything
+fn may_be_number(schema: &Schema) -> bool {
+	match schema {
+		Schema::Any|Schema::Number|Schema::Integer => true,
+		Schema::Union(members) => members.iter().any(may_be_number),
+		_ => false
+	}
+}
+
+/// # Kinds of values a comparison tells apart, which the data might be
+/// Numbers and text are one kind, as text might hold a number, booleans and null are the others.
+/// Arrays and objects are none of them.
+fn compared_kinds(schema: &Schema) -> Vec<&'static str> {
+	match schema {
+		Schema::Any => vec!["text", "boolean", "null"],
+		Schema::Number|Schema::Integer|Schema::String => vec!["text"],
+		Schema::Boolean => vec!["boolean"],
+		Schema::Null => vec!["null"],
+		Schema::Union(members) => members.iter().flat_map(compared_kinds).collect(),
+		_ => Vec::new()
+	}
+}
+
+/// Indefinite article of a kind of data, e.g. `an object`
+fn article(kind: &str) -> &'static str {
+	if kind.starts_with(['a', 'e', 'i', 'o', 'u']) { "an" } else { "a" }
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::LinkedList;
+
+	use crate::compiler::{build_model, CompilationError};
+	use super::CheckerComponent;
+
+	const USER: &str = r#"{ "type": "object", "additionalProperties": false, "properties": {
+		"name": { "type": "string" },
+		"address": { "type": ["object", "null"], "properties": { "city": { "type": "string" } }, "additionalProperties": false },
+		"orders": { "type": "array", "items": { "type": "object", "properties": { "id": { "type": "integer" } }, "additionalProperties": false } }
+	} }"#;
+
+	/// Name and marked part of the error
+	fn check(source: &'static str) -> Result<(), (String, String)> {
+		let mut sources = LinkedList::new();
+		sources.push_back(("pages/index.ws".to_string(), source));
+		let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+
+		let mut files = LinkedList::new();
+		files.push_back(("pages/user.schema.json".to_string(), USER));
+		files.push_back(("pages/user.json".to_string(), r#"{ "name": "Ada", "tags": ["x"] }"#));
+		files.push_back(("pages/broken.json".to_string(), "{ \"type\": 1 }"));
+		let mut checker = CheckerComponent::new(files);
+		checker.check(&model);
+		checker.finish().map_err(|err: CompilationError| (err.name, err.view.map_or(String::new(), |view| view.cut().to_string())))
+	}
+
+	#[test]
+	fn check_paths() {
+		assert!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}<p>{user.name}, {user?.address?.city}</p>").is_ok());
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}<p>{user.nmae}</p>").err().unwrap(), ("ERR_UNKNOWN_FIELD".to_string(), "nmae".to_string()));
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{user?.address?.zip}").err().unwrap(), ("ERR_UNKNOWN_FIELD".to_string(), "zip".to_string()));
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{user.name.first}").err().unwrap(), ("ERR_NOT_AN_OBJECT".to_string(), "first".to_string()));
+
+		// variables set from the response, samples and requests depending on the response
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{SET home = user.address}{home?.town}").err().unwrap(), ("ERR_UNKNOWN_FIELD".to_string(), "town".to_string()));
+		assert_eq!(check("{GET user FROM '/api/user' SAMPLE './user.json'}{GET orders FROM '/api/orders' PARAMS name=user.nam}").err().unwrap(), ("ERR_UNKNOWN_FIELD".to_string(), "nam".to_string()));
+		// data without schema may have any shape
+		assert!(check("{GET user FROM '/api/user'}{user.nmae.x}").is_ok());
+	}
+
+	#[test]
+	fn check_indices_and_keys() {
+		assert!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{user.orders[0].id}{user['name']}{user?.address?.city}").is_ok());
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{user.orders[1]['total']}").err().unwrap(), ("ERR_UNKNOWN_FIELD".to_string(), "['total']".to_string()));
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{user?.address?.[0]}").err().unwrap(), ("ERR_NOT_AN_ARRAY".to_string(), "[0]".to_string()));
+		// properties named like indices are no items
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{user.orders.0}").err().unwrap(), ("ERR_NOT_AN_OBJECT".to_string(), "0".to_string()));
+	}
+
+	#[test]
+	fn check_nullable_paths() {
+		assert!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{user.address}{user.address?.city}").is_ok());
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{user.address.city}").err().unwrap(), ("ERR_NULLABLE_PATH".to_string(), "city".to_string()));
+		// the rest of an optional path might be null as well
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{SET city = user.address?.city}{city.length}").err().unwrap(), ("ERR_NULLABLE_PATH".to_string(), "length".to_string()));
+	}
+
+	#[test]
+	fn check_loops() {
+		assert!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{FOR order IN user.orders}{order.id}{END}{user.orders[0].id}").is_ok());
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{FOR order IN user.orders}{order.total}{END}").err().unwrap(), ("ERR_UNKNOWN_FIELD".to_string(), "total".to_string()));
+		assert_eq!(check("{GET user FROM '/api/user' SAMPLE './user.json'}{FOR c IN user.name}{c}{END}").err().unwrap(), ("ERR_NOT_AN_ARRAY".to_string(), "user.name".to_string()));
+		// items of loops are not visible after them
+		assert!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{FOR user IN user.orders}{user.id}{END}{user.name}").is_ok());
+	}
+
+	#[test]
+	fn check_comparisons() {
+		assert!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{IF user.orders | length > 0}{END}{IF user.orders[0].id >= 3}{END}{IF user.name == 'Ada'}{END}{IF user.orders[0].id == user.name}{END}").is_ok());
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{IF user.name > 3}{END}").err().unwrap(), ("ERR_INVALID_COMPARISON".to_string(), "user.name".to_string()));
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{IF user.orders == 1}{END}").err().unwrap(), ("ERR_INVALID_COMPARISON".to_string(), "user.orders".to_string()));
+		// objects are never compared, so an address which might be null only equals null
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{IF user.address != user.name}{END}").err().unwrap(), ("ERR_INVALID_COMPARISON".to_string(), "user.name".to_string()));
+		// paths inside of comparisons are checked as well
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './user.schema.json'}{IF 1 < user.orders[0].total}{END}").err().unwrap(), ("ERR_UNKNOWN_FIELD".to_string(), "total".to_string()));
+	}
+
+	#[test]
+	fn check_files() {
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './other.json'}").err().unwrap(), ("ERR_MISSING_SCHEMA".to_string(), "SCHEMA".to_string()));
+		assert_eq!(check("{GET user FROM '/api/user' SCHEMA './broken.json'}").err().unwrap(), ("ERR_INVALID_SCHEMA".to_string(), "SCHEMA".to_string()));
+		assert!(check("{GET user FROM '/api/user'}...{ERROR e}{e.status}{END}").is_ok());
+		assert_eq!(check("{GET user FROM '/api/user'}...{ERROR e}{e.code}{END}").err().unwrap(), ("ERR_UNKNOWN_FIELD".to_string(), "code".to_string()));
+	}
+}
@@ -0,0 +1,552 @@
+use std::collections::LinkedList;
+
+use crate::{compiler::{CompilationError, CompilationState, model::{BlockOperation, CallOperation, CoreModel, Data, ExtendsDeclaration, RouteOperation, Template, TemplateOperation, VariableReference}}, strings::stringview::StringView};
+
+/// # Links templates of the core model
+/// * resolves inheritance: extending templates are merged with the template they extend
+/// * checks that every called template exists and is called with the parameters and slots it declares
+/// * checks that every routed template exists and its parameters are given by the path of the route
+/// * checks that templates do not render each other endlessly, i.e. through inserts and routes outside of conditions and loops
+pub struct LinkerComponent {
+	state: CompilationState
+}
+
+impl LinkerComponent {
+	pub fn new() -> LinkerComponent {
+		Self {
+			state: CompilationState::new()
+		}
+	}
+
+	pub fn is_ok(&self) -> bool {
+		self.state.is_ok()
+	}
+
+	/// # Resolves `{EXTENDS ...}` of all templates
+	/// Each extending template receives the operations of its extended template, in which
+	/// the blocks overridden by the extending template are replaced.
+	/// Extended templates are resolved before the templates extending them.
+	pub fn inherit(&mut self, model: &mut CoreModel) {
+		if let Err(err) = LinkerComponent::resolve_extends(model) {
+			self.state.error(err);
+		}
+	}
+
+	/// Linking stops at the first error
+	pub fn link(&mut self, model: &CoreModel) {
+		for template in model.templates.iter() {
+			if let Err(err) = LinkerComponent::link_template(model, template) {
+				self.state.error(err);
+				return;
+			}
+		}
+		let mut done = Vec::new();
+		for template in model.templates.iter() {
+			if let Err(err) = LinkerComponent::check_cycles(model, &template.id, &mut Vec::new(), &mut done) {
+				self.state.error(err);
+				return;
+			}
+		}
+	}
+
+	/// LinkerComponent is consumed and the linked model returned
+	pub fn finish<T>(self, model: T) -> Result<T, CompilationError> {
+		self.state.finish(model)
+	}
+
+	fn resolve_extends(model: &mut CoreModel) -> Result<(), CompilationError> {
+		let mut resolved: Vec<String> = model.templates.iter()
+			.filter(|template| template.extends.is_none())
+			.map(|template| template.id.clone())
+			.collect();
+
+		loop {
+			let next = model.templates.iter()
+				.find(|template| match &template.extends {
+					Some(parent) => !resolved.contains(&template.id) && resolved.contains(&parent.template),
+					None => false
+				})
+				.map(|template| template.id.clone());
+
+			match next {
+				Some(id) => {
+					LinkerComponent::merge_with_extended(model, &id)?;
+					resolved.push(id);
+				},
+				None => break
+			}
+		}
+
+		// remaining templates extend missing templates or each other
+		match model.templates.iter().find(|template| !resolved.contains(&template.id)) {
+			Some(template) => {
+				let ExtendsDeclaration { template: parent, view } = template.extends.clone().unwrap();
+				Err(if model.get_template_by_id(&parent).is_none() {
+					CompilationError {
+						name: "ERR_MISSING_TEMPLATE".to_string(),
+						desc: "cannot extend template".to_string(),
+						cause: format!("template '{}' extends '{}' which has not been compiled", template.id, parent),
+						suggestion: format!("check the path or add '{}' to the compiled sources", parent),
+						position: None,
+						view: Some(view)
+					}
+				} else {
+					CompilationError {
+						name: "ERR_CYCLIC_EXTENDS".to_string(),
+						desc: "cannot extend template".to_string(),
+						cause: format!("template '{}' extends '{}' which (indirectly) extends '{}' again", template.id, parent, template.id),
+						suggestion: "remove one {EXTENDS ...} of the cycle".to_string(),
+						position: None,
+						view: Some(view)
+					}
+				})
+			},
+			None => Ok(())
+		}
+	}
+
+	/// # Merges template with its (already resolved) extended template
+	/// * blocks overridden by the template must exist in the extended template
+	/// * variables used but not defined by the template must be visible where the extended template declares the block
+	fn merge_with_extended(model: &mut CoreModel, id: &str) -> Result<(), CompilationError> {
+		let template = model.get_template_by_id(id).unwrap();
+		let parent = model.get_template_by_id(&template.extends.as_ref().unwrap().template).unwrap();
+
+		let overrides: Vec<&BlockOperation> = template.operations.iter()
+			.filter_map(|operation| match operation {
+				TemplateOperation::Block(block) => Some(block),
+				_ => None
+			})
+			.collect();
+
+		let declared: Vec<&str> = TemplateOperation::walk(&parent.operations)
+			.filter_map(|operation| match operation {
+				TemplateOperation::Block(block) => Some(block.name.as_str()),
+				_ => None
+			})
+			.collect();
+		if let Some(block) = overrides.iter().find(|block| !declared.contains(&block.name.as_str())) {
+			return Err(
+				CompilationError {
+					name: "ERR_UNKNOWN_BLOCK".to_string(),
+					desc: "cannot override block".to_string(),
+					cause: format!("extended template '{}' does not declare block '{}'", parent.id, block.name),
+					suggestion: if declared.is_empty() {
+						format!("remove {{BLOCK {}}}, template '{}' does not have blocks", block.name, parent.id)
+					} else {
+						format!("override one of the declared blocks: {}", declared.join(", "))
+					},
+					position: None,
+					view: Some(block.view.clone())
+				}
+			);
+		}
+
+		let unresolved = template.unresolved.iter().find(|unresolved| {
+			let mut visible = Vec::new();
+			if let Some(block) = &unresolved.block {
+				LinkerComponent::block_scope(&parent.operations, block, &mut visible);
+			}
+			!parent.variables.contains(&unresolved.variable.id) && !visible.contains(&unresolved.variable.id.as_str())
+		});
+		if let Some(unresolved) = unresolved {
+			let mut err = parent.variables.undefined_variable(&unresolved.variable.id, &template.id);
+			err.view = Some(unresolved.view.clone());
+			return Err(err);
+		}
+
+		let operations = LinkerComponent::override_blocks(&parent.operations, &overrides);
+		let variables = parent.variables.clone();
+		let params = parent.params.clone();
+
+		let template = model.templates.iter_mut().find(|template| template.id == id).unwrap();
+		template.operations = operations;
+		for symbol in variables.iter() {
+			template.variables.insert(symbol.clone());
+		}
+		for param in params.into_iter().rev() {
+			if !template.params.iter().any(|p| p.id == param.id) {
+				template.params.push_front(param);
+			}
+		}
+		template.unresolved.clear();
+		Ok(())
+	}
+
+	/// # Collects the variables visible where the block of that name is declared, returns false if it is not declared
+	/// Loops, branches and states have scopes of their own like in the parser, so their variables (e.g. the item of a loop)
+	/// are only visible to the blocks declared inside of them.
+	fn block_scope<'a>(operations: &'a LinkedList<TemplateOperation>, name: &str, visible: &mut Vec<&'a str>) -> bool {
+		for operation in operations.iter() {
+			let scopes: Vec<(Option<&VariableReference>, &LinkedList<TemplateOperation>)> = match operation {
+				TemplateOperation::Block(block) if block.name == name => return true,
+				TemplateOperation::Set(set) => {
+					visible.push(&set.target.id);
+					Vec::new()
+				},
+				TemplateOperation::Subscribe(subscribe) => {
+					visible.push(&subscribe.target.id);
+					Vec::new()
+				},
+				TemplateOperation::Validate(validate) => {
+					visible.extend(validate.error.iter().map(|error| error.id.as_str()));
+					Vec::new()
+				},
+				TemplateOperation::Fetch(fetch) => {
+					visible.extend(fetch.target.iter().map(|target| target.id.as_str()));
+					// the content belongs to the surrounding scope
+					if LinkerComponent::block_scope(&fetch.operations, name, visible) {
+						return true;
+					}
+					fetch.loading.iter().chain(fetch.empty.iter()).map(|operations| (None, operations))
+						.chain(fetch.error.iter().map(|error| (Some(&error.variable), &error.operations)))
+						.collect()
+				},
+				TemplateOperation::Loop(repetition) => vec![(Some(&repetition.item), &repetition.operations)],
+				_ => operation.nested_operations().into_iter().map(|nested| (None, nested)).collect()
+			};
+			for (variable, nested) in scopes {
+				let outer = visible.len();
+				visible.extend(variable.map(|variable| variable.id.as_str()));
+				if LinkerComponent::block_scope(nested, name, visible) {
+					return true;
+				}
+				visible.truncate(outer);
+			}
+		}
+		false
+	}
+
+	/// Copies operations, replacing the contents of overridden blocks
+	fn override_blocks(operations: &LinkedList<TemplateOperation>, overrides: &[&BlockOperation]) -> LinkedList<TemplateOperation> {
+		operations.iter().map(|operation| {
+			if let TemplateOperation::Block(block) = operation {
+				if let Some(replacement) = overrides.iter().find(|o| o.name == block.name) {
+					return TemplateOperation::Block((*replacement).clone());
+				}
+			}
+
+			let mut operation = operation.clone();
+			for nested in operation.nested_operations_mut() {
+				*nested = LinkerComponent::override_blocks(nested, overrides);
+			}
+			operation
+		}).collect()
+	}
+
+	fn link_template(model: &CoreModel, template: &Template) -> Result<(), CompilationError> {
+		for operation in TemplateOperation::walk(&template.operations) {
+			match operation {
+				TemplateOperation::Call(call) => LinkerComponent::link_call(model, call)?,
+				TemplateOperation::Route(route) => LinkerComponent::link_route(model, route)?,
+				_ => ()
+			}
+		}
+		Ok(())
+	}
+
+	/// # Checks that the template does not render itself again
+	/// * `path` - templates rendering the template, outermost first
+	/// * `done` - templates checked already
+	///
+	/// Templates rendered inside of conditions and loops are not followed, e.g. recursive trees stop at their leaves.
+	fn check_cycles<'m>(model: &'m CoreModel, id: &'m str, path: &mut Vec<&'m str>, done: &mut Vec<&'m str>) -> Result<(), CompilationError> {
+		let template = match model.get_template_by_id(id) {
+			Some(template) if !done.contains(&id) => template,
+			_ => return Ok(())
+		};
+		path.push(id);
+		for (target, view) in LinkerComponent::rendered(&template.operations) {
+			if let Some(start) = path.iter().position(|id| *id == target) {
+				let cycle: Vec<&str> = path[start..].iter().copied().chain([target]).collect();
+				return Err(
+					CompilationError {
+						name: "ERR_CYCLIC_TEMPLATE".to_string(),
+						desc: "cannot render template".to_string(),
+						cause: format!("templates render each other endlessly: {}", cycle.join(" -> ")),
+						suggestion: "render one of the templates inside of {IF ...} or remove it from the cycle".to_string(),
+						position: None,
+						view: Some(view.clone())
+					}
+				);
+			}
+			LinkerComponent::check_cycles(model, target, path, done)?;
+		}
+		path.pop();
+		done.push(id);
+		Ok(())
+	}
+
+	/// Templates inserted or routed to, along with the instruction, except those inside of conditions and loops
+	fn rendered(operations: &LinkedList<TemplateOperation>) -> Vec<(&str, &StringView)> {
+		let mut rendered = Vec::new();
+		let mut walk = TemplateOperation::walk(operations);
+		while let Some(operation) = walk.next() {
+			match operation {
+				TemplateOperation::If(_)|TemplateOperation::Loop(_) => walk.skip_nested(),
+				TemplateOperation::Call(CallOperation { template: Data::Value(id), view, .. }) => rendered.push((id.as_str(), view)),
+				TemplateOperation::Route(route) => rendered.push((route.template.as_str(), &route.view)),
+				_ => ()
+			}
+		}
+		rendered
+	}
+
+	/// # Checks route against the declaration of the routed template
+	/// * routed template must exist
+	/// * all declared parameters must be parameters of the path, further parameters of the path are ignored
+	fn link_route(model: &CoreModel, route: &RouteOperation) -> Result<(), CompilationError> {
+		let routed = match model.get_template_by_id(&route.template) {
+			Some(template) => template,
+			None => return Err(
+				CompilationError {
+					name: "ERR_MISSING_TEMPLATE".to_string(),
+					desc: "cannot route to template".to_string(),
+					cause: format!("template '{}' is imported but has not been compiled", route.template),
+					suggestion: format!("check the import path or add '{}' to the compiled sources", route.template),
+					position: None,
+					view: Some(route.view.clone())
+				}
+			)
+		};
+
+		let params = route.params();
+		match routed.params.iter().find(|param| !params.contains(&param.id.as_str())) {
+			Some(param) => Err(
+				CompilationError {
+					name: "ERR_MISSING_ROUTE_PARAM".to_string(),
+					desc: "cannot route to template".to_string(),
+					cause: match &route.path {
+						Some(path) => format!("template '{}' expects parameter '{}' but path '{}' does not contain ':{}'", route.template, param.id, path, param.id),
+						None => format!("template '{}' expects parameter '{}' but fallbacks do not have parameters", route.template, param.id)
+					},
+					suggestion: format!("add the segment ':{}' to the path or remove the parameter from the template", param.id),
+					position: None,
+					view: Some(route.view.clone())
+				}
+			),
+			None => Ok(())
+		}
+	}
+
+	/// # Checks call against the declaration of the called template
+	/// * called template must exist
+	/// * all passed parameters must be declared
+	/// * all declared parameters must be passed
+	/// * all filled slots must be declared
+	fn link_call(model: &CoreModel, call: &CallOperation) -> Result<(), CompilationError> {
+		let id = match &call.template {
+			Data::Value(id) => id,
+			Data::Variable(variable, _) => return Err(
+				CompilationError {
+					name: "ERR_UNRESOLVED_TEMPLATE".to_string(),
+					desc: "cannot insert template".to_string(),
+					cause: format!("'{}' does not refer to an imported template", variable),
+					suggestion: format!("import the template using {{IMPORT '...' AS {}}}", variable.id),
+					position: None,
+					view: Some(call.view.clone())
+				}
+			)
+		};
+
+		let callee = match model.get_template_by_id(id) {
+			Some(template) => template,
+			None => return Err(
+				CompilationError {
+					name: "ERR_MISSING_TEMPLATE".to_string(),
+					desc: "cannot insert template".to_string(),
+					cause: format!("template '{}' is imported but has not been compiled", id),
+					suggestion: format!("check the import path or add '{}' to the compiled sources", id),
+					position: None,
+					view: Some(call.view.clone())
+				}
+			)
+		};
+
+		let declared: Vec<&str> = callee.params.iter().map(|param| param.id.as_str()).collect();
+		for param in call.params.iter() {
+			if !declared.contains(&param.name.as_str()) {
+				return Err(
+					CompilationError {
+						name: "ERR_UNKNOWN_PARAM".to_string(),
+						desc: "cannot pass template parameter".to_string(),
+						cause: format!("template '{}' does not declare parameter '{}'", id, param.name),
+						suggestion: if declared.is_empty() {
+							format!("remove '{}', template '{}' does not take parameters", param.name, id)
+						} else {
+							format!("use one of the declared parameters: {}", declared.join(", "))
+						},
+						position: None,
+						view: Some(call.view.clone())
+					}
+				);
+			}
+		}
+
+		if call.params.len() != declared.len() {
+			let missing: Vec<&str> = declared.iter()
+				.filter(|name| !call.params.iter().any(|param| param.name == **name))
+				.copied()
+				.collect();
+			return Err(
+				CompilationError {
+					name: "ERR_PARAM_ARITY".to_string(),
+					desc: "cannot insert template".to_string(),
+					cause: format!("template '{}' expects {} parameter(s) but {} were passed", id, declared.len(), call.params.len()),
+					suggestion: format!("pass the missing parameter(s): {}", missing.join(", ")),
+					position: None,
+					view: Some(call.view.clone())
+				}
+			);
+		}
+
+		let declared = callee.slots();
+		for slot in call.slots.iter() {
+			if !declared.contains(&slot.name.as_str()) {
+				return Err(
+					CompilationError {
+						name: "ERR_UNKNOWN_SLOT".to_string(),
+						desc: "cannot fill slot".to_string(),
+						cause: format!("template '{}' does not declare slot '{}'", id, slot.name),
+						suggestion: if declared.is_empty() {
+							format!("remove {{FILL {}}}, template '{}' does not have slots", slot.name, id)
+						} else {
+							format!("use one of the declared slots: {}", declared.join(", "))
+						},
+						position: None,
+						view: Some(call.view.clone())
+					}
+				);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::LinkedList;
+	use crate::{compiler::{build_model, model::{CallOperation, CoreModel, Data, Template, TemplateOperation, VariableReference}}, strings::stringview::StringView};
+	use super::LinkerComponent;
+
+	/// Builds model of `index.ws` importing `card.ws`
+	fn link(index: &'static str, card: &'static str) -> Result<(), String> {
+		let mut sources = LinkedList::new();
+		sources.push_back(("index.ws".to_string(), index));
+		sources.push_back(("card.ws".to_string(), card));
+		build_model(sources, &mut LinkedList::new()).map(|_| ()).map_err(|err| err.name)
+	}
+
+	#[test]
+	fn link_call_params() {
+		let card = "{PARAMS title, item}<h2>{title}</h2>{item.name}";
+
+		assert!(link("{IMPORT './card.ws' AS card}{INSERT card PARAMS item='x', title='y'}", card).is_ok());
+		assert_eq!(link("{IMPORT './card.ws' AS card}{INSERT card PARAMS title='y'}", card).err().unwrap(), "ERR_PARAM_ARITY");
+		assert_eq!(link("{IMPORT './card.ws' AS card}{INSERT card PARAMS title='y', color='red'}", card).err().unwrap(), "ERR_UNKNOWN_PARAM");
+		assert_eq!(link("{IMPORT './card.ws' AS card}{INSERT card PARAMS title='y'}", "<p>card</p>").err().unwrap(), "ERR_UNKNOWN_PARAM");
+		assert_eq!(link("{IMPORT './other.ws' AS card}{INSERT card}", card).err().unwrap(), "ERR_MISSING_TEMPLATE");
+	}
+
+	#[test]
+	fn link_unresolved_call() {
+		let view = StringView::new("{INSERT card}");
+		let mut template = Template::new("index.ws".to_string());
+		template.operations.push_back(TemplateOperation::Call(CallOperation {
+			template: Data::Variable(VariableReference::new("card"), None),
+			params: LinkedList::new(),
+			slots: LinkedList::new(),
+			view: view.clone(),
+			binding: None
+		}));
+		let mut model = CoreModel::new();
+		model.templates.push_back(template);
+
+		let mut linker = LinkerComponent::new();
+		linker.link(&model);
+		let err = linker.finish(()).err().unwrap();
+		assert_eq!(err.name, "ERR_UNRESOLVED_TEMPLATE");
+		assert!(err.view == Some(view));
+	}
+
+	#[test]
+	fn link_call_slots() {
+		let layout = "<header>{SLOT header}</header><main>{SLOT content}</main>";
+
+		assert!(link("{IMPORT './card.ws' AS layout}{INSERT layout}{FILL content}<p>x</p>{END}{END}", layout).is_ok());
+		assert_eq!(link("{IMPORT './card.ws' AS layout}{INSERT layout}{FILL footer}<p>x</p>{END}{END}", layout).err().unwrap(), "ERR_UNKNOWN_SLOT");
+		// calls inside of slots are linked as well
+		assert_eq!(link("{IMPORT './card.ws' AS layout}{INSERT layout}{FILL content}{INSERT layout PARAMS a='1'}{END}{END}", layout).err().unwrap(), "ERR_UNKNOWN_PARAM");
+	}
+
+	#[test]
+	fn link_routes() {
+		let user = "{PARAMS id}<h1>User {id}</h1>";
+
+		assert!(link("{IMPORT './card.ws' AS user}{ROUTE '/users/:id/:tab' USE user}", user).is_ok());
+		assert_eq!(link("{IMPORT './card.ws' AS user}{ROUTE '/users/:name' USE user}", user).err().unwrap(), "ERR_MISSING_ROUTE_PARAM");
+		assert_eq!(link("{IMPORT './card.ws' AS user}{ROUTE FALLBACK USE user}", user).err().unwrap(), "ERR_MISSING_ROUTE_PARAM");
+		assert_eq!(link("{IMPORT './other.ws' AS user}{ROUTE '/' USE user}", user).err().unwrap(), "ERR_MISSING_TEMPLATE");
+	}
+
+	#[test]
+	fn link_cycles() {
+		assert_eq!(link("{IMPORT './index.ws' AS self}{ROUTE FALLBACK USE self}", "").err().unwrap(), "ERR_CYCLIC_TEMPLATE");
+		assert_eq!(link("{IMPORT './card.ws' AS card}<main>{INSERT card}</main>", "{IMPORT './index.ws' AS page}{ROUTE '/' USE page}").err().unwrap(), "ERR_CYCLIC_TEMPLATE");
+		// recursion ends once the condition fails
+		assert!(link("{IMPORT './card.ws' AS tree}{INSERT tree PARAMS node='x'}", "{IMPORT './card.ws' AS tree}{PARAMS node}{IF node.child}{INSERT tree PARAMS node=node.child}{END}").is_ok());
+		assert!(link("{IMPORT './card.ws' AS card}{INSERT card}{INSERT card}", "<p>card</p>").is_ok());
+	}
+
+	#[test]
+	fn inherit_blocks() {
+		let base = "<title>{BLOCK title}Site{END}</title><main>{BLOCK content}<p>empty</p>{END}</main>";
+		let mut sources = LinkedList::new();
+		sources.push_back(("page.ws".to_string(), "{EXTENDS './base.ws'}\n{BLOCK content}{GET user FROM '/api'}<p>{user.name}</p>{END}"));
+		sources.push_back(("base.ws".to_string(), base));
+		let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+
+		let page = model.get_template_by_id("page.ws").unwrap();
+		let blocks: Vec<(&str, usize)> = page.operations.iter().filter_map(|operation| match operation {
+			TemplateOperation::Block(block) => Some((block.name.as_str(), block.operations.len())),
+			_ => None
+		}).collect();
+		// title keeps default content, content is overridden
+		assert_eq!(blocks, vec![("title", 1), ("content", 4)]);
+		assert_eq!(page.operations.len(), 5);
+		// variables defined inside of a block belong to the block
+		assert!(!page.variables.contains("user"));
+	}
+
+	#[test]
+	fn inherit_errors() {
+		let base = "{GET user FROM '/api'}<main>{BLOCK content}{END}</main>";
+		let extend = |page: &'static str, base: &'static str| -> Result<(), String> {
+			let mut sources = LinkedList::new();
+			sources.push_back(("page.ws".to_string(), page));
+			sources.push_back(("base.ws".to_string(), base));
+			build_model(sources, &mut LinkedList::new()).map(|_| ()).map_err(|err| err.name)
+		};
+
+		// variables of the extended template can be used
+		assert!(extend("{EXTENDS './base.ws'}{BLOCK content}{user.name}{END}", base).is_ok());
+		assert_eq!(extend("{EXTENDS './base.ws'}{BLOCK content}{other.name}{END}", base).err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(extend("{EXTENDS './base.ws'}{BLOCK footer}x{END}", base).err().unwrap(), "ERR_UNKNOWN_BLOCK");
+		// blocks see the variables of the loops and branches they are declared in, but not those of other loops
+		let list = "{GET items FROM '/api'}<ul>{FOR item IN items}{IF item.new}{SET label = 'new'}{BLOCK item}{END}{END}{END}</ul>{BLOCK footer}{END}";
+		assert!(extend("{EXTENDS './base.ws'}{BLOCK item}<li>{label}: {item.name}</li>{END}", list).is_ok());
+		assert_eq!(extend("{EXTENDS './base.ws'}{BLOCK footer}{item.name}{END}", list).err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(extend("{EXTENDS './base.ws'}<p>x</p>", base).err().unwrap(), "ERR_CONTENT_OUTSIDE_OF_BLOCK");
+		assert_eq!(extend("<p>x</p>{EXTENDS './base.ws'}", base).err().unwrap(), "ERR_MISPLACED_EXTENDS");
+		assert_eq!(extend("{EXTENDS './other.ws'}", base).err().unwrap(), "ERR_MISSING_TEMPLATE");
+		assert_eq!(extend("{EXTENDS './base.ws'}", "{EXTENDS './page.ws'}").err().unwrap(), "ERR_CYCLIC_EXTENDS");
+		assert_eq!(extend("{BLOCK a}{END}{BLOCK a}{END}", base).err().unwrap(), "ERR_DUPLICATE_BLOCK");
+
+		// errors of the inheritance point at the {EXTENDS ...} instruction
+		let mut sources = LinkedList::new();
+		sources.push_back(("page.ws".to_string(), "{EXTENDS './other.ws'}"));
+		let err = build_model(sources, &mut LinkedList::new()).err().unwrap();
+		assert_eq!(err.view.unwrap().cut(), "{EXTENDS './other.ws'}");
+	}
+}
@@ -1,2 +1,9 @@
 pub mod scanner;
+pub mod parser;
+pub mod linker;
 pub mod outputcomponent;
+pub mod scheduler;
+pub mod binder;
+pub mod splitter;
+pub mod checker;
+pub mod structcomponent;
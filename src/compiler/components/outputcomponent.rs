@@ -1,15 +1,119 @@
+use std::collections::{HashMap, LinkedList};
+
 use string_builder::Builder;
-use crate::compiler::model::CoreModel;
+use crate::compiler::model::{AttributePart, Binding, BindingKind, BindOperation, Chunk, CoreModel, Data, EventAction, EventOperation, FetchOperation, HttpMethod, PrintOperation, RequestBody, RouteOperation, Rule, Source, StreamMode, SubscribeOperation, Template, TemplateOperation, Transport, ValidateOperation};
+
+/**
+ * Name and value of a header or query parameter
+ */
+type Param<'m> = (&'m str, &'m Data);
 
 pub struct OutputComponent {
-    lines: Builder
-} 
+    lines: Builder,
+    /// base url of the selected environment (see `config::Environment`)
+    base_url: String,
+    /// names of the modules loading routed templates, by id of the routed template (see `SplitterComponent`)
+    chunks: HashMap<String, String>
+}
 
 impl OutputComponent {
     pub fn new() -> Self {
         OutputComponent {
-            lines: Builder::default()
+            lines: Builder::default(),
+            base_url: String::new(),
+            chunks: HashMap::new()
+        }
+    }
+
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.base_url = base_url.to_string();
+    }
+
+    /// Routes to templates of these modules name the module the client has to load first
+    pub fn set_chunks(&mut self, chunks: &[Chunk]) {
+        self.chunks = chunks.iter()
+            .filter_map(|chunk| chunk.entry.clone().map(|entry| (entry, chunk_name(chunk))))
+            .collect();
+    }
+}
+
+/**
+ * Name of the module of a chunk, `core` for the core module, e.g. `pages/user.ws` becomes `pages_user_ws`
+ */
+pub fn chunk_name(chunk: &Chunk) -> String {
+    chunk.entry.as_ref().map_or("core".to_string(), |entry| c_identifier(entry))
+}
+
+/**
+ * Converts template id into a C identifier, e.g. `pages/index.ws` becomes `pages_index_ws`
+ */
+fn c_identifier(id: &str) -> String {
+    id.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+/**
+ * Quoted C string literal.
+ * Control chars are written as octal escapes, which (unlike hex escapes) never take up the following chars.
+ * The second `?` of `??` is escaped, so no trigraph can be formed.
+ */
+fn c_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    let mut previous = None;
+    for c in text.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            '?' if previous == Some('?') => literal.push_str("\\?"),
+            c if c.is_ascii_control() => literal.push_str(&format!("\\{:03o}", c as u32)),
+            _ => literal.push(c)
         }
+        previous = Some(c);
+    }
+    literal.push('"');
+    literal
+}
+
+/**
+ * Data as `ws_value` initializer (see include/http.h)
+ */
+fn c_value(data: &Data) -> String {
+    match data {
+        Data::Value(value) => format!("{{ WS_VALUE, {} }}", c_string(value)),
+        Data::Variable(variable, _) => format!("{{ WS_VARIABLE, {} }}", c_string(&variable.to_string()))
+    }
+}
+
+/**
+ * Part of concatenated markup or attribute value as `ws_value` initializer
+ */
+fn c_part(part: &AttributePart) -> String {
+    match part {
+        AttributePart::Text(text) => format!("{{ WS_VALUE, {} }}", c_string(text)),
+        AttributePart::Value(data) => c_value(data)
+    }
+}
+
+fn c_method(method: HttpMethod) -> String {
+    format!("WS_HTTP_{}", method.name())
+}
+
+/**
+ * `WS_STATE_*` flags of the branches of a request
+ */
+fn c_states(fetch: &FetchOperation) -> String {
+    let states: Vec<&str> = [
+        (fetch.loading.is_some(), "WS_STATE_LOADING"),
+        (fetch.empty.is_some(), "WS_STATE_EMPTY"),
+        (fetch.error.is_some(), "WS_STATE_ERROR")
+    ].iter().filter(|(given, _)| *given).map(|(_, flag)| *flag).collect();
+
+    if states.is_empty() {
+        "0".to_string()
+    } else {
+        states.join(" | ")
     }
 }
 
@@ -20,15 +124,398 @@ impl OutputComponent {
     }
 
     /**
-     * Writes C-Code 
+     * Writes C-Code
+     */
+    pub fn generate_output(&mut self, model: &CoreModel) {
+        self.add_line("#include \"dom.h\"".to_string());
+        self.add_line(format!("const char* WS_BASE_URL = {};", c_string(&self.base_url)));
+        self.generate_templates(model.templates.iter().collect());
+    }
+
+    /**
+     * Writes C-Code of a single module, see `SplitterComponent`. Besides its own templates,
+     * modules declare the templates of the core module they insert.
      */
-    pub fn generateOutput(&mut self, model: &CoreModel) {
+    pub fn generate_chunk(&mut self, model: &CoreModel, chunk: &Chunk) {
+        self.add_line("#include \"dom.h\"".to_string());
+        let templates: Vec<&Template> = model.templates.iter().filter(|temp| chunk.templates.contains(&temp.id)).collect();
+        match &chunk.entry {
+            None => self.add_line(format!("const char* WS_BASE_URL = {};", c_string(&self.base_url))),
+            Some(_) => {
+                let mut inserted: Vec<&str> = Vec::new();
+                for temp in templates.iter() {
+                    for id in temp.calls() {
+                        if !chunk.templates.iter().any(|own| own == id) && !inserted.contains(&id) {
+                            inserted.push(id);
+                        }
+                    }
+                }
+                for id in inserted {
+                    self.add_line(format!("char* {}();", c_identifier(id)));
+                }
+            }
+        }
+        self.generate_templates(templates);
+    }
+
+    fn generate_templates(&mut self, templates: Vec<&Template>) {
         // first declare all functions (templates)
-        for temp in &model.templates {
-            self.add_line(format!("char* {}();", temp.id));
+        for temp in templates.iter() {
+            self.add_line(format!("char* {}();", c_identifier(&temp.id)));
         }
 
-        // 
+        // requests of every template
+        for temp in templates {
+            let fetches = temp.requests();
+            let listeners: Vec<&EventOperation> = TemplateOperation::walk(&temp.operations)
+                .filter_map(|operation| match operation {
+                    TemplateOperation::On(listener) => Some(listener),
+                    _ => None
+                })
+                .collect();
+            self.generate_requests(&c_identifier(&temp.id), temp, &fetches, &listeners);
+            self.generate_listeners(&c_identifier(&temp.id), &listeners);
+
+            let inputs: Vec<&BindOperation> = TemplateOperation::walk(&temp.operations)
+                .filter_map(|operation| match operation {
+                    TemplateOperation::Bind(bind) => Some(bind),
+                    _ => None
+                })
+                .collect();
+            self.generate_inputs(&c_identifier(&temp.id), temp, &inputs);
+
+            let validations: Vec<&ValidateOperation> = TemplateOperation::walk(&temp.operations)
+                .filter_map(|operation| match operation {
+                    TemplateOperation::Validate(validate) => Some(validate),
+                    _ => None
+                })
+                .collect();
+            self.generate_validations(&c_identifier(&temp.id), &validations);
+            self.generate_routes(&c_identifier(&temp.id), &temp.routes());
+
+            let subscriptions: Vec<&SubscribeOperation> = TemplateOperation::walk(&temp.operations)
+                .filter_map(|operation| match operation {
+                    TemplateOperation::Subscribe(subscribe) => Some(subscribe),
+                    _ => None
+                })
+                .collect();
+            self.generate_subscriptions(&c_identifier(&temp.id), &subscriptions);
+            self.generate_bindings(&c_identifier(&temp.id), &temp.id, &temp.bindings);
+        }
+    }
+
+    /**
+     * Writes `ws_request` descriptors, which are passed to `ws_http_send` by the client
+     */
+    fn generate_requests(&mut self, template: &str, temp: &Template, fetches: &[&FetchOperation], listeners: &[&EventOperation]) {
+        for fetch in fetches {
+            let listener = listeners.iter()
+                .find(|listener| matches!(&listener.action, EventAction::Request(request) if request.index == fetch.index))
+                .map_or(-1, |listener| listener.index as i64);
+            let name = format!("{}_request_{}", template, fetch.index);
+
+            let query: Vec<Param> = fetch.query.iter().map(|param| (param.name.as_str(), &param.value)).collect();
+            let query = self.generate_params(&format!("{}_query", name), &query);
+            let headers: Vec<Param> = fetch.headers.iter().map(|header| (header.name.as_str(), &header.value)).collect();
+            let headers = self.generate_params(&format!("{}_headers", name), &headers);
+
+            let dependencies = self.generate_dependencies(&format!("{}_dependencies", name), fetch);
+            let (loading, loading_count) = self.generate_state(&format!("{}_loading", name), temp, fetch.loading.as_ref());
+            let (empty, empty_count) = self.generate_state(&format!("{}_empty", name), temp, fetch.empty.as_ref());
+            let (error, error_count) = self.generate_state(&format!("{}_error", name), temp, fetch.error.as_ref().map(|error| &error.operations));
+
+            let body = match &fetch.body {
+                Some(RequestBody::Data(data)) => c_value(data),
+                Some(RequestBody::Form(id)) => format!("{{ WS_FORM, {} }}", c_string(id)),
+                None => "{ WS_NONE, (void*)0 }".to_string()
+            };
+
+            self.add_line(format!("static const ws_request {} = {{", name));
+            self.add_line(format!("    .method = {},", c_method(fetch.method)));
+            self.add_line(format!("    .url = {},", c_value(&fetch.from)));
+            self.add_line(format!("    .body = {},", body));
+            self.add_line(format!("    .query = {},", query));
+            self.add_line(format!("    .query_count = {},", fetch.query.len()));
+            self.add_line(format!("    .headers = {},", headers));
+            self.add_line(format!("    .header_count = {},", fetch.headers.len()));
+            self.add_line(format!("    .credentials = {},", fetch.credentials));
+            // the parser limits durations to the range of int
+            self.add_line(format!("    .cache_ms = {},", fetch.cache.map_or(0, |cache| cache.as_millis())));
+            self.add_line(format!("    .refresh_ms = {},", fetch.refresh.map_or(0, |refresh| refresh.as_millis())));
+            self.add_line(format!("    .target = {},", match &fetch.target {
+                Some(target) => c_string(&target.id),
+                None => "(void*)0".to_string()
+            }));
+            self.add_line(format!("    .states = {},", c_states(fetch)));
+            self.add_line(format!("    .error_variable = {},", match &fetch.error {
+                Some(error) => c_string(&error.variable.id),
+                None => "(void*)0".to_string()
+            }));
+            self.add_line(format!("    .region = {},", fetch.binding.map_or(-1, |binding| binding as i64)));
+            self.add_line(format!("    .loading = {},", loading));
+            self.add_line(format!("    .loading_count = {},", loading_count));
+            self.add_line(format!("    .empty = {},", empty));
+            self.add_line(format!("    .empty_count = {},", empty_count));
+            self.add_line(format!("    .error = {},", error));
+            self.add_line(format!("    .error_count = {},", error_count));
+            self.add_line(format!("    .dependencies = {},", dependencies));
+            self.add_line(format!("    .dependency_count = {},", fetch.dependencies.len()));
+            self.add_line(format!("    .listener = {}", listener));
+            self.add_line("};".to_string());
+        }
+
+        let mut requests: Vec<String> = (0..fetches.len()).map(|index| format!("&{}_request_{}", template, index)).collect();
+        if requests.is_empty() {
+            // arrays must not be empty in C
+            requests.push("(void*)0".to_string());
+        }
+        self.add_line(format!("static const ws_request* {}_requests[] = {{ {} }};", template, requests.join(", ")));
+        self.add_line(format!("static const int {}_request_count = {};", template, fetches.len()));
+    }
+
+    /**
+     * Writes `ws_subscription` descriptors, which are passed to `ws_subscribe` by the client
+     */
+    fn generate_subscriptions(&mut self, template: &str, subscriptions: &[&SubscribeOperation]) {
+        let mut names = Vec::new();
+        for (index, subscribe) in subscriptions.iter().enumerate() {
+            let name = format!("{}_subscription_{}", template, index);
+            self.add_line(format!("static const ws_subscription {} = {{", name));
+            self.add_line(format!("    .transport = {},", match subscribe.transport {
+                Transport::WebSocket => "WS_STREAM_WEBSOCKET",
+                Transport::EventSource => "WS_STREAM_EVENT_SOURCE"
+            }));
+            self.add_line(format!("    .url = {},", c_value(&subscribe.from)));
+            self.add_line(format!("    .target = {},", c_string(&subscribe.target.id)));
+            self.add_line(format!("    .append = {}", subscribe.mode == StreamMode::Append));
+            self.add_line("};".to_string());
+            names.push(format!("&{}", name));
+        }
+
+        if names.is_empty() {
+            names.push("(void*)0".to_string());
+        }
+        self.add_line(format!("static const ws_subscription* {}_subscriptions[] = {{ {} }};", template, names.join(", ")));
+        self.add_line(format!("static const int {}_subscription_count = {};", template, subscriptions.len()));
+    }
+
+    /**
+     * Writes `ws_listener` table, the host attaches each listener to the element it is rendered into
+     */
+    fn generate_listeners(&mut self, template: &str, listeners: &[&EventOperation]) {
+        if listeners.is_empty() {
+            self.add_line(format!("static const ws_listener* {}_listeners = (void*)0;", template));
+        } else {
+            self.add_line(format!("static const ws_listener {}_listeners[] = {{", template));
+            for listener in listeners {
+                let (action, handler, argument, request) = match &listener.action {
+                    EventAction::Call { handler, argument } => ("WS_ACTION_CALL", c_string(handler), argument.as_ref().map_or("{ WS_NONE, (void*)0 }".to_string(), c_value), -1),
+                    EventAction::Request(fetch) => ("WS_ACTION_REQUEST", "(void*)0".to_string(), "{ WS_NONE, (void*)0 }".to_string(), fetch.index as i64)
+                };
+                self.add_line(format!("    {{ {}, {}, {}, {}, {} }},", c_string(&listener.event), action, handler, argument, request));
+            }
+            self.add_line("};".to_string());
+        }
+        self.add_line(format!("static const int {}_listener_count = {};", template, listeners.len()));
+    }
+
+    /**
+     * Writes names of the writable variables and the `ws_input` table of the controls changing them
+     */
+    fn generate_inputs(&mut self, template: &str, temp: &Template, inputs: &[&BindOperation]) {
+        let writable: Vec<String> = temp.writable.iter().map(|variable| c_string(&variable.id)).collect();
+        if writable.is_empty() {
+            self.add_line(format!("static const char** {}_writable = (void*)0;", template));
+        } else {
+            self.add_line(format!("static const char* {}_writable[] = {{ {} }};", template, writable.join(", ")));
+        }
+        self.add_line(format!("static const int {}_writable_count = {};", template, writable.len()));
+
+        if inputs.is_empty() {
+            self.add_line(format!("static const ws_input* {}_inputs = (void*)0;", template));
+        } else {
+            self.add_line(format!("static const ws_input {}_inputs[] = {{", template));
+            for input in inputs {
+                let variable = temp.writable.iter().position(|variable| variable.id == input.target.id).unwrap_or_default();
+                self.add_line(format!("    {{ {}, {}, {} }},", c_string(&input.event), c_string(&input.target.to_string()), variable));
+            }
+            self.add_line("};".to_string());
+        }
+        self.add_line(format!("static const int {}_input_count = {};", template, inputs.len()));
+    }
+
+    /**
+     * Writes `ws_validation` table and the `ws_rule`s of each validation, messages are always given
+     */
+    fn generate_validations(&mut self, template: &str, validations: &[&ValidateOperation]) {
+        let mut entries = Vec::new();
+        for (index, validate) in validations.iter().enumerate() {
+            let name = format!("{}_validation_{}_rules", template, index);
+            self.add_line(format!("static const ws_rule {}[] = {{", name));
+            for rule in &validate.rules {
+                let (kind, bound, pattern) = match &rule.rule {
+                    Rule::Required => ("WS_RULE_REQUIRED", 0.0, None),
+                    Rule::Email => ("WS_RULE_EMAIL", 0.0, None),
+                    Rule::MinLength(length) => ("WS_RULE_MIN_LENGTH", *length as f64, None),
+                    Rule::MaxLength(length) => ("WS_RULE_MAX_LENGTH", *length as f64, None),
+                    Rule::Pattern(pattern) => ("WS_RULE_PATTERN", 0.0, Some(pattern)),
+                    Rule::Min(min) => ("WS_RULE_MIN", *min, None),
+                    Rule::Max(max) => ("WS_RULE_MAX", *max, None)
+                };
+                let pattern = pattern.map_or("(void*)0".to_string(), |pattern| c_string(pattern));
+                self.add_line(format!("    {{ {}, {:?}, {}, {} }},", kind, bound, pattern, c_string(&rule.message())));
+            }
+            self.add_line("};".to_string());
+
+            let error = validate.error.as_ref().map_or("(void*)0".to_string(), |error| c_string(&error.id));
+            entries.push(format!("    {{ {}, {}, {}, {} }},", c_string(&validate.target.to_string()), name, validate.rules.len(), error));
+        }
+
+        if entries.is_empty() {
+            self.add_line(format!("static const ws_validation* {}_validations = (void*)0;", template));
+        } else {
+            self.add_line(format!("static const ws_validation {}_validations[] = {{", template));
+            for entry in entries {
+                self.add_line(entry);
+            }
+            self.add_line("};".to_string());
+        }
+        self.add_line(format!("static const int {}_validation_count = {};", template, validations.len()));
+    }
+
+    /**
+     * Writes `ws_route` table of the template, routes are ordered by their index
+     */
+    fn generate_routes(&mut self, template: &str, routes: &[&RouteOperation]) {
+        let mut entries = Vec::new();
+        for route in routes {
+            let name = format!("{}_route_{}_params", template, route.index);
+            let params: Vec<String> = route.params().iter().map(|param| c_string(param)).collect();
+            let params = if params.is_empty() {
+                "(void*)0".to_string()
+            } else {
+                self.add_line(format!("static const char* {}[] = {{ {} }};", name, params.join(", ")));
+                name
+            };
+            let path = route.path.as_ref().map_or("(void*)0".to_string(), |path| c_string(path));
+            let chunk = self.chunks.get(&route.template).map_or("(void*)0".to_string(), |chunk| c_string(chunk));
+            entries.push(format!("    {{ {}, {}, {}, {}, {} }},", path, c_string(&route.template), chunk, params, route.params().len()));
+        }
+
+        if entries.is_empty() {
+            self.add_line(format!("static const ws_route* {}_routes = (void*)0;", template));
+        } else {
+            self.add_line(format!("static const ws_route {}_routes[] = {{", template));
+            for entry in entries {
+                self.add_line(entry);
+            }
+            self.add_line("};".to_string());
+        }
+        self.add_line(format!("static const int {}_route_count = {};", template, routes.len()));
+    }
+
+    /**
+     * Writes `ws_binding` table of the nodes the client patches when their sources change
+     */
+    fn generate_bindings(&mut self, template: &str, id: &str, bindings: &[Binding]) {
+        let mut entries = Vec::new();
+        for (index, binding) in bindings.iter().enumerate() {
+            let name = format!("{}_binding_{}", template, index);
+            let sources: Vec<String> = binding.sources.iter().map(|source| match source {
+                Source::Request(index) => format!("{{ WS_SOURCE_REQUEST, {} }}", index),
+                Source::Stream(index) => format!("{{ WS_SOURCE_STREAM, {} }}", index),
+                Source::Input(index) => format!("{{ WS_SOURCE_INPUT, {} }}", index),
+                Source::Route => "{ WS_SOURCE_ROUTE, 0 }".to_string()
+            }).collect();
+            self.add_line(format!("static const ws_source {}_sources[] = {{ {} }};", name, sources.join(", ")));
+
+            let (kind, attribute, parts, part_count) = match &binding.kind {
+                BindingKind::Text => ("WS_BINDING_TEXT", "(void*)0".to_string(), "(void*)0".to_string(), 0),
+                BindingKind::Region => ("WS_BINDING_REGION", "(void*)0".to_string(), "(void*)0".to_string(), 0),
+                BindingKind::Attribute { name: attribute, parts } => {
+                    let values: Vec<String> = parts.iter().map(c_part).collect();
+                    self.add_line(format!("static const ws_value {}_parts[] = {{ {} }};", name, values.join(", ")));
+                    ("WS_BINDING_ATTRIBUTE", c_string(attribute), format!("{}_parts", name), parts.len())
+                }
+            };
+            entries.push(format!("    {{ {}, {}, {}, {}, {}, {}, {}_sources, {} }},", kind, c_string(&Binding::anchor(id, index)), attribute, parts, part_count,
+                binding.parent.map_or(-1, |parent| parent as i64), name, binding.sources.len()));
+        }
+
+        if entries.is_empty() {
+            self.add_line(format!("static const ws_binding* {}_bindings = (void*)0;", template));
+        } else {
+            self.add_line(format!("static const ws_binding {}_bindings[] = {{", template));
+            for entry in entries {
+                self.add_line(entry);
+            }
+            self.add_line("};".to_string());
+        }
+        self.add_line(format!("static const int {}_binding_count = {};", template, bindings.len()));
+    }
+
+    /**
+     * Writes `ws_param` array and returns its name, or a null pointer if there are no params
+     */
+    fn generate_params(&mut self, name: &str, params: &[Param]) -> String {
+        if params.is_empty() {
+            return "(void*)0".to_string();
+        }
+        self.add_line(format!("static const ws_param {}[] = {{", name));
+        for (param, value) in params {
+            self.add_line(format!("    {{ {}, {} }},", c_string(param), c_value(value)));
+        }
+        self.add_line("};".to_string());
+        name.to_string()
+    }
+
+    /**
+     * Writes markup of a state of a request as `ws_value` parts and returns the name of the array and its length,
+     * or a null pointer if the state is empty or not declared. The parser only allows markup and inserted values in states,
+     * values bound to text nodes are enclosed in the comments of their anchor.
+     */
+    fn generate_state(&mut self, name: &str, temp: &Template, operations: Option<&LinkedList<TemplateOperation>>) -> (String, usize) {
+        let mut parts: Vec<AttributePart> = Vec::new();
+        let push_text = |parts: &mut Vec<AttributePart>, text: &str| match parts.last_mut() {
+            Some(AttributePart::Text(previous)) => previous.push_str(text),
+            _ => parts.push(AttributePart::Text(text.to_string()))
+        };
+        for operation in operations.into_iter().flatten() {
+            match operation {
+                TemplateOperation::Print(PrintOperation { content: Data::Value(text) }) => push_text(&mut parts, text),
+                TemplateOperation::Print(PrintOperation { content }) => parts.push(AttributePart::Value(content.clone())),
+                TemplateOperation::Insert(insert) => match insert.binding.filter(|index| matches!(temp.bindings[*index].kind, BindingKind::Text)) {
+                    Some(index) => {
+                        let anchor = Binding::anchor(&temp.id, index);
+                        push_text(&mut parts, &format!("<!--ws:{}-->", anchor));
+                        parts.push(AttributePart::Value(insert.content.clone()));
+                        push_text(&mut parts, &format!("<!--/ws:{}-->", anchor));
+                    },
+                    None => parts.push(AttributePart::Value(insert.content.clone()))
+                },
+                _ => ()
+            }
+        }
+
+        if parts.is_empty() {
+            return ("(void*)0".to_string(), 0);
+        }
+        let values: Vec<String> = parts.iter().map(c_part).collect();
+        self.add_line(format!("static const ws_value {}[] = {{ {} }};", name, values.join(", ")));
+        (name.to_string(), parts.len())
+    }
+
+    /**
+     * Writes indices of the requests a request depends on and returns the name of the array,
+     * or a null pointer if the request is independent
+     */
+    fn generate_dependencies(&mut self, name: &str, fetch: &FetchOperation) -> String {
+        if fetch.dependencies.is_empty() {
+            return "(void*)0".to_string();
+        }
+        let indices: Vec<String> = fetch.dependencies.iter().map(usize::to_string).collect();
+        self.add_line(format!("static const int {}[] = {{ {} }};", name, indices.join(", ")));
+        name.to_string()
     }
 
     /**
@@ -37,4 +524,213 @@ impl OutputComponent {
     pub fn finish(self) -> String {
         self.lines.string().unwrap()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::LinkedList;
+
+    use crate::compiler::{build_model, config::Config};
+    use crate::compiler::components::splitter::SplitterComponent;
+    use super::{c_string, chunk_name, OutputComponent};
+
+    fn generate(source: &'static str) -> String {
+        generate_for(source, "")
+    }
+
+    fn generate_for(source: &'static str, base_url: &str) -> String {
+        let mut sources = LinkedList::new();
+        sources.push_back(("pages/index.ws".to_string(), source));
+        let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+
+        let mut output = OutputComponent::new();
+        output.set_base_url(base_url);
+        output.generate_output(&model);
+        output.finish()
+    }
+
+    #[test]
+    fn escape_c_strings() {
+        assert_eq!(c_string("say \"hi\"\\n"), "\"say \\\"hi\\\"\\\\n\"");
+        assert_eq!(c_string("a\r\n\tb"), "\"a\\r\\n\\tb\"");
+        assert_eq!(c_string("\u{7}1"), "\"\\0071\"");
+        assert_eq!(c_string("what??!"), "\"what?\\?!\"");
+    }
+
+    #[test]
+    fn generate_requests() {
+        let code = generate("{GET session FROM '/api/session'}{SET user = session.user}\
+            {IF session.user}{POST user TO '/api/users' HEADERS Authorization=session.token, Accept='application/json' AS created}{END}\
+            {DELETE TO '/api/users/1'}{PUT FORM 'signup' TO '/api/signup'}");
+
+        assert!(code.contains("char* pages_index_ws();"));
+        assert!(code.contains("static const ws_param pages_index_ws_request_1_headers[] = {\n    { \"Authorization\", { WS_VARIABLE, \"session.token\" } },\n    { \"Accept\", { WS_VALUE, \"application/json\" } },\n};"));
+        assert!(code.contains("    .method = WS_HTTP_POST,\n    .url = { WS_VALUE, \"/api/users\" },\n    .body = { WS_VARIABLE, \"user\" },\n    .query = (void*)0,\n    .query_count = 0,\n    .headers = pages_index_ws_request_1_headers,\n    .header_count = 2,\n    .credentials = false,\n    .cache_ms = 0,\n    .refresh_ms = 0,\n    .target = \"created\",\n    .states = 0,\n    .error_variable = (void*)0"));
+        assert!(code.contains("    .method = WS_HTTP_DELETE,\n    .url = { WS_VALUE, \"/api/users/1\" },\n    .body = { WS_NONE, (void*)0 },\n    .query = (void*)0,\n    .query_count = 0,\n    .headers = (void*)0,\n    .header_count = 0,\n    .credentials = false,\n    .cache_ms = 0,\n    .refresh_ms = 0,\n    .target = (void*)0,"));
+        assert!(code.contains(".body = { WS_FORM, \"signup\" },"));
+        assert!(code.contains("static const int pages_index_ws_request_count = 4;"));
+    }
+
+    #[test]
+    fn generate_query_and_credentials() {
+        let config = Config::parse("[production]\nbase_url = https://api.example.com").ok().unwrap();
+        let code = generate_for("{GET route FROM '/api/route'}{GET session FROM '/api/session'}\
+            {GET user FROM '/api/user' PARAMS id=route.id, q='a b' HEADERS Authorization=session.token WITH credentials}",
+            &config.get_environment("production").ok().unwrap().base_url);
+
+        assert!(code.contains("const char* WS_BASE_URL = \"https://api.example.com\";"));
+        assert!(code.contains("static const ws_param pages_index_ws_request_2_query[] = {\n    { \"id\", { WS_VARIABLE, \"route.id\" } },\n    { \"q\", { WS_VALUE, \"a b\" } },\n};"));
+        assert!(code.contains("    .query = pages_index_ws_request_2_query,\n    .query_count = 2,\n    .headers = pages_index_ws_request_2_headers,\n    .header_count = 1,\n    .credentials = true,\n    .cache_ms = 0,\n    .refresh_ms = 0,\n    .target = \"user\","));
+    }
+
+    #[test]
+    fn generate_states() {
+        let code = generate("{GET items FROM '/api/items'}{FOR item IN items}{item}{END}{LOADING}...{ERROR e}<p class=\"{e.status}\">{e.message}</p>{END}\
+            {GET user FROM '/api/user'}<ul>{FOR item IN items}{GET price FROM item.url}{price}{LOADING}?{END}{END}</ul>");
+
+        assert!(code.contains("    .target = \"items\",\n    .states = WS_STATE_LOADING | WS_STATE_ERROR,\n    .error_variable = \"e\",\n    .region = 0,\n    \
+            .loading = pages_index_ws_request_0_loading,\n    .loading_count = 1,\n    .empty = (void*)0,\n    .empty_count = 0,\n    \
+            .error = pages_index_ws_request_0_error,\n    .error_count = 5,"));
+        assert!(code.contains("static const ws_value pages_index_ws_request_0_loading[] = { { WS_VALUE, \"...\" } };"));
+        // the error is a source of the attribute and the text node inside of the region
+        assert!(code.contains("static const ws_value pages_index_ws_request_0_error[] = { { WS_VALUE, \"<p class=\\\"\" }, { WS_VARIABLE, \"e.status\" }, \
+            { WS_VALUE, \"\\\" data-ws-anchor=\\\"pages/index.ws:2\\\"><!--ws:pages/index.ws:3-->\" }, { WS_VARIABLE, \"e.message\" }, { WS_VALUE, \"<!--/ws:pages/index.ws:3--></p>\" } };"));
+        assert!(code.contains("    .target = \"user\",\n    .states = 0,\n    .error_variable = (void*)0,\n    .region = -1,\n    .loading = (void*)0,"));
+        // requests inside of loops are rendered again along with the loop
+        assert!(code.contains("    .target = \"price\",\n    .states = WS_STATE_LOADING,\n    .error_variable = (void*)0,\n    .region = -1,\n    \
+            .loading = pages_index_ws_request_2_loading,"));
+    }
+
+    #[test]
+    fn generate_cache_and_refresh() {
+        let code = generate("{GET stats FROM '/api/stats' CACHE 2m}{GET prices FROM '/api/prices' REFRESH EVERY 500ms}");
+
+        assert!(code.contains("    .credentials = false,\n    .cache_ms = 120000,\n    .refresh_ms = 0,\n    .target = \"stats\","));
+        assert!(code.contains("    .credentials = false,\n    .cache_ms = 0,\n    .refresh_ms = 500,\n    .target = \"prices\","));
+    }
+
+    #[test]
+    fn generate_subscriptions() {
+        let code = generate("{SUBSCRIBE prices FROM 'ws://localhost/prices'}{IF prices}{SUBSCRIBE events FROM SSE '/events' APPEND}{END}");
+
+        assert!(code.contains("static const ws_subscription pages_index_ws_subscription_0 = {\n    .transport = WS_STREAM_WEBSOCKET,\n    .url = { WS_VALUE, \"ws://localhost/prices\" },\n    .target = \"prices\",\n    .append = false\n};"));
+        assert!(code.contains("    .transport = WS_STREAM_EVENT_SOURCE,\n    .url = { WS_VALUE, \"/events\" },\n    .target = \"events\",\n    .append = true\n};"));
+        assert!(code.contains("static const ws_subscription* pages_index_ws_subscriptions[] = { &pages_index_ws_subscription_0, &pages_index_ws_subscription_1 };"));
+        assert!(code.contains("static const int pages_index_ws_subscription_count = 2;"));
+    }
+
+    #[test]
+    fn generate_dependencies() {
+        let code = generate("{GET session FROM '/api/session'}{GET news FROM '/api/news'}{SET url = '/api/users/' + session.id}{GET user FROM url}");
+
+        assert!(code.contains("static const int pages_index_ws_request_2_dependencies[] = { 0 };"));
+        assert!(code.contains("    .target = \"user\",\n    .states = 0,\n    .error_variable = (void*)0,\n    .region = -1,\n    .loading = (void*)0,\n    .loading_count = 0,\n    .empty = (void*)0,\n    .empty_count = 0,\n    \
+            .error = (void*)0,\n    .error_count = 0,\n    .dependencies = pages_index_ws_request_2_dependencies,\n    .dependency_count = 1,\n    .listener = -1\n};"));
+        assert!(code.contains("    .target = \"news\",\n    .states = 0,\n    .error_variable = (void*)0,\n    .region = -1,\n    .loading = (void*)0,\n    .loading_count = 0,\n    .empty = (void*)0,\n    .empty_count = 0,\n    \
+            .error = (void*)0,\n    .error_count = 0,\n    .dependencies = (void*)0,\n    .dependency_count = 0,\n    .listener = -1\n};"));
+    }
+
+    #[test]
+    fn generate_paths() {
+        let code = generate("{GET user FROM '/api/user'}<p title=\"{user?.tags[0]['first name']}\"></p>");
+        assert!(code.contains("static const ws_value pages_index_ws_binding_0_parts[] = { { WS_VARIABLE, \"user?.tags[0]['first name']\" } };"));
+    }
+
+    #[test]
+    fn generate_bindings() {
+        let code = generate("{GET user FROM '/api/user'}<a href=\"/users/{user.id}\">{user.name}</a>{IF user.admin}<b>{user.role}</b>{END}");
+
+        assert!(code.contains("static const ws_value pages_index_ws_binding_0_parts[] = { { WS_VALUE, \"/users/\" }, { WS_VARIABLE, \"user.id\" } };"));
+        assert!(code.contains("static const ws_source pages_index_ws_binding_3_sources[] = { { WS_SOURCE_REQUEST, 0 } };"));
+        assert!(code.contains("static const ws_binding pages_index_ws_bindings[] = {\n    \
+            { WS_BINDING_ATTRIBUTE, \"pages/index.ws:0\", \"href\", pages_index_ws_binding_0_parts, 2, -1, pages_index_ws_binding_0_sources, 1 },\n    \
+            { WS_BINDING_TEXT, \"pages/index.ws:1\", (void*)0, (void*)0, 0, -1, pages_index_ws_binding_1_sources, 1 },\n    \
+            { WS_BINDING_REGION, \"pages/index.ws:2\", (void*)0, (void*)0, 0, -1, pages_index_ws_binding_2_sources, 1 },\n    \
+            { WS_BINDING_TEXT, \"pages/index.ws:3\", (void*)0, (void*)0, 0, 2, pages_index_ws_binding_3_sources, 1 },\n};"));
+        assert!(code.contains("static const int pages_index_ws_binding_count = 4;"));
+        assert!(generate("<p>static</p>").contains("static const ws_binding* pages_index_ws_bindings = (void*)0;"));
+    }
+    #[test]
+    fn generate_listeners() {
+        let code = generate("<button {ON click CALL refresh}>Reload</button>{GET user FROM '/api/user'}\
+            <form {ON submit POST form TO '/api/users' HEADERS Authorization=user.token AS saved}><button {ON click CALL select user.id}></button></form>");
+
+        assert!(code.contains("    .target = \"saved\",\n    .states = 0,\n    .error_variable = (void*)0,\n    .region = -1,\n    .loading = (void*)0,\n    .loading_count = 0,\n    .empty = (void*)0,\n    .empty_count = 0,\n    \
+            .error = (void*)0,\n    .error_count = 0,\n    .dependencies = pages_index_ws_request_1_dependencies,\n    .dependency_count = 1,\n    .listener = 1\n};"));
+        assert!(code.contains("static const int pages_index_ws_request_count = 2;"));
+        assert!(code.contains("static const ws_listener pages_index_ws_listeners[] = {\n    \
+            { \"click\", WS_ACTION_CALL, \"refresh\", { WS_NONE, (void*)0 }, -1 },\n    \
+            { \"submit\", WS_ACTION_REQUEST, (void*)0, { WS_NONE, (void*)0 }, 1 },\n    \
+            { \"click\", WS_ACTION_CALL, \"select\", { WS_VARIABLE, \"user.id\" }, -1 },\n};"));
+        assert!(code.contains("static const int pages_index_ws_listener_count = 3;"));
+    }
+    #[test]
+    fn generate_inputs() {
+        let code = generate("{GET user FROM '/api/user'}{SET query = 'all'}<input {BIND input TO query}><input {BIND change TO user.email}><p>{user.email}</p>");
+
+        assert!(code.contains("static const char* pages_index_ws_writable[] = { \"query\", \"user\" };"));
+        assert!(code.contains("static const ws_input pages_index_ws_inputs[] = {\n    { \"input\", \"query\", 0 },\n    { \"change\", \"user.email\", 1 },\n};"));
+        assert!(code.contains("static const ws_source pages_index_ws_binding_0_sources[] = { { WS_SOURCE_REQUEST, 0 }, { WS_SOURCE_INPUT, 1 } };"));
+        assert!(generate("<p>static</p>").contains("static const ws_input* pages_index_ws_inputs = (void*)0;"));
+    }
+
+    #[test]
+    fn generate_validations() {
+        let code = generate("{SET user = 'x'}{VALIDATE user.email REQUIRED 'Please enter your email' EMAIL AS emailError}\
+            {VALIDATE user.age MIN -1.5 MAX 130}{VALIDATE user.zip PATTERN '\\d{5}'}");
+
+        assert!(code.contains("static const ws_rule pages_index_ws_validation_0_rules[] = {\n    { WS_RULE_REQUIRED, 0.0, (void*)0, \"Please enter your email\" },\n    { WS_RULE_EMAIL, 0.0, (void*)0, \"Invalid email address\" },\n};"));
+        assert!(code.contains("    { WS_RULE_MIN, -1.5, (void*)0, \"At least -1.5\" },\n    { WS_RULE_MAX, 130.0, (void*)0, \"At most 130\" },"));
+        assert!(code.contains("    { WS_RULE_PATTERN, 0.0, \"\\\\d{5}\", \"Invalid format\" },"));
+        assert!(code.contains("static const ws_validation pages_index_ws_validations[] = {\n    { \"user.email\", pages_index_ws_validation_0_rules, 2, \"emailError\" },\n    { \"user.age\", pages_index_ws_validation_1_rules, 2, (void*)0 },"));
+        assert!(generate("<p>static</p>").contains("static const ws_validation* pages_index_ws_validations = (void*)0;"));
+    }
+
+    #[test]
+    fn generate_routes() {
+        let mut sources = LinkedList::new();
+        sources.push_back(("pages/index.ws".to_string(), "{IMPORT './user.ws' AS user}{IMPORT './404.ws' AS missing}\
+            <main>{ROUTE '/users/:id/:tab' USE user}{ROUTE FALLBACK USE missing}</main>"));
+        sources.push_back(("pages/user.ws".to_string(), "{PARAMS id}<h1>{id}</h1>"));
+        sources.push_back(("pages/404.ws".to_string(), "<h1>Not found</h1>"));
+        let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+        let mut output = OutputComponent::new();
+        output.generate_output(&model);
+        let code = output.finish();
+
+        assert!(code.contains("static const char* pages_index_ws_route_0_params[] = { \"id\", \"tab\" };"));
+        assert!(code.contains("static const ws_route pages_index_ws_routes[] = {\n    { \"/users/:id/:tab\", \"pages/user.ws\", (void*)0, pages_index_ws_route_0_params, 2 },\n    { (void*)0, \"pages/404.ws\", (void*)0, (void*)0, 0 },\n};"));
+        assert!(code.contains("static const ws_source pages_index_ws_binding_0_sources[] = { { WS_SOURCE_ROUTE, 0 } };"));
+        assert!(code.contains("static const ws_route* pages_user_ws_routes = (void*)0;"));
+    }
+
+    #[test]
+    fn generate_chunks() {
+        let mut sources = LinkedList::new();
+        sources.push_back(("pages/index.ws".to_string(), "{IMPORT './user.ws' AS user}{IMPORT './404.ws' AS missing}\
+            <main>{ROUTE '/users/:id' USE user}{ROUTE FALLBACK USE missing}</main>"));
+        sources.push_back(("pages/user.ws".to_string(), "{PARAMS id}{IMPORT './avatar.ws' AS avatar}{INSERT avatar}{GET user FROM '/api/user'}"));
+        sources.push_back(("pages/avatar.ws".to_string(), "<img>"));
+        sources.push_back(("pages/404.ws".to_string(), "{IMPORT './avatar.ws' AS avatar}{INSERT avatar}<h1>Not found</h1>"));
+        let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+        let chunks = SplitterComponent::new().split(&model);
+
+        let modules: Vec<(String, String)> = chunks.iter().map(|chunk| {
+            let mut output = OutputComponent::new();
+            output.set_chunks(&chunks);
+            output.generate_chunk(&model, chunk);
+            (chunk_name(chunk), output.finish())
+        }).collect();
+        let names: Vec<&str> = modules.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["core", "pages_user_ws", "pages_404_ws"]);
+
+        let (core, user) = (&modules[0].1, &modules[1].1);
+        assert!(core.contains("const char* WS_BASE_URL = \"\";"));
+        assert!(core.contains("char* pages_avatar_ws();"));
+        assert!(!core.contains("pages_user_ws_request_0"));
+        assert!(core.contains("    { \"/users/:id\", \"pages/user.ws\", \"pages_user_ws\", pages_index_ws_route_0_params, 1 },\n    { (void*)0, \"pages/404.ws\", \"pages_404_ws\", (void*)0, 0 },"));
+        assert!(user.starts_with("#include \"dom.h\"\nchar* pages_avatar_ws();\nchar* pages_user_ws();\n"));
+        assert!(user.contains("static const ws_request pages_user_ws_request_0 = {"));
+        assert!(!user.contains("WS_BASE_URL"));
+    }
+}
@@ -0,0 +1,2074 @@
+use std::{collections::{LinkedList, linked_list}, iter::Peekable, time::Duration};
+
+use crate::{compiler::{CompilationError, CompilationState, CompilationWarning, symbols::{Definition, ScopeKind, SymbolKind, SymbolTable}, model::{BindOperation, BlockOperation, CallOperation, CallParam, Comparison, ConditionalOperation, Data, Expression, ErrorState, EventAction, EventOperation, ExtendsDeclaration, FetchOperation, Filter, Header, HttpMethod, ImportDeclaration, InsertOperation, LoopOperation, Operand, PrintOperation, QueryParam, RequestBody, RouteOperation, Rule, SchemaReference, SetOperation, SlotContent, SlotOperation, StreamMode, SubscribeOperation, Template, TemplateOperation, Transport, UnresolvedVariable, ValidateOperation, ValidationRule, VariableReference}}, pattern::Pattern, strings::stringview::StringView};
+use super::scanner::{FunctionName, KeyWordName, Token, TokenModel, TokenType};
+
+type Words = Peekable<linked_list::IntoIter<Token>>;
+
+/// Instruction opening a block which is closed by `{END}`
+enum BlockKind {
+	/// `{INSERT ...}` followed by `{FILL ...}` blocks
+	Call(CallOperation),
+	/// `{FILL name}` inside of a call
+	Fill(String),
+	/// `{BLOCK name}`, region which can be overridden by extending templates
+	Block(String),
+	/// `{IF condition}`, holds the operations of the first branch once `{ELSE}` has been reached
+	If(Expression, Option<LinkedList<TemplateOperation>>),
+	/// `{FOR item IN items}`
+	For(VariableReference, Data),
+	/// request and the branch which is currently parsed.\
+	/// Until one of `{LOADING}`, `{EMPTY}` or `{ERROR}` is reached, it is not known if the request has states:
+	/// without them the block is dissolved (see `close_pending_fetches`) and does not need an `{END}`.
+	Fetch(Box<FetchOperation>, FetchBranch)
+}
+
+/// Branch of a request with states
+#[derive(Clone, Copy, PartialEq)]
+enum FetchBranch {
+	Content,
+	Loading,
+	Empty,
+	Error
+}
+
+/// Open block and the operations collected inside of it
+struct OpenBlock {
+	kind: BlockKind,
+	operations: LinkedList<TemplateOperation>,
+	/// opening instruction, used for error reporting
+	view: StringView
+}
+
+impl OpenBlock {
+	fn new(kind: BlockKind, view: &StringView) -> OpenBlock {
+		Self {
+			kind,
+			operations: LinkedList::new(),
+			view: view.clone()
+		}
+	}
+
+	/// Scope opened by the block, variables defined inside of it are not visible outside of it
+	fn scope(&self) -> Option<ScopeKind> {
+		match self.kind {
+			BlockKind::Call(_) => None,
+			BlockKind::Fill(_) | BlockKind::Block(_) | BlockKind::If(..) => Some(ScopeKind::Block),
+			BlockKind::For(..) => Some(ScopeKind::Loop),
+			// the content belongs to the surrounding scope, so it does not change if the block is dissolved
+			BlockKind::Fetch(_, FetchBranch::Content) => None,
+			BlockKind::Fetch(..) => Some(ScopeKind::Block)
+		}
+	}
+
+	/// Request which might still be dissolved
+	fn is_pending_fetch(&self) -> bool {
+		matches!(&self.kind, BlockKind::Fetch(fetch, FetchBranch::Content) if !fetch.has_states())
+	}
+}
+
+/// # Resolves the id of an imported template (or another file referenced by a template)
+/// Paths are relative to the importing template, e.g. `./card.ws` imported by `pages/index.ws` is `pages/card.ws`.
+fn resolve_template_id(importer: &str, path: &str) -> String {
+	let mut segments: Vec<&str> = importer.split('/').collect();
+	// drop file name of the importing template
+	segments.pop();
+	for segment in path.split('/') {
+		match segment {
+			""|"." => {},
+			".." => { segments.pop(); },
+			_ => segments.push(segment)
+		}
+	}
+	segments.join("/")
+}
+
+pub struct ParserComponent {
+	template: Template,
+	state: CompilationState,
+	/// currently open blocks, innermost last
+	open_blocks: LinkedList<OpenBlock>,
+	/// variables visible at the current position
+	symbols: SymbolTable,
+	/// names of all `{BLOCK ...}`s of the template
+	block_names: LinkedList<String>,
+	/// names of all `{SLOT ...}`s of the template
+	slot_names: LinkedList<String>,
+	/// set as soon as the template contains anything besides imports and whitespace
+	has_content: bool,
+	/// set once the `{PARAMS ...}` header has been parsed
+	has_params: bool,
+	/// paths of all `{ROUTE ...}`s of the template with parameter names removed, e.g. `/users/:`, `None` for the fallback
+	routes: Vec<Option<String>>
+}
+
+impl ParserComponent {
+	pub fn new(id: String) -> ParserComponent {
+		Self {
+			template: Template::new(id),
+			state: CompilationState::new(),
+			open_blocks: LinkedList::new(),
+			symbols: SymbolTable::new(),
+			block_names: LinkedList::new(),
+			slot_names: LinkedList::new(),
+			has_content: false,
+			has_params: false,
+			routes: Vec::new()
+		}
+	}
+
+	/// # Converts tmodel into template
+	/// Parsing stops at the first error.
+	pub fn parse(&mut self, tmodel: TokenModel) {
+		let mut tokens = tmodel.tokenstream.into_iter().peekable();
+		while let Some(tok) = tokens.next() {
+			if let Err(err) = self.parse_token(tok, &mut tokens) {
+				self.state.error(err);
+				return;
+			}
+		}
+
+		self.close_pending_fetches();
+		if let Some(block) = self.open_blocks.back() {
+			self.state.error(
+				CompilationError {
+					name: "ERR_UNCLOSED_BLOCK".to_string(),
+					desc: "block does not have an end".to_string(),
+					cause: "template ends inside of block".to_string(),
+					suggestion: "insert '{END}' to close the block".to_string(),
+					position: None,
+					view: Some(block.view.clone())
+				}
+			);
+		}
+	}
+
+	pub fn take_warnings(&mut self) -> LinkedList<CompilationWarning> {
+		self.state.take_warnings()
+	}
+
+	/// ParserComponent is consumed and its template returned
+	pub fn finish(mut self) -> Result<Template, CompilationError> {
+		self.template.variables = self.symbols.into_template_scope();
+		self.state.finish(self.template)
+	}
+
+	fn parse_token(&mut self, tok: Token, tokens: &mut Words) -> Result<(), CompilationError> {
+		// calls only contain {FILL ...} blocks
+		if let Some(OpenBlock { kind: BlockKind::Call(_), .. }) = self.open_blocks.back() {
+			if !ParserComponent::is_whitespace(&tok) && !tok.is_function(FunctionName::FILL) && !tok.is_function(FunctionName::END) {
+				return Err(
+					CompilationError {
+						name: "ERR_CONTENT_OUTSIDE_OF_FILL".to_string(),
+						desc: "cannot pass content to template".to_string(),
+						cause: "content of a template call must be placed in a slot".to_string(),
+						suggestion: "enclose the content in {FILL name} ... {END}".to_string(),
+						position: None,
+						view: Some(tok.view)
+					}
+				);
+			}
+		}
+
+		// extending templates only contain {BLOCK ...}s which override the extended template
+		if self.template.extends.is_some() && self.open_blocks.is_empty() && !ParserComponent::is_allowed_outside_of_blocks(&tok) {
+			return Err(
+				CompilationError {
+					name: "ERR_CONTENT_OUTSIDE_OF_BLOCK".to_string(),
+					desc: "cannot add content to extending template".to_string(),
+					cause: "content of an extending template must be placed in a block".to_string(),
+					suggestion: "enclose the content in {BLOCK name} ... {END}".to_string(),
+					position: None,
+					view: Some(tok.view)
+				}
+			);
+		}
+
+		match tok.ttype {
+			TokenType::PlainText => {
+				if ParserComponent::is_whitespace(&tok) {
+					// whitespace between slots of a call is insignificant
+					if let Some(OpenBlock { kind: BlockKind::Call(_), .. }) = self.open_blocks.back() {
+						return Ok(());
+					}
+				} else {
+					self.has_content = true;
+				}
+				self.push_operation(TemplateOperation::Print(PrintOperation {
+					content: Data::Value(tok.view.cut().to_string())
+				}));
+				Ok(())
+			},
+			TokenType::Instruction(instruction) => self.parse_instruction(tok.view, instruction.words.into_iter().peekable(), tokens),
+			_ => Err(ParserComponent::unexpected_word(&tok, "plain text or instruction"))
+		}
+	}
+
+	fn is_whitespace(tok: &Token) -> bool {
+		matches!(tok.ttype, TokenType::PlainText) && tok.view.cut().trim().is_empty()
+	}
+
+	/// Whitespace and headers are allowed outside of blocks in extending templates
+	fn is_allowed_outside_of_blocks(tok: &Token) -> bool {
+		match &tok.ttype {
+			TokenType::PlainText => ParserComponent::is_whitespace(tok),
+			TokenType::Instruction(instruction) => matches!(
+				instruction.words.front().map(|word| &word.ttype),
+				Some(TokenType::Function(FunctionName::IMPORT|FunctionName::EXTENDS|FunctionName::BLOCK)) | Some(TokenType::KeyWord(KeyWordName::PARAMS))
+			),
+			_ => false
+		}
+	}
+
+	/// Opens block and the scope it introduces
+	fn open_block(&mut self, block: OpenBlock) {
+		if let Some(kind) = block.scope() {
+			self.symbols.enter(kind);
+		}
+		self.open_blocks.push_back(block);
+	}
+
+	/// Adds operation to the innermost open block or the template itself
+	fn push_operation(&mut self, operation: TemplateOperation) {
+		match self.open_blocks.back_mut() {
+			Some(block) => block.operations.push_back(operation),
+			None => self.template.operations.push_back(operation)
+		}
+	}
+
+	/// # Parses a single instruction
+	/// * `view` - whole instruction including `{` and `}`
+	/// * `words` - words of the instruction
+	/// * `tokens` - following tokens of the template
+	fn parse_instruction(&mut self, view: StringView, mut words: Words, tokens: &mut Words) -> Result<(), CompilationError> {
+		let first = match words.next() {
+			Some(word) => word,
+			None => return Err(
+				CompilationError {
+					name: "ERR_EMPTY_INSTRUCTION".to_string(),
+					desc: "cannot parse instruction".to_string(),
+					cause: "instruction does not contain any words".to_string(),
+					suggestion: "remove the instruction or escape its brackets using '{{' and '}}'".to_string(),
+					position: None,
+					view: Some(view)
+				}
+			)
+		};
+
+		// states are written into the client as markup with inserted values (see `OutputComponent`)
+		let in_state = matches!(self.open_blocks.back().map(|block| &block.kind), Some(BlockKind::Fetch(_, branch)) if *branch != FetchBranch::Content);
+		if in_state && !matches!(first.ttype, TokenType::Identifier|TokenType::Function(FunctionName::END|FunctionName::LOADING|FunctionName::EMPTY|FunctionName::ERROR)) {
+			return Err(
+				CompilationError {
+					name: "ERR_INSTRUCTION_IN_STATE".to_string(),
+					desc: "cannot parse instruction".to_string(),
+					cause: format!("states of a request only contain markup and inserted values, but found '{}'", first.view.cut()),
+					suggestion: "move the instruction into the content of the request or in front of it".to_string(),
+					position: None,
+					view: Some(view)
+				}
+			);
+		}
+
+		match &first.ttype {
+			TokenType::Function(FunctionName::IMPORT) => self.parse_import(&view, &mut words)?,
+			TokenType::Function(FunctionName::INSERT) => {
+				self.has_content = true;
+				self.parse_call(&view, &mut words, tokens)?
+			},
+			TokenType::Function(FunctionName::SLOT) => {
+				self.has_content = true;
+				self.parse_slot(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::FILL) => self.parse_fill(&view, &mut words)?,
+			TokenType::Function(FunctionName::END) => self.parse_end(&view)?,
+			TokenType::Function(FunctionName::EXTENDS) => self.parse_extends(&view, &mut words)?,
+			TokenType::Function(FunctionName::BLOCK) => {
+				self.has_content = true;
+				self.parse_block(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::SET) => {
+				self.has_content = true;
+				self.parse_set(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::IF) => {
+				self.has_content = true;
+				self.parse_if(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::ELSE) => self.parse_else(&view)?,
+			TokenType::Function(FunctionName::LOADING) => self.parse_fetch_branch(FetchBranch::Loading, &first, &view, &mut words)?,
+			TokenType::Function(FunctionName::EMPTY) => self.parse_fetch_branch(FetchBranch::Empty, &first, &view, &mut words)?,
+			TokenType::Function(FunctionName::ERROR) => self.parse_fetch_branch(FetchBranch::Error, &first, &view, &mut words)?,
+			TokenType::Function(FunctionName::FOR) => {
+				self.has_content = true;
+				self.parse_for(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::GET) => {
+				self.has_content = true;
+				self.parse_fetch(&view, &mut words)?
+			},
+			TokenType::Function(function @ (FunctionName::POST|FunctionName::PUT|FunctionName::PATCH|FunctionName::DELETE)) => {
+				self.has_content = true;
+				self.parse_request(ParserComponent::request_method(function), &view, &mut words)?
+			},
+			TokenType::Function(FunctionName::SUBSCRIBE) => {
+				self.has_content = true;
+				self.parse_subscribe(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::ON) => {
+				self.has_content = true;
+				self.parse_on(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::BIND) => {
+				self.has_content = true;
+				self.parse_bind(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::VALIDATE) => {
+				self.has_content = true;
+				self.parse_validate(&view, &mut words)?
+			},
+			TokenType::Function(FunctionName::ROUTE) => {
+				self.has_content = true;
+				self.parse_route(&view, &mut words)?
+			},
+			TokenType::KeyWord(KeyWordName::PARAMS) => self.parse_params_declaration(&first, &view, &mut words)?,
+			TokenType::Identifier => {
+				self.has_content = true;
+				let content = self.parse_data(first)?;
+				self.push_operation(TemplateOperation::Insert(InsertOperation { content, binding: None }));
+			},
+			TokenType::Function(_) => return Err(
+				CompilationError {
+					name: "ERR_UNSUPPORTED_INSTRUCTION".to_string(),
+					desc: "cannot parse instruction".to_string(),
+					cause: format!("instruction '{}' is not supported yet", first.view.cut()),
+					suggestion: "remove the instruction".to_string(),
+					position: None,
+					view: Some(view)
+				}
+			),
+			_ => return Err(ParserComponent::unexpected_word(&first, "function or identifier"))
+		}
+
+		ParserComponent::expect_instruction_end(&mut words)
+	}
+
+	/// `{IMPORT 'path' AS alias}`
+	fn parse_import(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let path = ParserComponent::next_word(words, view, "path of imported template")?;
+		let path = match &path.ttype {
+			TokenType::StringLiteral(literal) => literal.cut().to_string(),
+			_ => return Err(ParserComponent::unexpected_word(&path, "path of imported template as string literal"))
+		};
+		ParserComponent::expect_keyword(words, view, KeyWordName::AS)?;
+		let alias = ParserComponent::expect_name(words, view, "alias of imported template")?;
+
+		if self.template.get_import_by_alias(alias.view.cut()).is_some() {
+			return Err(
+				CompilationError {
+					name: "ERR_DUPLICATE_IMPORT".to_string(),
+					desc: "cannot import template".to_string(),
+					cause: format!("alias '{}' is already used by another import", alias.view.cut()),
+					suggestion: "choose another alias".to_string(),
+					position: None,
+					view: Some(alias.view)
+				}
+			);
+		}
+
+		self.template.imports.push_back(ImportDeclaration {
+			template: resolve_template_id(&self.template.id, &path),
+			alias: alias.view.cut().to_string()
+		});
+		Ok(())
+	}
+
+	/// # Parses template call
+	/// `{INSERT alias PARAMS name=value, ...}`\
+	/// If the call is directly followed by `{FILL name}` it opens a block, which passes markup into slots of the called template:
+	/// `{INSERT alias} {FILL name} ... {END} {END}`
+	fn parse_call(&mut self, view: &StringView, words: &mut Words, tokens: &mut Words) -> Result<(), CompilationError> {
+		let template = self.expect_imported_template(words, view, "cannot insert template")?;
+
+		let mut params = LinkedList::new();
+		if ParserComponent::next_is_keyword(words, KeyWordName::PARAMS) {
+			words.next();
+			for (name, value) in self.parse_assignments(view, words, "parameter", ParserComponent::duplicate_param)? {
+				params.push_back(CallParam { name, value });
+			}
+		}
+
+		ParserComponent::expect_instruction_end(words)?;
+		let call = CallOperation {
+			template: Data::Value(template),
+			params,
+			slots: LinkedList::new(),
+			view: view.clone(),
+			binding: None
+		};
+
+		// look behind whitespace for {FILL ...}
+		let mut whitespace = LinkedList::new();
+		while let Some(tok) = tokens.next_if(ParserComponent::is_whitespace) {
+			whitespace.push_back(tok);
+		}
+
+		if tokens.peek().is_some_and(|tok| tok.is_function(FunctionName::FILL)) {
+			self.open_block(OpenBlock::new(BlockKind::Call(call), view));
+		} else {
+			self.push_operation(TemplateOperation::Call(call));
+			for tok in whitespace {
+				self.parse_token(tok, tokens)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Expects alias of an imported template and returns the id of the template
+	fn expect_imported_template(&self, words: &mut Words, view: &StringView, desc: &str) -> Result<String, CompilationError> {
+		let alias = ParserComponent::expect_name(words, view, "alias of imported template")?;
+		match self.template.get_import_by_alias(alias.view.cut()) {
+			Some(import) => Ok(import.template.clone()),
+			None => Err(
+				CompilationError {
+					name: "ERR_UNKNOWN_TEMPLATE".to_string(),
+					desc: desc.to_string(),
+					cause: format!("template '{}' has not been imported", alias.view.cut()),
+					suggestion: format!("import the template using {{IMPORT './path.ws' AS {}}}", alias.view.cut()),
+					position: None,
+					view: Some(alias.view)
+				}
+			)
+		}
+	}
+
+	/// # Parses route
+	/// `{ROUTE 'path' USE alias}` renders the imported template in place if the current path matches,
+	/// `{ROUTE FALLBACK USE alias}` if no route of the template matches.
+	/// Segments like `:id` match any segment and pass it to the parameter of the same name.
+	fn parse_route(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let word = ParserComponent::next_word(words, view, "path or 'FALLBACK'")?;
+		let path = match &word.ttype {
+			TokenType::StringLiteral(literal) => Some(ParserComponent::check_route_path(literal.cut(), &word)?),
+			TokenType::KeyWord(KeyWordName::FALLBACK) => None,
+			_ => return Err(ParserComponent::unexpected_word(&word, "path or 'FALLBACK'"))
+		};
+		ParserComponent::expect_keyword(words, view, KeyWordName::USE)?;
+		let template = self.expect_imported_template(words, view, "cannot route to template")?;
+
+		// parameters match any segment, so routes differing in parameter names only are the same
+		let key = path.as_ref().map(|path| path.split('/')
+			.filter(|segment| !segment.is_empty())
+			.map(|segment| if segment.starts_with(':') { ":" } else { segment })
+			.collect::<Vec<&str>>()
+			.join("/"));
+		if self.routes.contains(&key) {
+			return Err(
+				CompilationError {
+					name: "ERR_DUPLICATE_ROUTE".to_string(),
+					desc: "cannot route to template".to_string(),
+					cause: match &path {
+						Some(path) => format!("paths like '{}' are already routed by the template", path),
+						None => "the template already has a fallback".to_string()
+					},
+					suggestion: "remove one of the routes, only the first one would ever be rendered".to_string(),
+					position: None,
+					view: Some(word.view)
+				}
+			);
+		}
+		self.routes.push(key);
+
+		self.push_operation(TemplateOperation::Route(RouteOperation { path, template, index: 0, binding: None, view: view.clone() }));
+		Ok(())
+	}
+
+	/// Paths start with '/' and consist of literal segments and parameters like `:id`, which are named once each
+	fn check_route_path(path: &str, word: &Token) -> Result<String, CompilationError> {
+		let mut params: Vec<&str> = Vec::new();
+		let mut cause = match path.starts_with('/') {
+			true => None,
+			false => Some("paths of routes start with '/'".to_string())
+		};
+		for segment in path.split('/') {
+			if cause.is_some() {
+				break;
+			}
+			if segment.contains(['?', '#', ' ']) {
+				cause = Some(format!("segment '{}' contains '?', '#' or whitespace", segment));
+			} else if let Some(name) = segment.strip_prefix(':') {
+				if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+					cause = Some(format!("parameter '{}' must be named using letters, digits and '_'", segment));
+				} else if params.contains(&name) {
+					cause = Some(format!("parameter '{}' is used more than once", name));
+				}
+				params.push(name);
+			}
+		}
+		match cause {
+			None => Ok(path.to_string()),
+			Some(cause) => Err(
+				CompilationError {
+					name: "ERR_INVALID_ROUTE".to_string(),
+					desc: "cannot route to template".to_string(),
+					cause,
+					suggestion: "use a path like '/users/:id'".to_string(),
+					position: None,
+					view: Some(word.view.clone())
+				}
+			)
+		}
+	}
+
+	/// `{SLOT name}`
+	fn parse_slot(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let name = ParserComponent::expect_name(words, view, "slot name")?;
+		if self.slot_names.iter().any(|slot| slot == name.view.cut()) {
+			return Err(ParserComponent::duplicate_slot(name));
+		}
+
+		self.slot_names.push_back(name.view.cut().to_string());
+		self.push_operation(TemplateOperation::Slot(SlotOperation {
+			name: name.view.cut().to_string()
+		}));
+		Ok(())
+	}
+
+	/// # Parses the `{EXTENDS 'path'}` header
+	/// Must be declared once, before any content of the template.
+	fn parse_extends(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		if self.has_content || self.template.extends.is_some() {
+			return Err(
+				CompilationError {
+					name: "ERR_MISPLACED_EXTENDS".to_string(),
+					desc: "cannot extend template".to_string(),
+					cause: "a template can only extend one other template, declared at its top".to_string(),
+					suggestion: "move {EXTENDS ...} to the top of the template and remove other ones".to_string(),
+					position: None,
+					view: Some(view.clone())
+				}
+			);
+		}
+
+		let path = ParserComponent::next_word(words, view, "path of extended template")?;
+		match &path.ttype {
+			TokenType::StringLiteral(literal) => {
+				self.template.extends = Some(ExtendsDeclaration {
+					template: resolve_template_id(&self.template.id, literal.cut()),
+					view: view.clone()
+				});
+				Ok(())
+			},
+			_ => Err(ParserComponent::unexpected_word(&path, "path of extended template as string literal"))
+		}
+	}
+
+	/// `{BLOCK name}`
+	fn parse_block(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let name = ParserComponent::expect_name(words, view, "block name")?;
+		if self.block_names.iter().any(|block| block == name.view.cut()) {
+			return Err(
+				CompilationError {
+					name: "ERR_DUPLICATE_BLOCK".to_string(),
+					desc: "cannot declare block".to_string(),
+					cause: format!("block '{}' is declared more than once", name.view.cut()),
+					suggestion: format!("remove the second '{}' or choose another name", name.view.cut()),
+					position: None,
+					view: Some(name.view)
+				}
+			);
+		}
+
+		self.block_names.push_back(name.view.cut().to_string());
+		self.open_block(OpenBlock::new(BlockKind::Block(name.view.cut().to_string()), view));
+		Ok(())
+	}
+
+	/// `{FILL name}`, only allowed directly inside of a call
+	fn parse_fill(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		self.close_pending_fetches();
+		let name = ParserComponent::expect_name(words, view, "slot name")?;
+		match self.open_blocks.back() {
+			Some(OpenBlock { kind: BlockKind::Call(call), .. }) => {
+				if call.slots.iter().any(|slot| slot.name == name.view.cut()) {
+					return Err(ParserComponent::duplicate_slot(name));
+				}
+			},
+			_ => return Err(
+				CompilationError {
+					name: "ERR_FILL_OUTSIDE_OF_CALL".to_string(),
+					desc: "cannot fill slot".to_string(),
+					cause: "slots can only be filled inside of a template call".to_string(),
+					suggestion: "place {FILL name} ... {END} directly after {INSERT template}".to_string(),
+					position: None,
+					view: Some(view.clone())
+				}
+			)
+		}
+
+		self.open_block(OpenBlock::new(BlockKind::Fill(name.view.cut().to_string()), view));
+		Ok(())
+	}
+
+	/// `{END}` closes the innermost open block
+	fn parse_end(&mut self, view: &StringView) -> Result<(), CompilationError> {
+		self.close_pending_fetches();
+		let block = match self.open_blocks.pop_back() {
+			Some(block) => block,
+			None => return Err(
+				CompilationError {
+					name: "ERR_UNEXPECTED_END".to_string(),
+					desc: "cannot close block".to_string(),
+					cause: "there is no open block to close".to_string(),
+					suggestion: "remove '{END}'".to_string(),
+					position: None,
+					view: Some(view.clone())
+				}
+			)
+		};
+		if block.scope().is_some() {
+			self.symbols.leave();
+		}
+
+		match block.kind {
+			BlockKind::Call(call) => self.push_operation(TemplateOperation::Call(call)),
+			BlockKind::Block(name) => self.push_operation(TemplateOperation::Block(BlockOperation {
+				name,
+				operations: block.operations,
+				view: block.view
+			})),
+			BlockKind::Fill(name) => {
+				if let Some(OpenBlock { kind: BlockKind::Call(call), .. }) = self.open_blocks.back_mut() {
+					call.slots.push_back(SlotContent {
+						name,
+						operations: block.operations
+					});
+				}
+			},
+			BlockKind::If(condition, first_branch) => self.push_operation(TemplateOperation::If(match first_branch {
+				Some(operations) => ConditionalOperation { condition, operations, alternative: block.operations, binding: None },
+				None => ConditionalOperation { condition, operations: block.operations, alternative: LinkedList::new(), binding: None }
+			})),
+			BlockKind::For(item, items) => self.push_operation(TemplateOperation::Loop(LoopOperation {
+				item,
+				items,
+				operations: block.operations,
+				binding: None
+			})),
+			BlockKind::Fetch(mut fetch, branch) => {
+				ParserComponent::store_fetch_branch(&mut fetch, branch, block.operations);
+				self.push_operation(TemplateOperation::Fetch(fetch));
+			}
+		}
+		Ok(())
+	}
+
+	/// # Opens block of a request
+	/// The request is dissolved unless it turns out to have states.
+	fn push_fetch(&mut self, fetch: FetchOperation, view: &StringView) {
+		self.open_block(OpenBlock::new(BlockKind::Fetch(Box::new(fetch), FetchBranch::Content), view));
+	}
+
+	/// # Dissolves innermost requests without states
+	/// The request is added to the enclosing block, followed by the operations collected after it.
+	fn close_pending_fetches(&mut self) {
+		while self.open_blocks.back().is_some_and(OpenBlock::is_pending_fetch) {
+			let block = self.open_blocks.pop_back().unwrap();
+			if let BlockKind::Fetch(fetch, _) = block.kind {
+				self.push_operation(TemplateOperation::Fetch(fetch));
+			}
+			for operation in block.operations {
+				self.push_operation(operation);
+			}
+		}
+	}
+
+	/// Moves operations collected for branch into the request
+	fn store_fetch_branch(fetch: &mut FetchOperation, branch: FetchBranch, operations: LinkedList<TemplateOperation>) {
+		match branch {
+			FetchBranch::Content => fetch.operations = operations,
+			FetchBranch::Loading => fetch.loading = Some(operations),
+			FetchBranch::Empty => fetch.empty = Some(operations),
+			FetchBranch::Error => if let Some(error) = &mut fetch.error {
+				error.operations = operations;
+			}
+		}
+	}
+
+	/// # Switches the innermost request to another branch
+	/// `{LOADING}`, `{EMPTY}` and `{ERROR name}`, each may be given once per request.
+	/// The error branch has its own scope containing the error variable.
+	fn parse_fetch_branch(&mut self, next: FetchBranch, keyword: &Token, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let error_name = match next {
+			FetchBranch::Error => Some(ParserComponent::expect_name(words, view, "name of error variable")?),
+			_ => None
+		};
+
+		let block = match self.open_blocks.back_mut() {
+			Some(block) if matches!(block.kind, BlockKind::Fetch(..)) => block,
+			_ => return Err(
+				CompilationError {
+					name: "ERR_STATE_OUTSIDE_OF_REQUEST".to_string(),
+					desc: format!("cannot use {{{}}}", keyword.view.cut()),
+					cause: "states can only be declared directly inside of a request".to_string(),
+					suggestion: format!("place {{{}}} after {{GET ...}} and its content, or close the inner block before it", keyword.view.cut()),
+					position: None,
+					view: Some(view.clone())
+				}
+			)
+		};
+		let BlockKind::Fetch(fetch, branch) = &mut block.kind else { unreachable!() };
+
+		let declared = match next {
+			FetchBranch::Loading => fetch.loading.is_some(),
+			FetchBranch::Empty => fetch.empty.is_some(),
+			_ => fetch.error.is_some()
+		};
+		if declared || *branch == next {
+			return Err(
+				CompilationError {
+					name: "ERR_DUPLICATE_STATE".to_string(),
+					desc: format!("cannot use {{{}}}", keyword.view.cut()),
+					cause: format!("request already has a {} branch", keyword.view.cut()),
+					suggestion: format!("remove the second {{{}}}", keyword.view.cut()),
+					position: None,
+					view: Some(view.clone())
+				}
+			);
+		}
+
+		let previous = *branch;
+		ParserComponent::store_fetch_branch(fetch, previous, std::mem::take(&mut block.operations));
+		if let Some(name) = &error_name {
+			fetch.error = Some(ErrorState {
+				variable: VariableReference::new(name.view.cut()),
+				operations: LinkedList::new()
+			});
+		}
+		*branch = next;
+
+		if previous != FetchBranch::Content {
+			self.symbols.leave();
+		}
+		self.symbols.enter(ScopeKind::Block);
+		if let Some(name) = error_name {
+			self.define_variable(&name, SymbolKind::Variable)?;
+		}
+		Ok(())
+	}
+
+	/// # Parses `{SET name = expression}`
+	/// The expression is evaluated before the variable is (re)defined, so it may use the previous value.
+	fn parse_set(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let name = ParserComponent::expect_name(words, view, "name of variable")?;
+		ParserComponent::expect_symbol(words, view, '=')?;
+		let value = self.parse_expression(view, words)?;
+
+		let target = self.define_variable(&name, SymbolKind::Variable)?;
+		self.push_operation(TemplateOperation::Set(SetOperation { target, value }));
+		Ok(())
+	}
+
+	/// `{IF condition}`, the condition may compare two expressions, e.g. `{IF items | length > 0}`
+	fn parse_if(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let mut condition = self.parse_expression(view, words)?;
+		if let Some(comparison) = ParserComponent::parse_comparison(words)? {
+			condition.comparison = Some((comparison, self.parse_expression(view, words)?.operands));
+		}
+		self.open_block(OpenBlock::new(BlockKind::If(condition, None), view));
+		Ok(())
+	}
+
+	/// `{ELSE}` switches the innermost `{IF}` to its second branch, which has its own scope
+	fn parse_else(&mut self, view: &StringView) -> Result<(), CompilationError> {
+		self.close_pending_fetches();
+		match self.open_blocks.back_mut() {
+			Some(block) if matches!(block.kind, BlockKind::If(_, None)) => {
+				if let BlockKind::If(_, first_branch) = &mut block.kind {
+					*first_branch = Some(std::mem::take(&mut block.operations));
+				}
+				self.symbols.leave();
+				self.symbols.enter(ScopeKind::Block);
+				Ok(())
+			},
+			_ => Err(
+				CompilationError {
+					name: "ERR_UNEXPECTED_ELSE".to_string(),
+					desc: "cannot parse {ELSE}".to_string(),
+					cause: "{ELSE} must be placed directly inside of {IF} and only once".to_string(),
+					suggestion: "remove '{ELSE}' or close the inner block before it".to_string(),
+					position: None,
+					view: Some(view.clone())
+				}
+			)
+		}
+	}
+
+	/// `{FOR item IN items}`, the item is only visible inside of the loop
+	fn parse_for(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let item = ParserComponent::expect_name(words, view, "name of loop variable")?;
+		ParserComponent::expect_keyword(words, view, KeyWordName::IN)?;
+		let items = ParserComponent::next_word(words, view, "data to loop over")?;
+		let items = self.parse_data(items)?;
+
+		self.open_block(OpenBlock::new(BlockKind::For(VariableReference::new(item.view.cut()), items), view));
+		self.define_variable(&item, SymbolKind::LoopItem)?;
+		Ok(())
+	}
+
+	/// `{GET name FROM source PARAMS name=value, ... HEADERS name=value, ... WITH credentials}`
+	fn parse_fetch(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let (mut fetch, target) = self.read_fetch(view, words)?;
+		fetch.target = Some(self.define_variable(&target, SymbolKind::Variable)?);
+		self.push_fetch(fetch, view);
+		Ok(())
+	}
+
+	/// Reads `GET` request, returns it along with the name of its target, which is not defined yet
+	fn read_fetch(&mut self, view: &StringView, words: &mut Words) -> Result<(FetchOperation, Token), CompilationError> {
+		let target = ParserComponent::expect_name(words, view, "name of fetched variable")?;
+		ParserComponent::expect_keyword(words, view, KeyWordName::FROM)?;
+		let from = ParserComponent::next_word(words, view, "data source")?;
+		let from = self.parse_data(from)?;
+
+		let mut fetch = FetchOperation::new(HttpMethod::GET, from);
+		self.parse_request_options(&mut fetch, view, words)?;
+		Ok((fetch, target))
+	}
+
+	/// # Parses subscription to a stream
+	/// `{SUBSCRIBE name FROM url}` for WebSockets or `{SUBSCRIBE name FROM SSE url}` for server-sent events,
+	/// optionally followed by `APPEND` to collect all messages instead of keeping the latest one (`REPLACE`)
+	fn parse_subscribe(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let target = ParserComponent::expect_name(words, view, "name of subscribed variable")?;
+		ParserComponent::expect_keyword(words, view, KeyWordName::FROM)?;
+		let transport = if ParserComponent::next_is_keyword(words, KeyWordName::SSE) {
+			words.next();
+			Transport::EventSource
+		} else {
+			Transport::WebSocket
+		};
+		let from = ParserComponent::next_word(words, view, "url of stream")?;
+		if let TokenType::StringLiteral(url) = &from.ttype {
+			let url = url.cut();
+			let invalid = match transport {
+				Transport::WebSocket if url.starts_with("http://") || url.starts_with("https://") =>
+					Some(("a WebSocket url", "use a 'ws://' or 'wss://' url, or subscribe to server-sent events using 'FROM SSE'")),
+				Transport::EventSource if url.starts_with("ws://") || url.starts_with("wss://") =>
+					Some(("an url of server-sent events", "use an 'http://' or 'https://' url, or subscribe to the WebSocket without 'SSE'")),
+				_ => None
+			};
+			if let Some((kind, suggestion)) = invalid {
+				return Err(
+					CompilationError {
+						name: "ERR_INVALID_STREAM_URL".to_string(),
+						desc: "cannot parse subscription".to_string(),
+						cause: format!("'{}' is not {}", url, kind),
+						suggestion: suggestion.to_string(),
+						position: None,
+						view: Some(from.view.clone())
+					}
+				);
+			}
+		}
+		let from = self.parse_data(from)?;
+
+		let mode = if ParserComponent::next_is_keyword(words, KeyWordName::APPEND) {
+			words.next();
+			StreamMode::Append
+		} else {
+			if ParserComponent::next_is_keyword(words, KeyWordName::REPLACE) {
+				words.next();
+			}
+			StreamMode::Replace
+		};
+		ParserComponent::expect_instruction_end(words)?;
+
+		let target = self.define_variable(&target, SymbolKind::Variable)?;
+		self.push_operation(TemplateOperation::Subscribe(SubscribeOperation { target, transport, from, mode, index: 0 }));
+		Ok(())
+	}
+
+	/// # Parses request sending data
+	/// `{POST body TO url ... AS response}`
+	/// * the body is a value, a variable or `FORM 'id'` and may be omitted (e.g. `{DELETE TO url}`)
+	/// * options are the same as for `{GET}`
+	/// * the response is only bound to a variable if `AS` is given
+	fn parse_request(&mut self, method: HttpMethod, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let (mut fetch, target) = self.read_request(method, view, words)?;
+		if let Some(target) = target {
+			fetch.target = Some(self.define_variable(&target, SymbolKind::Variable)?);
+		}
+		self.push_fetch(fetch, view);
+		Ok(())
+	}
+
+	/// Reads request sending data, returns it along with the name of its target, which is not defined yet
+	fn read_request(&mut self, method: HttpMethod, view: &StringView, words: &mut Words) -> Result<(FetchOperation, Option<Token>), CompilationError> {
+		let body = if ParserComponent::next_is_keyword(words, KeyWordName::TO) {
+			None
+		} else if ParserComponent::next_is_keyword(words, KeyWordName::FORM) {
+			words.next();
+			let form = ParserComponent::next_word(words, view, "id of form")?;
+			match &form.ttype {
+				TokenType::StringLiteral(id) => Some(RequestBody::Form(id.cut().to_string())),
+				_ => return Err(ParserComponent::unexpected_word(&form, "id of form as string literal"))
+			}
+		} else {
+			let body = ParserComponent::next_word(words, view, "request body")?;
+			Some(RequestBody::Data(self.parse_data(body)?))
+		};
+
+		ParserComponent::expect_keyword(words, view, KeyWordName::TO)?;
+		let to = ParserComponent::next_word(words, view, "url")?;
+		let mut fetch = FetchOperation::new(method, self.parse_data(to)?);
+		fetch.body = body;
+		self.parse_request_options(&mut fetch, view, words)?;
+
+		if ParserComponent::next_is_keyword(words, KeyWordName::AS) {
+			words.next();
+			let name = ParserComponent::expect_name(words, view, "name of response variable")?;
+			return Ok((fetch, Some(name)));
+		}
+		Ok((fetch, None))
+	}
+
+	fn request_method(function: &FunctionName) -> HttpMethod {
+		match function {
+			FunctionName::POST => HttpMethod::POST,
+			FunctionName::PUT => HttpMethod::PUT,
+			FunctionName::PATCH => HttpMethod::PATCH,
+			FunctionName::DELETE => HttpMethod::DELETE,
+			_ => HttpMethod::GET
+		}
+	}
+
+	/// # Parses event handler
+	/// `{ON event CALL handler argument}` calls a handler of the client, the argument is optional.
+	/// `{ON event request}` sends a request written like `{GET ...}`, `{POST ...}` etc. whenever the event occurs.
+	/// The handler is placed inside of the tag of the listening element, e.g. `<button {ON click CALL refresh}>`.
+	/// Within the handler, `form` holds the fields of the form the element belongs to.
+	fn parse_on(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let event = ParserComponent::expect_name(words, view, "name of event")?;
+
+		self.symbols.enter(ScopeKind::Handler);
+		self.symbols.define(VariableReference::new("form"), SymbolKind::Param);
+		let action = self.parse_event_action(view, words);
+		self.symbols.leave();
+		let (mut action, target) = action?;
+
+		// the response is visible after the handler, like responses of other requests
+		if let (EventAction::Request(fetch), Some(target)) = (&mut action, target) {
+			fetch.target = Some(self.define_variable(&target, SymbolKind::Variable)?);
+		}
+		self.push_operation(TemplateOperation::On(EventOperation { event: event.view.cut().to_string(), action, index: 0 }));
+		Ok(())
+	}
+
+	/// # Parses two-way binding of a form control
+	/// `{BIND event TO path}`, placed inside of the tag of the control, e.g. `<input {BIND input TO user.email}>`.
+	/// The control shows the value at path and replaces it whenever the event occurs.
+	/// The root variable of the path becomes writable, parameters and loop items cannot be bound.
+	fn parse_bind(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let event = ParserComponent::expect_name(words, view, "name of event")?;
+		ParserComponent::expect_keyword(words, view, KeyWordName::TO)?;
+		let word = ParserComponent::next_word(words, view, "bound variable")?;
+		let word_view = word.view.clone();
+		let target = match &word.ttype {
+			TokenType::Identifier => self.parse_data(word)?,
+			_ => return Err(ParserComponent::unexpected_word(&word, "variable"))
+		};
+		let target = match target {
+			Data::Variable(target, _) => target,
+			_ => return Err(ParserComponent::unexpected_word(&Token::word_from_source(&word_view), "variable"))
+		};
+
+		let root = target.id.clone();
+		if let Some(symbol) = self.symbols.lookup(&root).filter(|symbol| symbol.kind != SymbolKind::Variable) {
+			return Err(
+				CompilationError {
+					name: "ERR_READ_ONLY_VARIABLE".to_string(),
+					desc: "cannot bind form control".to_string(),
+					cause: format!("'{}' is {} and cannot be changed", root, match symbol.kind {
+						SymbolKind::LoopItem => "the item of a loop",
+						_ => "given by the caller"
+					}),
+					suggestion: format!("copy the value into a variable using {{SET name = {}}} and bind that one", root),
+					position: None,
+					view: Some(word_view)
+				}
+			);
+		}
+		if !self.template.writable.iter().any(|variable| variable.id == root) {
+			self.template.writable.push_back(VariableReference::new(&root));
+		}
+
+		self.push_operation(TemplateOperation::Bind(BindOperation { event: event.view.cut().to_string(), target, index: 0 }));
+		Ok(())
+	}
+
+	/// # Parses validation rules of a value
+	/// `{VALIDATE path rule ... [AS error]}` using the rules `REQUIRED`, `EMAIL`, `MINLENGTH n`, `MAXLENGTH n`,
+	/// `PATTERN 'regex'`, `MIN n` and `MAX n`, each optionally followed by a custom message, e.g.
+	/// `{VALIDATE user.email REQUIRED 'Please enter your email' EMAIL AS emailError}`.
+	/// Empty values only violate `REQUIRED`. The error variable holds the message of the first violated rule.
+	fn parse_validate(&mut self, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let word = ParserComponent::next_word(words, view, "validated variable")?;
+		let word_view = word.view.clone();
+		let target = match &word.ttype {
+			TokenType::Identifier => self.parse_data(word)?,
+			_ => return Err(ParserComponent::unexpected_word(&word, "variable"))
+		};
+		let target = match target {
+			Data::Variable(target, _) => target,
+			_ => return Err(ParserComponent::unexpected_word(&Token::word_from_source(&word_view), "variable"))
+		};
+
+		let mut rules: Vec<ValidationRule> = Vec::new();
+		while let Some(Token { ttype: TokenType::KeyWord(keyword @ (KeyWordName::REQUIRED|KeyWordName::EMAIL|KeyWordName::MINLENGTH|KeyWordName::MAXLENGTH|KeyWordName::PATTERN|KeyWordName::MIN|KeyWordName::MAX)), .. }) = words.peek() {
+			let keyword = *keyword;
+			let name = words.next().unwrap();
+			let rule = match keyword {
+				KeyWordName::REQUIRED => Rule::Required,
+				KeyWordName::EMAIL => Rule::Email,
+				KeyWordName::MINLENGTH => Rule::MinLength(ParserComponent::parse_length(&ParserComponent::next_word(words, view, "length")?)?),
+				KeyWordName::MAXLENGTH => Rule::MaxLength(ParserComponent::parse_length(&ParserComponent::next_word(words, view, "length")?)?),
+				KeyWordName::MIN => Rule::Min(ParserComponent::parse_number(&ParserComponent::next_word(words, view, "number")?)?),
+				KeyWordName::MAX => Rule::Max(ParserComponent::parse_number(&ParserComponent::next_word(words, view, "number")?)?),
+				_ => Rule::Pattern(ParserComponent::parse_pattern(&ParserComponent::next_word(words, view, "pattern")?)?)
+			};
+			if rules.iter().any(|other| std::mem::discriminant(&other.rule) == std::mem::discriminant(&rule)) {
+				return Err(
+					CompilationError {
+						name: "ERR_DUPLICATE_RULE".to_string(),
+						desc: "cannot parse validation".to_string(),
+						cause: format!("rule '{}' is given more than once", name.view.cut()),
+						suggestion: format!("remove the second '{}'", name.view.cut()),
+						position: None,
+						view: Some(name.view)
+					}
+				);
+			}
+			let message = match words.peek() {
+				Some(Token { ttype: TokenType::StringLiteral(literal), .. }) => Some(literal.cut().to_string()),
+				_ => None
+			};
+			if message.is_some() {
+				words.next();
+			}
+			rules.push(ValidationRule { rule, message });
+		}
+		if rules.is_empty() {
+			let word = ParserComponent::next_word(words, view, "validation rule")?;
+			return Err(ParserComponent::unexpected_word(&word, "validation rule"));
+		}
+
+		let error = match ParserComponent::next_is_keyword(words, KeyWordName::AS) {
+			true => {
+				words.next();
+				let name = ParserComponent::expect_name(words, view, "name of error variable")?;
+				Some(self.define_variable(&name, SymbolKind::Variable)?)
+			},
+			false => None
+		};
+		self.push_operation(TemplateOperation::Validate(ValidateOperation { target, rules, error }));
+		Ok(())
+	}
+
+	/// Parses length of a validation rule, e.g. `MINLENGTH 8`
+	fn parse_length(word: &Token) -> Result<usize, CompilationError> {
+		let length = match word.ttype {
+			TokenType::Identifier => word.view.cut().parse().ok(),
+			_ => None
+		};
+		length.ok_or_else(|| ParserComponent::invalid_number(word, "length"))
+	}
+
+	/// Parses number of a validation rule, e.g. `MIN -10.5`
+	fn parse_number(word: &Token) -> Result<f64, CompilationError> {
+		let number = match word.ttype {
+			TokenType::Identifier => word.view.cut().parse::<f64>().ok().filter(|number| number.is_finite()),
+			_ => None
+		};
+		number.ok_or_else(|| ParserComponent::invalid_number(word, "number"))
+	}
+
+	fn invalid_number(word: &Token, expected: &str) -> CompilationError {
+		CompilationError {
+			name: "ERR_INVALID_NUMBER".to_string(),
+			desc: "cannot parse validation".to_string(),
+			cause: format!("'{}' is not a valid {}", word.view.cut(), expected),
+			suggestion: format!("replace '{}' with a {}, e.g. '8'", word.view.cut(), expected),
+			position: None,
+			view: Some(word.view.clone())
+		}
+	}
+
+	/// Parses regular expression of a `PATTERN` rule, it is checked by the compiler so the client never gets an invalid one
+	fn parse_pattern(word: &Token) -> Result<String, CompilationError> {
+		let literal = match &word.ttype {
+			TokenType::StringLiteral(literal) => literal.cut(),
+			_ => return Err(ParserComponent::unexpected_word(word, "pattern as string literal"))
+		};
+		match Pattern::parse(literal) {
+			Ok(_) => Ok(literal.to_string()),
+			Err(cause) => Err(
+				CompilationError {
+					name: "ERR_INVALID_PATTERN".to_string(),
+					desc: "cannot parse validation".to_string(),
+					cause: format!("pattern '{}' is invalid: {}", literal, cause),
+					suggestion: "fix the pattern, it must match the whole value".to_string(),
+					position: None,
+					view: Some(word.view.clone())
+				}
+			)
+		}
+	}
+
+	fn parse_event_action(&mut self, view: &StringView, words: &mut Words) -> Result<(EventAction, Option<Token>), CompilationError> {
+		let first = ParserComponent::next_word(words, view, "'CALL' or request")?;
+		let (fetch, target) = match &first.ttype {
+			TokenType::KeyWord(KeyWordName::CALL) => {
+				let handler = ParserComponent::expect_name(words, view, "name of handler")?;
+				let argument = match words.next() {
+					Some(word) => Some(self.parse_data(word)?),
+					None => None
+				};
+				return Ok((EventAction::Call { handler: handler.view.cut().to_string(), argument }, None));
+			},
+			TokenType::Function(FunctionName::GET) => {
+				let (fetch, target) = self.read_fetch(view, words)?;
+				(fetch, Some(target))
+			},
+			TokenType::Function(function @ (FunctionName::POST|FunctionName::PUT|FunctionName::PATCH|FunctionName::DELETE)) => self.read_request(ParserComponent::request_method(function), view, words)?,
+			_ => return Err(ParserComponent::unexpected_word(&first, "'CALL' or request"))
+		};
+
+		if fetch.cache.is_some() || fetch.refresh.is_some() {
+			return Err(
+				CompilationError {
+					name: "ERR_REPEATED_HANDLER_REQUEST".to_string(),
+					desc: "cannot parse event handler".to_string(),
+					cause: "requests of handlers are sent once per event and cannot be cached or refreshed".to_string(),
+					suggestion: "remove the 'CACHE' and 'REFRESH' options".to_string(),
+					position: None,
+					view: Some(view.clone())
+				}
+			);
+		}
+		Ok((EventAction::Request(Box::new(fetch)), target))
+	}
+
+	/// # Parses options of a request
+	/// Every option may be given once, in any order:
+	/// * `PARAMS name=value, ...` - query parameters appended to the url
+	/// * `HEADERS name=value, ...` - custom headers
+	/// * `WITH credentials` - send cookies and authorization to other origins
+	/// * `CACHE duration`, `REFRESH EVERY duration` - reuse or repeat responses of `GET` requests
+	/// * `SCHEMA 'path'`, `SAMPLE 'path'` - JSON Schema or sample data the response of a `GET` request is checked against
+	fn parse_request_options(&mut self, fetch: &mut FetchOperation, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		let mut used: Vec<KeyWordName> = Vec::new();
+		while let Some(Token { ttype: TokenType::KeyWord(keyword @ (KeyWordName::PARAMS|KeyWordName::HEADERS|KeyWordName::WITH|KeyWordName::CACHE|KeyWordName::REFRESH|KeyWordName::SCHEMA|KeyWordName::SAMPLE)), .. }) = words.peek() {
+			let keyword = *keyword;
+			let option = words.next().unwrap();
+			// a response is described either by a schema or by a sample
+			let duplicate = match keyword {
+				KeyWordName::SCHEMA|KeyWordName::SAMPLE => fetch.schema.is_some(),
+				_ => used.contains(&keyword)
+			};
+			if duplicate {
+				return Err(
+					CompilationError {
+						name: "ERR_DUPLICATE_OPTION".to_string(),
+						desc: "cannot parse request".to_string(),
+						cause: format!("option '{}' is given more than once", option.view.cut()),
+						suggestion: format!("merge both '{}' into one", option.view.cut()),
+						position: None,
+						view: Some(option.view)
+					}
+				);
+			}
+			used.push(keyword);
+
+			match keyword {
+				KeyWordName::PARAMS => for (name, value) in self.parse_assignments(view, words, "query parameter", ParserComponent::duplicate_query_param)? {
+					fetch.query.push_back(QueryParam { name, value });
+				},
+				KeyWordName::HEADERS => for (name, value) in self.parse_assignments(view, words, "header", ParserComponent::duplicate_header)? {
+					fetch.headers.push_back(Header { name, value });
+				},
+				KeyWordName::CACHE => {
+					ParserComponent::expect_reading_request(fetch, option)?;
+					let duration = ParserComponent::next_word(words, view, "duration")?;
+					fetch.cache = Some(ParserComponent::parse_duration(&duration)?);
+				},
+				KeyWordName::REFRESH => {
+					ParserComponent::expect_reading_request(fetch, option)?;
+					ParserComponent::expect_keyword(words, view, KeyWordName::EVERY)?;
+					let duration = ParserComponent::next_word(words, view, "duration")?;
+					fetch.refresh = Some(ParserComponent::parse_duration(&duration)?);
+				},
+				KeyWordName::SCHEMA|KeyWordName::SAMPLE => {
+					let reference = option.view.clone();
+					ParserComponent::expect_reading_request(fetch, option)?;
+					let path = match ParserComponent::next_word(words, view, "path of the file")? {
+						Token { ttype: TokenType::StringLiteral(literal), .. } => literal.cut().to_string(),
+						word => return Err(ParserComponent::unexpected_word(&word, "path of the file as string literal"))
+					};
+					fetch.schema = Some(SchemaReference { id: resolve_template_id(&self.template.id, &path), sample: keyword == KeyWordName::SAMPLE, view: reference });
+				},
+				_ => {
+					let credentials = ParserComponent::next_word(words, view, "'credentials'")?;
+					if credentials.view.cut() != "credentials" {
+						return Err(ParserComponent::unexpected_word(&credentials, "'credentials'"));
+					}
+					fetch.credentials = true;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Options like `CACHE` and `REFRESH` repeat requests and are therefore only given to `GET` requests
+	fn expect_reading_request(fetch: &FetchOperation, option: Token) -> Result<(), CompilationError> {
+		if fetch.method == HttpMethod::GET {
+			return Ok(());
+		}
+		Err(
+			CompilationError {
+				name: "ERR_UNCACHEABLE_REQUEST".to_string(),
+				desc: "cannot parse request".to_string(),
+				cause: format!("{} requests change data and cannot use option '{}'", fetch.method.name(), option.view.cut()),
+				suggestion: format!("remove the '{}' option or add it to the GET request reading the data", option.view.cut()),
+				position: None,
+				view: Some(option.view)
+			}
+		)
+	}
+
+	/// # Parses duration
+	/// Number followed by a unit: `ms`, `s`, `m` or `h`, e.g. `60s`.\
+	/// Clients keep durations as milliseconds in a C `int`, so they must not be longer than `i32::MAX` milliseconds (about 24 days).
+	fn parse_duration(word: &Token) -> Result<Duration, CompilationError> {
+		let text = word.view.cut();
+		let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+		let (amount, unit) = text.split_at(split);
+		let amount: Option<u64> = match word.ttype {
+			TokenType::Identifier => amount.parse().ok(),
+			_ => None
+		};
+		let factor: Option<u64> = match unit {
+			"ms" => Some(1),
+			"s" => Some(1000),
+			"m" => Some(60 * 1000),
+			"h" => Some(3600 * 1000),
+			_ => None
+		};
+		let error = |cause: String| CompilationError {
+			name: "ERR_INVALID_DURATION".to_string(),
+			desc: "cannot parse instruction".to_string(),
+			cause,
+			suggestion: "use a number followed by 'ms', 's', 'm' or 'h', e.g. '60s'".to_string(),
+			position: None,
+			view: Some(word.view.clone())
+		};
+		match (amount, factor) {
+			(Some(amount), Some(factor)) => match amount.checked_mul(factor).filter(|millis| *millis <= i32::MAX as u64) {
+				Some(millis) => Ok(Duration::from_millis(millis)),
+				None => Err(error(format!("'{}' is longer than {}ms", text, i32::MAX)))
+			},
+			_ => Err(error(format!("'{}' is not a duration", text)))
+		}
+	}
+
+	/// # Parses list of named values
+	/// `name=value, ...`, every name may only be used once
+	/// * `what` - what the names stand for, used for error reporting
+	/// * `duplicate` - creates the error for a name which is used twice
+	fn parse_assignments(&mut self, view: &StringView, words: &mut Words, what: &str, duplicate: fn(Token) -> CompilationError) -> Result<Vec<(String, Data)>, CompilationError> {
+		let mut assignments: Vec<(String, Data)> = Vec::new();
+		loop {
+			let name = ParserComponent::expect_name(words, view, &format!("{} name", what))?;
+			if assignments.iter().any(|(assigned, _)| assigned == name.view.cut()) {
+				return Err(duplicate(name));
+			}
+			ParserComponent::expect_symbol(words, view, '=')?;
+			let value = ParserComponent::next_word(words, view, &format!("{} value", what))?;
+			assignments.push((name.view.cut().to_string(), self.parse_data(value)?));
+
+			if !ParserComponent::next_is_symbol(words, ',') {
+				return Ok(assignments);
+			}
+			words.next();
+		}
+	}
+
+	/// # Parses the `{PARAMS name, ...}` header
+	/// Parameters must be declared once, before any content of the template.
+	fn parse_params_declaration(&mut self, keyword: &Token, view: &StringView, words: &mut Words) -> Result<(), CompilationError> {
+		if self.has_content || self.has_params {
+			return Err(
+				CompilationError {
+					name: "ERR_MISPLACED_PARAMS".to_string(),
+					desc: "cannot declare template parameters".to_string(),
+					cause: "parameters must be declared once at the top of the template".to_string(),
+					suggestion: "move all parameters into a single {PARAMS ...} header before any content".to_string(),
+					position: None,
+					view: Some(keyword.view.clone())
+				}
+			);
+		}
+		self.has_params = true;
+
+		loop {
+			let name = ParserComponent::expect_name(words, view, "parameter name")?;
+			if self.template.params.iter().any(|param| param.id == name.view.cut()) {
+				return Err(ParserComponent::duplicate_param(name));
+			}
+
+			let param = self.define_variable(&name, SymbolKind::Param)?;
+			self.template.params.push_back(param);
+
+			if !ParserComponent::next_is_symbol(words, ',') {
+				return Ok(());
+			}
+			words.next();
+		}
+	}
+
+	/// # Defines variable in the innermost scope
+	/// Warns if the variable is redefined in the same scope or shadows a variable of an outer scope.
+	fn define_variable(&mut self, name: &Token, kind: SymbolKind) -> Result<VariableReference, CompilationError> {
+		let variable = VariableReference::new(name.view.cut());
+		match self.symbols.define(variable.clone(), kind) {
+			Definition::New => {},
+			Definition::Redefined => self.state.warn(
+				CompilationWarning {
+					name: "WARN_VARIABLE_REDEFINED".to_string(),
+					cause: format!("variable '{}' has already been defined and its value is replaced", variable.id),
+					suggestion: "choose another name if the previous value is still needed".to_string(),
+					view: Some(name.view.clone())
+				}
+			),
+			Definition::Shadowed(_) => self.state.warn(
+				CompilationWarning {
+					name: "WARN_VARIABLE_SHADOWED".to_string(),
+					cause: format!("variable '{}' hides a variable of the same name outside of this block", variable.id),
+					suggestion: "choose another name to avoid confusion".to_string(),
+					view: Some(name.view.clone())
+				}
+			),
+			Definition::LoopItem => return Err(
+				CompilationError {
+					name: "ERR_LOOP_ITEM_ASSIGNED".to_string(),
+					desc: format!("cannot define variable '{}'", variable.id),
+					cause: format!("'{}' is the item of the enclosing loop and cannot be replaced inside of it", variable.id),
+					suggestion: "choose another name for the variable".to_string(),
+					position: None,
+					view: Some(name.view.clone())
+				}
+			)
+		}
+		Ok(variable)
+	}
+
+	/// # Parses expression
+	/// `operand | filter + operand ...`
+	fn parse_expression(&mut self, view: &StringView, words: &mut Words) -> Result<Expression, CompilationError> {
+		let mut operands = LinkedList::new();
+		loop {
+			let word = ParserComponent::next_word(words, view, "value")?;
+			let data = self.parse_data(word)?;
+
+			let mut filters = LinkedList::new();
+			while ParserComponent::next_is_symbol(words, '|') {
+				words.next();
+				let name = ParserComponent::expect_name(words, view, "filter name")?;
+				match Filter::from_name(name.view.cut()) {
+					Some(filter) => filters.push_back(filter),
+					None => return Err(
+						CompilationError {
+							name: "ERR_UNKNOWN_FILTER".to_string(),
+							desc: "cannot apply filter".to_string(),
+							cause: format!("filter '{}' does not exist", name.view.cut()),
+							suggestion: "use one of the filters: length, upper, lower, trim".to_string(),
+							position: None,
+							view: Some(name.view)
+						}
+					)
+				}
+			}
+			operands.push_back(Operand { data, filters });
+
+			if !ParserComponent::next_is_symbol(words, '+') {
+				return Ok(Expression { operands, comparison: None });
+			}
+			words.next();
+		}
+	}
+
+	/// # Parses comparison operator, if one follows
+	/// Operators of two chars are scanned as two adjacent symbols, e.g. `<` and `=`
+	fn parse_comparison(words: &mut Words) -> Result<Option<Comparison>, CompilationError> {
+		let first = match words.peek() {
+			Some(Token { ttype: TokenType::Symbol('='|'!'|'<'|'>'), .. }) => words.next().unwrap(),
+			_ => return Ok(None)
+		};
+		let mut symbol = first.view.clone();
+		if let Some(Token { ttype: TokenType::Symbol('='), view }) = words.peek() {
+			if view.from == first.view.to + 1 {
+				symbol.to = view.to;
+				words.next();
+			}
+		}
+		match Comparison::from_symbol(symbol.cut()) {
+			Some(comparison) => Ok(Some(comparison)),
+			None => Err(
+				CompilationError {
+					name: "ERR_UNKNOWN_COMPARISON".to_string(),
+					desc: "cannot parse condition".to_string(),
+					cause: format!("comparison '{}' does not exist", symbol.cut()),
+					suggestion: "use one of the comparisons: ==, !=, <, <=, >, >=".to_string(),
+					position: None,
+					view: Some(symbol)
+				}
+			)
+		}
+	}
+
+	/// # Converts word into data
+	/// * string literals and numbers are values
+	/// * identifiers reference a variable or data inside of it, e.g. `user?.orders[0]['first name']`,\
+	///   the variable must have been defined before\
+	///   (in extending templates it might also be defined by the extended template)
+	fn parse_data(&mut self, word: Token) -> Result<Data, CompilationError> {
+		match &word.ttype {
+			TokenType::StringLiteral(literal) => Ok(Data::Value(literal.cut().to_string())),
+			TokenType::Identifier if word.view.cut().starts_with(|c: char| c.is_ascii_digit()) => Ok(Data::Value(word.view.cut().to_string())),
+			TokenType::Identifier => {
+				let variable = match VariableReference::parse(word.view.cut()) {
+					Ok(variable) => variable,
+					Err(cause) => return Err(
+						CompilationError {
+							name: "ERR_INVALID_PATH".to_string(),
+							desc: "cannot parse data path".to_string(),
+							cause,
+							suggestion: "separate names by '.' or '?.', write indices as [0] and other keys as ['key']".to_string(),
+							position: None,
+							view: Some(word.view.clone())
+						}
+					)
+				};
+				if self.symbols.lookup(&variable.id).is_none() {
+					let mut err = self.symbols.undefined_variable(&variable.id, &self.template.id);
+					if self.template.extends.is_none() {
+						err.view = Some(word.view.clone());
+						return Err(err);
+					}
+					self.template.unresolved.push_back(UnresolvedVariable {
+						variable: VariableReference::new(&variable.id),
+						block: self.open_blocks.iter().find_map(|block| match &block.kind {
+							BlockKind::Block(name) => Some(name.clone()),
+							_ => None
+						}),
+						view: word.view.clone()
+					});
+				}
+				Ok(Data::Variable(variable, Some(word.view.clone())))
+			},
+			_ => Err(ParserComponent::unexpected_word(&word, "string literal or identifier"))
+		}
+	}
+
+	fn next_word(words: &mut Words, view: &StringView, expected: &str) -> Result<Token, CompilationError> {
+		match words.next() {
+			Some(word) => Ok(word),
+			None => Err(
+				CompilationError {
+					name: "ERR_UNEXPECTED_INSTRUCTION_END".to_string(),
+					desc: "cannot parse instruction".to_string(),
+					cause: format!("expected {} but instruction ended", expected),
+					suggestion: format!("add {} to the instruction", expected),
+					position: None,
+					view: Some(view.clone())
+				}
+			)
+		}
+	}
+
+	/// Expects identifier which can be used as name, so it must not be a path
+	fn expect_name(words: &mut Words, view: &StringView, expected: &str) -> Result<Token, CompilationError> {
+		let word = ParserComponent::next_word(words, view, expected)?;
+		match word.ttype {
+			TokenType::Identifier if !word.view.cut().contains('.') => Ok(word),
+			_ => Err(ParserComponent::unexpected_word(&word, expected))
+		}
+	}
+
+	fn expect_keyword(words: &mut Words, view: &StringView, keyword: KeyWordName) -> Result<(), CompilationError> {
+		let expected = format!("keyword {:?}", keyword);
+		let word = ParserComponent::next_word(words, view, &expected)?;
+		match &word.ttype {
+			TokenType::KeyWord(k) if *k == keyword => Ok(()),
+			_ => Err(ParserComponent::unexpected_word(&word, &expected))
+		}
+	}
+
+	fn expect_symbol(words: &mut Words, view: &StringView, symbol: char) -> Result<(), CompilationError> {
+		let expected = format!("'{}'", symbol);
+		let word = ParserComponent::next_word(words, view, &expected)?;
+		match word.ttype {
+			TokenType::Symbol(s) if s == symbol => Ok(()),
+			_ => Err(ParserComponent::unexpected_word(&word, &expected))
+		}
+	}
+
+	fn expect_instruction_end(words: &mut Words) -> Result<(), CompilationError> {
+		match words.next() {
+			Some(word) => Err(ParserComponent::unexpected_word(&word, "end of instruction")),
+			None => Ok(())
+		}
+	}
+
+	fn next_is_keyword(words: &mut Words, keyword: KeyWordName) -> bool {
+		matches!(words.peek(), Some(Token { ttype: TokenType::KeyWord(k), .. }) if *k == keyword)
+	}
+
+	fn next_is_symbol(words: &mut Words, symbol: char) -> bool {
+		matches!(words.peek(), Some(Token { ttype: TokenType::Symbol(s), .. }) if *s == symbol)
+	}
+
+	fn unexpected_word(word: &Token, expected: &str) -> CompilationError {
+		CompilationError {
+			name: "ERR_UNEXPECTED_WORD".to_string(),
+			desc: "cannot parse instruction".to_string(),
+			cause: format!("expected {} but found '{}'", expected, word.view.cut()),
+			suggestion: format!("replace '{}' with {}", word.view.cut(), expected),
+			position: None,
+			view: Some(word.view.clone())
+		}
+	}
+
+	fn duplicate_slot(name: Token) -> CompilationError {
+		CompilationError {
+			name: "ERR_DUPLICATE_SLOT".to_string(),
+			desc: "cannot use slot".to_string(),
+			cause: format!("slot '{}' is used more than once", name.view.cut()),
+			suggestion: format!("remove the second '{}' or choose another name", name.view.cut()),
+			position: None,
+			view: Some(name.view)
+		}
+	}
+
+	fn duplicate_header(name: Token) -> CompilationError {
+		CompilationError {
+			name: "ERR_DUPLICATE_HEADER".to_string(),
+			desc: "cannot send header".to_string(),
+			cause: format!("header '{}' is set more than once", name.view.cut()),
+			suggestion: format!("remove the second '{}'", name.view.cut()),
+			position: None,
+			view: Some(name.view)
+		}
+	}
+
+	fn duplicate_query_param(name: Token) -> CompilationError {
+		CompilationError {
+			name: "ERR_DUPLICATE_QUERY_PARAM".to_string(),
+			desc: "cannot send query parameter".to_string(),
+			cause: format!("query parameter '{}' is set more than once", name.view.cut()),
+			suggestion: format!("remove the second '{}'", name.view.cut()),
+			position: None,
+			view: Some(name.view)
+		}
+	}
+
+	fn duplicate_param(name: Token) -> CompilationError {
+		CompilationError {
+			name: "ERR_DUPLICATE_PARAM".to_string(),
+			desc: "cannot use template parameter".to_string(),
+			cause: format!("parameter '{}' is used more than once", name.view.cut()),
+			suggestion: format!("remove the second '{}'", name.view.cut()),
+			position: None,
+			view: Some(name.view)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use crate::{compiler::{components::scanner::InputComponent, model::{Comparison, Data, EventAction, EventOperation, Filter, HttpMethod, InsertOperation, PathKey, PathSegment, RequestBody, Rule, StreamMode, Template, TemplateOperation, Transport, ValidateOperation, VariableReference}}, strings::sourceview::SourceView};
+	use super::{ParserComponent, resolve_template_id};
+
+	fn parse(id: &str, source: &str) -> Result<Template, String> {
+		let mut input = InputComponent::new(id.to_string());
+		input.scan(SourceView::from_string(source));
+		let tmodel = input.finish().map_err(|err| err.name)?;
+
+		let mut parser = ParserComponent::new(id.to_string());
+		parser.parse(tmodel);
+		parser.finish().map_err(|err| err.name)
+	}
+
+	fn warnings(source: &str) -> Vec<String> {
+		let mut input = InputComponent::new("index.ws".to_string());
+		input.scan(SourceView::from_string(source));
+
+		let mut parser = ParserComponent::new("index.ws".to_string());
+		parser.parse(input.finish().ok().unwrap());
+		parser.state.warnings.iter().map(|warning| warning.name.clone()).collect()
+	}
+
+	#[test]
+	fn resolve_template_ids() {
+		assert_eq!(resolve_template_id("index.ws", "./card.ws"), "card.ws");
+		assert_eq!(resolve_template_id("pages/index.ws", "./card.ws"), "pages/card.ws");
+		assert_eq!(resolve_template_id("pages/index.ws", "../shared/card.ws"), "shared/card.ws");
+	}
+
+	#[test]
+	fn parse_call_with_params() {
+		let template = parse("index.ws", "{IMPORT './card.ws' AS card}{GET product FROM '/api'}{INSERT card PARAMS title='Hello', item=product}").unwrap();
+
+		match template.operations.back() {
+			Some(TemplateOperation::Call(call)) => {
+				assert!(matches!(&call.template, Data::Value(id) if id == "card.ws"));
+				let names: Vec<&str> = call.params.iter().map(|param| param.name.as_str()).collect();
+				assert_eq!(names, vec!["title", "item"]);
+				assert!(matches!(&call.params.front().unwrap().value, Data::Value(v) if v == "Hello"));
+				assert!(matches!(&call.params.back().unwrap().value, Data::Variable(v, _) if v.id == "product"));
+			},
+			_ => panic!("last operation should be a call")
+		}
+
+		assert_eq!(parse("index.ws", "{INSERT card}").err().unwrap(), "ERR_UNKNOWN_TEMPLATE");
+		assert_eq!(parse("index.ws", "{IMPORT './card.ws' AS card}{INSERT card PARAMS item=product}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{IMPORT './card.ws' AS card}{INSERT card PARAMS a='1', a='2'}").err().unwrap(), "ERR_DUPLICATE_PARAM");
+		assert_eq!(parse("index.ws", "{IMPORT './card.ws' AS card}{INSERT card PARAMS a='1',}").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+	}
+
+	#[test]
+	fn parse_params_declaration() {
+		let template = parse("card.ws", "{# card #}\n{PARAMS title, item}\n<h2>{title}</h2>{item.name}").unwrap();
+
+		let params: Vec<&str> = template.params.iter().map(|param| param.id.as_str()).collect();
+		assert_eq!(params, vec!["title", "item"]);
+		assert!(template.variables.contains("item"));
+
+		assert_eq!(parse("card.ws", "<h2>{PARAMS title}</h2>").err().unwrap(), "ERR_MISPLACED_PARAMS");
+		assert_eq!(parse("card.ws", "{PARAMS title}{PARAMS item}").err().unwrap(), "ERR_MISPLACED_PARAMS");
+		assert_eq!(parse("card.ws", "{PARAMS title, title}").err().unwrap(), "ERR_DUPLICATE_PARAM");
+		assert_eq!(parse("card.ws", "{PARAMS item.name}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+	}
+
+	#[test]
+	fn parse_call_with_slots() {
+		let template = parse("index.ws", "{IMPORT './layout.ws' AS layout}\n{INSERT layout}\n  {FILL header}<h1>Title</h1>{END}\n  {FILL content}<p>Text</p>{END}\n{END}").unwrap();
+
+		match template.operations.back() {
+			Some(TemplateOperation::Call(call)) => {
+				let names: Vec<&str> = call.slots.iter().map(|slot| slot.name.as_str()).collect();
+				assert_eq!(names, vec!["header", "content"]);
+				assert!(matches!(call.slots.front().unwrap().operations.front(), Some(TemplateOperation::Print(_))));
+			},
+			_ => panic!("last operation should be a call")
+		}
+
+		// calls without slots do not open a block
+		let template = parse("index.ws", "{IMPORT './card.ws' AS card}{INSERT card} <p>after</p>").unwrap();
+		assert!(matches!(template.operations.back(), Some(TemplateOperation::Print(_))));
+
+		assert_eq!(parse("index.ws", "{IMPORT './layout.ws' AS layout}{INSERT layout}{FILL a}x{END}text{END}").err().unwrap(), "ERR_CONTENT_OUTSIDE_OF_FILL");
+		assert_eq!(parse("index.ws", "{IMPORT './layout.ws' AS layout}{INSERT layout}{FILL a}x{END}{FILL a}y{END}{END}").err().unwrap(), "ERR_DUPLICATE_SLOT");
+		assert_eq!(parse("index.ws", "{IMPORT './layout.ws' AS layout}{INSERT layout}{FILL a}x{END}").err().unwrap(), "ERR_UNCLOSED_BLOCK");
+		assert_eq!(parse("index.ws", "{FILL a}x{END}").err().unwrap(), "ERR_FILL_OUTSIDE_OF_CALL");
+		assert_eq!(parse("index.ws", "{END}").err().unwrap(), "ERR_UNEXPECTED_END");
+	}
+
+	#[test]
+	fn parse_slot_declarations() {
+		let template = parse("layout.ws", "<header>{SLOT header}</header><main>{SLOT content}</main>").unwrap();
+
+		assert_eq!(template.slots(), vec!["header", "content"]);
+		assert_eq!(parse("layout.ws", "{SLOT a}{SLOT a}").err().unwrap(), "ERR_DUPLICATE_SLOT");
+	}
+
+	#[test]
+	fn parse_paths() {
+		let template = parse("index.ws", "{GET user FROM '/api'}{user?.orders[0]['first name'].city}{user.items.0}").unwrap();
+		let paths: Vec<VariableReference> = template.operations.iter().filter_map(|operation| match operation {
+			TemplateOperation::Insert(InsertOperation { content: Data::Variable(variable, _), .. }) => Some(variable.clone()),
+			_ => None
+		}).collect();
+		assert_eq!(paths[0].id, "user");
+		assert_eq!(paths[0].path, vec![
+			PathSegment { key: PathKey::Property("orders".to_string()), optional: true },
+			PathSegment { key: PathKey::Index(0), optional: false },
+			PathSegment { key: PathKey::Property("first name".to_string()), optional: false },
+			PathSegment { key: PathKey::Property("city".to_string()), optional: false }
+		]);
+		assert_eq!(paths[0].to_string(), "user?.orders[0]['first name'].city");
+		assert_eq!(paths[1].to_string(), "user.items.0");
+		assert_eq!(VariableReference::parse("user?.[2]?.name").unwrap().to_string(), "user?.[2]?.name");
+		assert_eq!(VariableReference::spans("user?.orders[0]['a b']"), vec![6..12, 12..15, 15..22]);
+
+		for invalid in ["{user.}", "{user..name}", "{user[x]}", "{user[0}", "{user?name}", "{user['a'b]}"] {
+			assert_eq!(parse("index.ws", &format!("{{GET user FROM '/api'}}{}", invalid)).err().unwrap(), "ERR_INVALID_PATH", "{}", invalid);
+		}
+	}
+
+	#[test]
+	fn parse_set() {
+		let template = parse("index.ws", "{GET order FROM '/api'}{SET total = order.items | length}{SET label = 'Hi ' + order.name | upper}{total}").unwrap();
+
+		let mut operations = template.operations.iter().skip(1);
+		match operations.next() {
+			Some(TemplateOperation::Set(set)) => {
+				assert_eq!(set.target.id, "total");
+				let operand = set.value.operands.front().unwrap();
+				assert!(matches!(&operand.data, Data::Variable(v, _) if v.to_string() == "order.items"));
+				assert_eq!(operand.filters.iter().collect::<Vec<_>>(), vec![&Filter::Length]);
+			},
+			_ => panic!("second operation should be a SET")
+		}
+		match operations.next() {
+			Some(TemplateOperation::Set(set)) => {
+				assert_eq!(set.value.operands.len(), 2);
+				assert!(matches!(&set.value.operands.front().unwrap().data, Data::Value(v) if v == "Hi "));
+			},
+			_ => panic!("third operation should be a SET")
+		}
+		assert!(template.variables.contains("label"));
+
+		assert_eq!(parse("index.ws", "{SET a = b}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{SET a = 'x' | reverse}").err().unwrap(), "ERR_UNKNOWN_FILTER");
+		assert_eq!(parse("index.ws", "{SET a 'x'}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{SET a = 'x' +}").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+	}
+
+	#[test]
+	fn parse_conditions_and_loops() {
+		let template = parse("index.ws", "{GET order FROM '/api'}{IF order.paid}<p>paid</p>{ELSE}<p>open</p>{END}{FOR item IN order.items}{SET price = item.price}{price}{END}").unwrap();
+
+		let mut operations = template.operations.iter().skip(1);
+		match operations.next() {
+			Some(TemplateOperation::If(conditional)) => {
+				assert_eq!(conditional.operations.len(), 1);
+				assert_eq!(conditional.alternative.len(), 1);
+			},
+			_ => panic!("second operation should be an IF")
+		}
+		match operations.next() {
+			Some(TemplateOperation::Loop(repetition)) => {
+				assert_eq!(repetition.item.id, "item");
+				assert!(matches!(&repetition.items, Data::Variable(v, _) if v.to_string() == "order.items"));
+				assert_eq!(repetition.operations.len(), 2);
+			},
+			_ => panic!("third operation should be a FOR")
+		}
+
+		// variables defined inside of blocks are not visible outside of them
+		assert!(!template.variables.contains("price"));
+		assert_eq!(parse("index.ws", "{FOR item IN '123'}{END}{item}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{IF 1}{SET a = 1}{ELSE}{a}{END}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{IF 1}{ELSE}{ELSE}{END}").err().unwrap(), "ERR_UNEXPECTED_ELSE");
+		assert_eq!(parse("index.ws", "{ELSE}").err().unwrap(), "ERR_UNEXPECTED_ELSE");
+		assert_eq!(parse("index.ws", "{FOR item '123'}{END}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{IF 1}").err().unwrap(), "ERR_UNCLOSED_BLOCK");
+		assert_eq!(parse("index.ws", "{BLOCK a}{SET b = 1}{END}{b}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{FOR item IN '123'}{SET item = 1}{END}").err().unwrap(), "ERR_LOOP_ITEM_ASSIGNED");
+		assert!(parse("index.ws", "{FOR item IN '123'}{IF 1}{SET item = 1}{END}{END}").is_ok());
+	}
+
+	#[test]
+	fn parse_comparisons() {
+		let template = parse("index.ws", "{GET order FROM '/api'}{IF order.items | length + 1 >= 3}{END}{IF order.state!='paid'}{END}{IF order.total<10}{END}").unwrap();
+		let comparisons: Vec<(usize, Comparison, usize)> = template.operations.iter().filter_map(|operation| match operation {
+			TemplateOperation::If(conditional) => conditional.condition.comparison.as_ref()
+				.map(|(comparison, operands)| (conditional.condition.operands.len(), *comparison, operands.len())),
+			_ => None
+		}).collect();
+		assert_eq!(comparisons, vec![(2, Comparison::GreaterOrEqual, 1), (1, Comparison::NotEqual, 1), (1, Comparison::Less, 1)]);
+
+		assert_eq!(parse("index.ws", "{IF 1 = 1}{END}").err().unwrap(), "ERR_UNKNOWN_COMPARISON");
+		assert_eq!(parse("index.ws", "{IF 1 < = 1}{END}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{IF 1 >}{END}").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+		assert_eq!(parse("index.ws", "{IF a!b}{END}").err().unwrap(), "ERR_INVALID_CHAR_IN_WORD");
+	}
+
+	#[test]
+	fn variable_warnings() {
+		assert!(warnings("{SET a = 1}{IF a}{SET b = a}{END}{SET b = 2}").is_empty());
+		assert_eq!(warnings("{SET a = 1}{SET a = a + 1}"), vec!["WARN_VARIABLE_REDEFINED"]);
+		assert_eq!(warnings("{SET a = 1}{FOR a IN '123'}{END}"), vec!["WARN_VARIABLE_SHADOWED"]);
+	}
+
+	#[test]
+	fn parse_requests() {
+		let template = parse("index.ws", "{GET user FROM '/api/user'}{PATCH user TO '/api/user' HEADERS Content-Type='application/json' AS updated}{updated.name}{DELETE TO '/api/user'}").unwrap();
+
+		let mut operations = template.operations.iter().skip(1);
+		match operations.next() {
+			Some(TemplateOperation::Fetch(fetch)) => {
+				assert_eq!(fetch.method, HttpMethod::PATCH);
+				assert_eq!(fetch.target.as_ref().unwrap().id, "updated");
+				assert!(matches!(&fetch.body, Some(RequestBody::Data(Data::Variable(v, _))) if v.id == "user"));
+				assert_eq!(fetch.headers.front().unwrap().name, "Content-Type");
+			},
+			_ => panic!("second operation should be a PATCH")
+		}
+		match operations.nth(1) {
+			Some(TemplateOperation::Fetch(fetch)) => {
+				assert_eq!(fetch.method, HttpMethod::DELETE);
+				assert!(fetch.target.is_none() && fetch.body.is_none());
+			},
+			_ => panic!("last operation should be a DELETE")
+		}
+
+		assert!(parse("index.ws", "{POST FORM 'signup' TO '/api/signup'}").is_ok());
+		assert_eq!(parse("index.ws", "{POST FORM signup TO '/api/signup'}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{POST user TO '/api/users'}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{PUT 'x' TO '/api' HEADERS a='1', a='2'}").err().unwrap(), "ERR_DUPLICATE_HEADER");
+		assert_eq!(parse("index.ws", "{PUT 'x' '/api'}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{POST 'x' TO '/api' AS}").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+
+		let template = parse("index.ws", "{GET route FROM '/route'}{GET user FROM '/api/user' WITH credentials PARAMS id=route.id, page=1}").unwrap();
+		match template.operations.back() {
+			Some(TemplateOperation::Fetch(fetch)) => {
+				assert!(fetch.credentials);
+				let query: Vec<&str> = fetch.query.iter().map(|param| param.name.as_str()).collect();
+				assert_eq!(query, vec!["id", "page"]);
+			},
+			_ => panic!("last operation should be a GET")
+		}
+		assert_eq!(parse("index.ws", "{GET a FROM '/api' PARAMS x=1 PARAMS y=2}").err().unwrap(), "ERR_DUPLICATE_OPTION");
+		assert_eq!(parse("index.ws", "{GET a FROM '/api' PARAMS x=1, x=2}").err().unwrap(), "ERR_DUPLICATE_QUERY_PARAM");
+		assert_eq!(parse("index.ws", "{GET a FROM '/api' WITH cookies}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+	}
+
+	#[test]
+	fn parse_cache_option() {
+		let cache = |src: &'static str| -> Result<Option<Duration>, String> {
+			match parse("index.ws", src)?.operations.front() {
+				Some(TemplateOperation::Fetch(fetch)) => Ok(fetch.cache),
+				_ => panic!("first operation should be a request")
+			}
+		};
+
+		assert_eq!(cache("{GET a FROM '/api' CACHE 60s}"), Ok(Some(Duration::from_secs(60))));
+		assert_eq!(cache("{GET a FROM '/api' CACHE 500ms PARAMS x=1}"), Ok(Some(Duration::from_millis(500))));
+		assert_eq!(cache("{GET a FROM '/api' CACHE 5m}"), Ok(Some(Duration::from_secs(300))));
+		assert_eq!(cache("{GET a FROM '/api'}"), Ok(None));
+		assert_eq!(cache("{GET a FROM '/api' CACHE 60}").err().unwrap(), "ERR_INVALID_DURATION");
+		assert_eq!(cache("{GET a FROM '/api' CACHE '60s'}").err().unwrap(), "ERR_INVALID_DURATION");
+		assert_eq!(cache("{GET a FROM '/api' CACHE 596h}"), Ok(Some(Duration::from_secs(596 * 3600))));
+		assert_eq!(cache("{GET a FROM '/api' CACHE 597h}").err().unwrap(), "ERR_INVALID_DURATION");
+		assert_eq!(cache("{GET a FROM '/api' CACHE 18446744073709551615m}").err().unwrap(), "ERR_INVALID_DURATION");
+		assert_eq!(cache("{GET a FROM '/api' CACHE 1s CACHE 2s}").err().unwrap(), "ERR_DUPLICATE_OPTION");
+		assert_eq!(cache("{POST 'x' TO '/api' CACHE 60s}").err().unwrap(), "ERR_UNCACHEABLE_REQUEST");
+	}
+
+	#[test]
+	fn parse_refresh_option() {
+		let template = parse("index.ws", "{GET prices FROM '/api/prices' REFRESH EVERY 10s CACHE 5s}").unwrap();
+		match template.operations.front() {
+			Some(TemplateOperation::Fetch(fetch)) => {
+				assert_eq!(fetch.refresh, Some(Duration::from_secs(10)));
+				assert_eq!(fetch.cache, Some(Duration::from_secs(5)));
+			},
+			_ => panic!("first operation should be a GET")
+		}
+
+		assert_eq!(parse("index.ws", "{GET a FROM '/api' REFRESH 10s}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{GET a FROM '/api' REFRESH EVERY}").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+		assert_eq!(parse("index.ws", "{GET a FROM '/api' REFRESH EVERY 10x}").err().unwrap(), "ERR_INVALID_DURATION");
+		assert_eq!(parse("index.ws", "{DELETE TO '/api' REFRESH EVERY 10s}").err().unwrap(), "ERR_UNCACHEABLE_REQUEST");
+	}
+
+	#[test]
+	fn parse_schema_option() {
+		let schema = |src: &'static str| -> Result<Option<(String, bool)>, String> {
+			match parse("pages/index.ws", src)?.operations.front() {
+				Some(TemplateOperation::Fetch(fetch)) => Ok(fetch.schema.as_ref().map(|schema| (schema.id.clone(), schema.sample))),
+				_ => panic!("first operation should be a request")
+			}
+		};
+
+		assert_eq!(schema("{GET user FROM '/api/user' SCHEMA '../schemas/user.json'}"), Ok(Some(("schemas/user.json".to_string(), false))));
+		assert_eq!(schema("{GET user FROM '/api/user' CACHE 5s SAMPLE './user.json'}"), Ok(Some(("pages/user.json".to_string(), true))));
+		assert_eq!(schema("{GET user FROM '/api/user' SCHEMA user}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(schema("{GET user FROM '/api/user' SCHEMA './a.json' SAMPLE './b.json'}").err().unwrap(), "ERR_DUPLICATE_OPTION");
+		assert_eq!(schema("{DELETE TO '/api/user' SCHEMA './user.json'}").err().unwrap(), "ERR_UNCACHEABLE_REQUEST");
+	}
+
+	#[test]
+	fn parse_subscriptions() {
+		let template = parse("index.ws", "{SUBSCRIBE prices FROM 'ws://localhost/prices'}{SUBSCRIBE events FROM SSE '/stream' APPEND}{prices.eur}").unwrap();
+
+		let mut operations = template.operations.iter();
+		match operations.next() {
+			Some(TemplateOperation::Subscribe(subscribe)) => {
+				assert_eq!(subscribe.target.id, "prices");
+				assert_eq!((subscribe.transport, subscribe.mode), (Transport::WebSocket, StreamMode::Replace));
+			},
+			_ => panic!("first operation should be a subscription")
+		}
+		match operations.next() {
+			Some(TemplateOperation::Subscribe(subscribe)) => {
+				assert!(matches!(&subscribe.from, Data::Value(url) if url == "/stream"));
+				assert_eq!((subscribe.transport, subscribe.mode), (Transport::EventSource, StreamMode::Append));
+			},
+			_ => panic!("second operation should be a subscription")
+		}
+
+		assert!(parse("index.ws", "{SUBSCRIBE a FROM 'wss://localhost' REPLACE}").is_ok());
+		assert_eq!(parse("index.ws", "{SUBSCRIBE a FROM 'http://localhost'}").err().unwrap(), "ERR_INVALID_STREAM_URL");
+		assert_eq!(parse("index.ws", "{SUBSCRIBE a FROM SSE 'ws://localhost'}").err().unwrap(), "ERR_INVALID_STREAM_URL");
+		assert_eq!(parse("index.ws", "{SUBSCRIBE a FROM SSE}").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+		assert_eq!(parse("index.ws", "{SUBSCRIBE a FROM 'ws://localhost' PREPEND}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{SUBSCRIBE a FROM 'ws://localhost' APPEND REPLACE}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+	}
+
+	#[test]
+	fn parse_event_handlers() {
+		let template = parse("index.ws", "<button {ON click CALL refresh}>Reload</button>\
+			<form {ON submit POST form TO '/api/save' AS saved}></form>{saved.id}").unwrap();
+
+		let mut listeners = template.operations.iter().filter_map(|operation| match operation {
+			TemplateOperation::On(listener) => Some(listener),
+			_ => None
+		});
+		match listeners.next() {
+			Some(EventOperation { event, action: EventAction::Call { handler, argument: None }, .. }) => assert_eq!((event.as_str(), handler.as_str()), ("click", "refresh")),
+			_ => panic!("first listener should call a handler")
+		}
+		match listeners.next() {
+			Some(EventOperation { event, action: EventAction::Request(fetch), .. }) => {
+				assert_eq!((event.as_str(), fetch.method), ("submit", HttpMethod::POST));
+				assert!(matches!(&fetch.body, Some(RequestBody::Data(Data::Variable(form, _))) if form.id == "form"));
+				assert_eq!(fetch.target.as_ref().unwrap().id, "saved");
+			},
+			_ => panic!("second listener should send a request")
+		}
+
+		assert!(parse("index.ws", "{GET user FROM '/api/user'}<a {ON click CALL select user.id}>{ON click GET details FROM user.url}</a>").is_ok());
+		// `form` is only visible within the handler
+		assert_eq!(parse("index.ws", "<form {ON submit POST form TO '/api/save'}>{form}</form>").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "<a {ON click}>").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+		assert_eq!(parse("index.ws", "<a {ON click SET a = 1}>").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "<a {ON click CALL select 'a' 'b'}>").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "<a {ON click GET a FROM '/a' REFRESH EVERY 1s}>").err().unwrap(), "ERR_REPEATED_HANDLER_REQUEST");
+	}
+
+	#[test]
+	fn parse_form_bindings() {
+		let template = parse("index.ws", "{GET user FROM '/api/user'}{SET query = 'all'}<input {BIND input TO query}>\
+			<input {BIND change TO user.email}><input {BIND change TO user.name}>").unwrap();
+
+		assert_eq!(template.writable.iter().map(|variable| variable.id.as_str()).collect::<Vec<&str>>(), vec!["query", "user"]);
+		match template.operations.iter().find(|operation| matches!(operation, TemplateOperation::Bind(_))) {
+			Some(TemplateOperation::Bind(bind)) => assert_eq!((bind.event.as_str(), bind.target.id.as_str()), ("input", "query")),
+			_ => panic!("template should contain a bound control")
+		}
+
+		assert_eq!(parse("index.ws", "{GET items FROM '/api'}{FOR item IN items}<input {BIND input TO item.name}>{END}").err().unwrap(), "ERR_READ_ONLY_VARIABLE");
+		assert_eq!(parse("index.ws", "{PARAMS user}<input {BIND input TO user.name}>").err().unwrap(), "ERR_READ_ONLY_VARIABLE");
+		assert_eq!(parse("index.ws", "<input {BIND input TO 'text'}>").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "<input {BIND input TO missing}>").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{SET a = 1}<input {BIND input a}>").err().unwrap(), "ERR_UNEXPECTED_WORD");
+	}
+
+	#[test]
+	fn parse_validations() {
+		let template = parse("index.ws", "{SET user = 'x'}{VALIDATE user.email REQUIRED 'Please enter your email' EMAIL AS emailError}\
+			{VALIDATE user.age MIN -1.5 MAX 130}{VALIDATE user.zip PATTERN '\\d{5}' MINLENGTH 5 MAXLENGTH 5}{emailError}").unwrap();
+
+		let validations: Vec<&ValidateOperation> = template.operations.iter().filter_map(|operation| match operation {
+			TemplateOperation::Validate(validate) => Some(validate),
+			_ => None
+		}).collect();
+		assert_eq!(validations.len(), 3);
+		assert_eq!(validations[0].target.to_string(), "user.email");
+		assert_eq!(validations[0].rules.iter().map(|rule| (rule.rule.clone(), rule.message.clone())).collect::<Vec<(Rule, Option<String>)>>(),
+			vec![(Rule::Required, Some("Please enter your email".to_string())), (Rule::Email, None)]);
+		assert_eq!(validations[0].error.as_ref().unwrap().id, "emailError");
+		assert_eq!(validations[1].rules.iter().map(|rule| rule.rule.clone()).collect::<Vec<Rule>>(), vec![Rule::Min(-1.5), Rule::Max(130.0)]);
+		assert_eq!(validations[2].rules.iter().map(|rule| rule.rule.clone()).collect::<Vec<Rule>>(),
+			vec![Rule::Pattern("\\d{5}".to_string()), Rule::MinLength(5), Rule::MaxLength(5)]);
+		assert!(validations[2].error.is_none());
+
+		assert_eq!(parse("index.ws", "{SET a = 1}{VALIDATE a}").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+		assert_eq!(parse("index.ws", "{SET a = 1}{VALIDATE a AS e}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{VALIDATE 'a' REQUIRED}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{VALIDATE a REQUIRED}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{SET a = 1}{VALIDATE a REQUIRED REQUIRED 'x'}").err().unwrap(), "ERR_DUPLICATE_RULE");
+		assert_eq!(parse("index.ws", "{SET a = 1}{VALIDATE a MINLENGTH -1}").err().unwrap(), "ERR_INVALID_NUMBER");
+		assert_eq!(parse("index.ws", "{SET a = 1}{VALIDATE a MAX ten}").err().unwrap(), "ERR_INVALID_NUMBER");
+		assert_eq!(parse("index.ws", "{SET a = 1}{VALIDATE a PATTERN '[a-'}").err().unwrap(), "ERR_INVALID_PATTERN");
+		assert_eq!(parse("index.ws", "{SET a = 1}{VALIDATE a PATTERN abc}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+	}
+
+	#[test]
+	fn parse_routes() {
+		let template = parse("index.ws", "{IMPORT './home.ws' AS home}{IMPORT './user.ws' AS user}{IMPORT './404.ws' AS missing}\
+			<main>{ROUTE '/' USE home}{ROUTE '/users/:id' USE user}{ROUTE FALLBACK USE missing}</main>").unwrap();
+
+		let routes: Vec<(Option<&str>, &str, Vec<&str>)> = template.routes().into_iter()
+			.map(|route| (route.path.as_deref(), route.template.as_str(), route.params()))
+			.collect();
+		assert_eq!(routes, vec![(Some("/"), "home.ws", vec![]), (Some("/users/:id"), "user.ws", vec!["id"]), (None, "404.ws", vec![])]);
+
+		// segments are compared and passed decoded
+		let user = template.routes()[1];
+		assert_eq!(user.matches("/users/J%C3%BCrgen%2Fx?tab=1"), Some(vec![("id".to_string(), "Jürgen/x".to_string())]));
+		assert_eq!(user.matches("/users/100%"), Some(vec![("id".to_string(), "100%".to_string())]));
+		assert_eq!(user.matches("/%75sers/1"), Some(vec![("id".to_string(), "1".to_string())]));
+
+		assert_eq!(parse("index.ws", "{ROUTE '/' USE home}").err().unwrap(), "ERR_UNKNOWN_TEMPLATE");
+		assert_eq!(parse("index.ws", "{IMPORT './home.ws' AS home}{ROUTE 'home' USE home}").err().unwrap(), "ERR_INVALID_ROUTE");
+		assert_eq!(parse("index.ws", "{IMPORT './home.ws' AS home}{ROUTE '/a/:' USE home}").err().unwrap(), "ERR_INVALID_ROUTE");
+		assert_eq!(parse("index.ws", "{IMPORT './home.ws' AS home}{ROUTE '/:a/:a' USE home}").err().unwrap(), "ERR_INVALID_ROUTE");
+		assert_eq!(parse("index.ws", "{IMPORT './home.ws' AS home}{ROUTE '/a?b' USE home}").err().unwrap(), "ERR_INVALID_ROUTE");
+		assert_eq!(parse("index.ws", "{IMPORT './home.ws' AS home}{ROUTE '/users/:id' USE home}{ROUTE '/users/:name/' USE home}").err().unwrap(), "ERR_DUPLICATE_ROUTE");
+		assert_eq!(parse("index.ws", "{IMPORT './home.ws' AS home}{ROUTE FALLBACK USE home}{ROUTE FALLBACK USE home}").err().unwrap(), "ERR_DUPLICATE_ROUTE");
+		assert_eq!(parse("index.ws", "{IMPORT './home.ws' AS home}{ROUTE '/' home}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+		assert_eq!(parse("index.ws", "{IMPORT './home.ws' AS home}{ROUTE home USE home}").err().unwrap(), "ERR_UNEXPECTED_WORD");
+	}
+
+	#[test]
+	fn parse_fetch_states() {
+		let template = parse("index.ws", "{GET items FROM '/api'}<ul>{FOR item IN items}{item}{END}</ul>{LOADING}spinner{ERROR e}{e.status}: {e.message}{END}<p>{items}</p>").unwrap();
+
+		assert_eq!(template.operations.len(), 4);
+		match template.operations.front() {
+			Some(TemplateOperation::Fetch(fetch)) => {
+				assert_eq!(fetch.operations.len(), 3);
+				assert_eq!(fetch.loading.as_ref().unwrap().len(), 1);
+				assert!(fetch.empty.is_none());
+				let error = fetch.error.as_ref().unwrap();
+				assert_eq!(error.variable.id, "e");
+				assert_eq!(error.operations.len(), 3);
+			},
+			_ => panic!("first operation should be a GET with states")
+		}
+
+		// requests without states are not blocks
+		let template = parse("index.ws", "{IF 1}{GET a FROM '/a'}{GET b FROM '/b'}{a}{b}{END}{GET c FROM '/c'}{EMPTY}none{END}").unwrap();
+		match template.operations.front() {
+			Some(TemplateOperation::If(conditional)) => assert_eq!(conditional.operations.len(), 4),
+			_ => panic!("first operation should be an IF")
+		}
+		assert!(matches!(template.operations.back(), Some(TemplateOperation::Fetch(fetch)) if fetch.empty.is_some()));
+
+		assert_eq!(parse("index.ws", "{GET a FROM '/a'}{ERROR e}{END}{e}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{GET a FROM '/a'}{ERROR e}{e}{EMPTY}{e}{END}").err().unwrap(), "ERR_UNDEFINED_VARIABLE");
+		assert_eq!(parse("index.ws", "{GET a FROM '/a'}{LOADING}{SET b = 1}{END}").err().unwrap(), "ERR_INSTRUCTION_IN_STATE");
+		assert_eq!(parse("index.ws", "{GET a FROM '/a'}{EMPTY}{IF a}x{END}{END}").err().unwrap(), "ERR_INSTRUCTION_IN_STATE");
+		assert_eq!(parse("index.ws", "{GET a FROM '/a'}{LOADING}x{LOADING}y{END}").err().unwrap(), "ERR_DUPLICATE_STATE");
+		assert_eq!(parse("index.ws", "{GET a FROM '/a'}{IF a}{LOADING}{END}{END}").err().unwrap(), "ERR_STATE_OUTSIDE_OF_REQUEST");
+		assert_eq!(parse("index.ws", "{LOADING}").err().unwrap(), "ERR_STATE_OUTSIDE_OF_REQUEST");
+		assert_eq!(parse("index.ws", "{GET a FROM '/a'}{LOADING}x").err().unwrap(), "ERR_UNCLOSED_BLOCK");
+		assert_eq!(parse("index.ws", "{GET a FROM '/a'}{ERROR}{END}").err().unwrap(), "ERR_UNEXPECTED_INSTRUCTION_END");
+	}
+}
@@ -1,44 +1,83 @@
 use std::{collections::LinkedList};
 
-use crate::{compiler::{CompilationError, CompilationState, model::Template}, strings::{sourceview::SourceView, stringview::StringView}};
-#[derive(Debug)]
-enum FunctionName {
+use crate::{compiler::{CompilationError, CompilationState}, strings::{sourceview::SourceView, stringview::StringView}};
+#[derive(Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) enum FunctionName {
 	IMPORT,
 	INSERT,
 	GET,
 	IF,
-	END
+	END,
+	RAW,
+	SLOT,
+	FILL,
+	EXTENDS,
+	BLOCK,
+	SET,
+	FOR,
+	ELSE,
+	POST,
+	PUT,
+	PATCH,
+	DELETE,
+	LOADING,
+	EMPTY,
+	ERROR,
+	SUBSCRIBE,
+	ON,
+	BIND,
+	VALIDATE,
+	ROUTE
 }
-#[derive(Debug)]
-enum KeyWordName {
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) enum KeyWordName {
 	FROM,
 	AS,
 	TO,
-	PARAMS
+	PARAMS,
+	IN,
+	HEADERS,
+	FORM,
+	WITH,
+	CACHE,
+	REFRESH,
+	EVERY,
+	SSE,
+	APPEND,
+	REPLACE,
+	CALL,
+	REQUIRED,
+	EMAIL,
+	MINLENGTH,
+	MAXLENGTH,
+	PATTERN,
+	MIN,
+	MAX,
+	USE,
+	FALLBACK,
+	SCHEMA,
+	SAMPLE
 }
 #[derive(Debug)]
-struct InstructionToken<'this> {
-	pub words: LinkedList<Token<'this>>
-} impl<'this> InstructionToken<'this> {
-	pub fn new(list: LinkedList<Token<'this>>) -> Self {
-		Self{
-			words: list
-		}
-	}
+pub(crate) struct InstructionToken {
+	pub words: LinkedList<Token>
 }
 
 #[derive(Debug)]
-enum TokenType<'this> {
+pub(crate) enum TokenType {
 	Function(FunctionName),
 	KeyWord(KeyWordName),
-	StringLiteral(StringView<'this>),
+	StringLiteral(StringView),
 	Identifier,
-	Instruction(InstructionToken<'this>),
+	Symbol(char),
+	Instruction(InstructionToken),
 	PlainText
 } 
 
-impl<'this> TokenType<'this> {
-	pub fn from_instruction_word<'call>(view: &'call str) -> TokenType<'this> {
+impl TokenType {
+	pub fn from_instruction_word(view: &str) -> TokenType {
 		match view {
 			// Functions
 			"IMPORT" => TokenType::Function(FunctionName::IMPORT),
@@ -46,12 +85,54 @@ impl<'this> TokenType<'this> {
 			"GET" => TokenType::Function(FunctionName::GET),
 			"IF" => TokenType::Function(FunctionName::IF),
 			"END" => TokenType::Function(FunctionName::END),
+			"RAW" => TokenType::Function(FunctionName::RAW),
+			"SLOT" => TokenType::Function(FunctionName::SLOT),
+			"FILL" => TokenType::Function(FunctionName::FILL),
+			"EXTENDS" => TokenType::Function(FunctionName::EXTENDS),
+			"BLOCK" => TokenType::Function(FunctionName::BLOCK),
+			"SET" => TokenType::Function(FunctionName::SET),
+			"FOR" => TokenType::Function(FunctionName::FOR),
+			"ELSE" => TokenType::Function(FunctionName::ELSE),
+			"POST" => TokenType::Function(FunctionName::POST),
+			"PUT" => TokenType::Function(FunctionName::PUT),
+			"PATCH" => TokenType::Function(FunctionName::PATCH),
+			"DELETE" => TokenType::Function(FunctionName::DELETE),
+			"LOADING" => TokenType::Function(FunctionName::LOADING),
+			"EMPTY" => TokenType::Function(FunctionName::EMPTY),
+			"ERROR" => TokenType::Function(FunctionName::ERROR),
+			"SUBSCRIBE" => TokenType::Function(FunctionName::SUBSCRIBE),
+			"ON" => TokenType::Function(FunctionName::ON),
+			"BIND" => TokenType::Function(FunctionName::BIND),
+			"VALIDATE" => TokenType::Function(FunctionName::VALIDATE),
+			"ROUTE" => TokenType::Function(FunctionName::ROUTE),
 
 			// Keyword
 			"AS" => TokenType::KeyWord(KeyWordName::AS),
 			"FROM" => TokenType::KeyWord(KeyWordName::FROM),
 			"PARAMS" => TokenType::KeyWord(KeyWordName::PARAMS),
 			"TO" => TokenType::KeyWord(KeyWordName::TO),
+			"IN" => TokenType::KeyWord(KeyWordName::IN),
+			"HEADERS" => TokenType::KeyWord(KeyWordName::HEADERS),
+			"FORM" => TokenType::KeyWord(KeyWordName::FORM),
+			"WITH" => TokenType::KeyWord(KeyWordName::WITH),
+			"CACHE" => TokenType::KeyWord(KeyWordName::CACHE),
+			"REFRESH" => TokenType::KeyWord(KeyWordName::REFRESH),
+			"EVERY" => TokenType::KeyWord(KeyWordName::EVERY),
+			"SSE" => TokenType::KeyWord(KeyWordName::SSE),
+			"APPEND" => TokenType::KeyWord(KeyWordName::APPEND),
+			"REPLACE" => TokenType::KeyWord(KeyWordName::REPLACE),
+			"CALL" => TokenType::KeyWord(KeyWordName::CALL),
+			"REQUIRED" => TokenType::KeyWord(KeyWordName::REQUIRED),
+			"EMAIL" => TokenType::KeyWord(KeyWordName::EMAIL),
+			"MINLENGTH" => TokenType::KeyWord(KeyWordName::MINLENGTH),
+			"MAXLENGTH" => TokenType::KeyWord(KeyWordName::MAXLENGTH),
+			"PATTERN" => TokenType::KeyWord(KeyWordName::PATTERN),
+			"MIN" => TokenType::KeyWord(KeyWordName::MIN),
+			"MAX" => TokenType::KeyWord(KeyWordName::MAX),
+			"USE" => TokenType::KeyWord(KeyWordName::USE),
+			"FALLBACK" => TokenType::KeyWord(KeyWordName::FALLBACK),
+			"SCHEMA" => TokenType::KeyWord(KeyWordName::SCHEMA),
+			"SAMPLE" => TokenType::KeyWord(KeyWordName::SAMPLE),
 			// Identifier
 			_ => TokenType::Identifier
 		}
@@ -59,32 +140,43 @@ impl<'this> TokenType<'this> {
 }
 
 #[derive(Debug)]
-struct Token<'this> {
-	pub view: StringView<'this>,
-	ttype: TokenType<'this>
+pub(crate) struct Token {
+	pub view: StringView,
+	pub ttype: TokenType
 }
-impl<'this> Token<'this> {
+impl Token {
 
-	pub fn word_from_source<'call>(view: &'call StringView<'this>) -> Token<'this> {
+	pub fn word_from_source(view: &StringView) -> Token {
 		Token {
 			view: view.clone(),
 			ttype: TokenType::from_instruction_word(view.cut())
 		}
 	}
+
+	/// Checks if this is an instruction starting with function `name`
+	pub fn is_function(&self, name: FunctionName) -> bool {
+		match &self.ttype {
+			TokenType::Instruction(instruction) => match instruction.words.front() {
+				Some(Token { ttype: TokenType::Function(function), .. }) => *function == name,
+				_ => false
+			},
+			_ => false
+		}
+	}
 }
 
 #[derive(Debug)]
-pub struct TokenModel<'this> {
-	tokenstream: LinkedList<Token<'this>>
+pub struct TokenModel {
+	pub(crate) tokenstream: LinkedList<Token>
 }
-impl<'this> TokenModel<'this> {
-	pub fn new() -> TokenModel<'this> {
+impl TokenModel {
+	pub fn new() -> TokenModel {
 		Self {
 			tokenstream: LinkedList::new()
 		}
 	}
 
-	pub fn append(&mut self, tok: Token<'this>) {
+	fn append(&mut self, tok: Token) {
 		self.tokenstream.push_back(tok);
 	}
 }
@@ -93,19 +185,26 @@ impl<'this> TokenModel<'this> {
 enum WordBegin {
 	Identifier(usize), 
 	StringLiteral(usize), 
-	InstructionEnd(usize)
+	Symbol(usize),
+	InstructionEnd
 }
 
-pub struct InputComponent<'this> {
-	template: Template<'this>,
-	pub tmodel: TokenModel<'this>,
-	state: CompilationState<'this>
+/// Chars that form a word on their own, e.g. `=` in `title='Hello'` or `<` in `count < 3`
+fn is_symbol(c: char) -> bool {
+	matches!(c, '='|','|'|'|'+'|'<'|'>'|'!')
+}
+
+pub struct InputComponent {
+	/// id of the scanned template, named by errors at its end
+	id: String,
+	pub tmodel: TokenModel,
+	state: CompilationState
 } 
 
-impl<'this> InputComponent<'this> {
-	pub fn new(id: String) -> InputComponent<'this> {
+impl InputComponent {
+	pub fn new(id: String) -> InputComponent {
 		Self {
-			template: Template::new(id),
+			id,
 			tmodel: TokenModel::new(),
 			state: CompilationState::new()
 		}
@@ -119,64 +218,235 @@ impl<'this> InputComponent<'this> {
 		self.state.get_error()
 	}
 
-	/**
-	 * Converts source in tmodel
-	 */
-	pub fn scan<'call>(&mut self, mut source: SourceView<'this>) {
-		let mut last_instruction_end_index = source.cursor;
-		loop {
-			match InputComponent::find_next_instruction_start(&mut source) {
-				Some(instruction_start_index) => {
-					// Append plain text token
-					if instruction_start_index > (last_instruction_end_index + 1) as usize{
-						self.tmodel.append(
-							Token {
-								ttype: TokenType::PlainText,
-								view: source.view.substring((last_instruction_end_index + 1) as usize, instruction_start_index - 1).unwrap()
-							}	
-						);
+	/// InputComponent is consumed and its tmodel returned
+	pub fn finish(self) -> Result<TokenModel, CompilationError> {
+		self.state.finish(self.tmodel)
+	}
+
+	/// # Converts source in tmodel
+	/// Plain text is collected until an instruction starts. Besides instructions, the following is handled:
+	/// * `{# ... #}` comments are removed from the output
+	/// * `{{` and `}}` are escapes for literal `{` and `}` in plain text
+	/// * `{RAW} ... {END}` blocks are copied into plain text without being scanned
+	/// * contents of `<script>` and `<style>` elements are plain text, unless the element opts in using the `data-ws` attribute
+	pub fn scan(&mut self, mut source: SourceView) {
+		if let Err(err) = self.scan_source(&mut source) {
+			self.state.error(err);
+		}
+	}
+
+	fn scan_source(&mut self, source: &mut SourceView) -> Result<(), CompilationError> {
+		// index of the first char of the current plain text
+		let mut text_begin: usize = 0;
+		while let Some(c) = source.next() {
+			let index = source.cursor as usize;
+			match c {
+				'{' => {
+					if source.peek() == Some('{') {
+						// escaped '{': keep the first one as text, skip the second
+						self.append_plain_text(source, text_begin, index + 1);
+						source.next();
+						text_begin = index + 2;
+						continue;
 					}
-					match InputComponent::find_next_instruction_end(&mut source) {
-						Ok(instruction_end_index) => {
-							
-							let instruction_view = source.clone_ranged(instruction_start_index, instruction_end_index);
-							//																		    skip '{' ^                   
-							match InputComponent::scan_instruction(instruction_view) {
-								Ok(tok) => {
-									self.tmodel.append(tok);
-									last_instruction_end_index = instruction_end_index as i64;
-								}
-								Err(err) => {
-									self.state.error(err);
-									return;
-								}
-							}
-						},
-
-						Err(err) => {
-							self.state.error(err);
-							return;
-						}
+
+					self.append_plain_text(source, text_begin, index);
+					if source.peek() == Some('#') {
+						// comments are dropped entirely
+						self.find_comment_end(source)?;
+						text_begin = source.cursor as usize + 1;
+						continue;
 					}
-				}
 
-				None => {
-					let total_end_index = source.view.original.chars().count() - 1;
-					if total_end_index > (last_instruction_end_index + 1) as usize{
-						self.tmodel.append(
-							Token {
-								ttype: TokenType::PlainText,
-								view: source.view.substring((last_instruction_end_index + 1) as usize, total_end_index).unwrap()
-							}	
-						);
+					let instruction_end_index = InputComponent::find_next_instruction_end(source)?;
+					let instruction_view = source.clone_ranged(index, instruction_end_index);
+					let tok = InputComponent::scan_instruction(instruction_view)?;
+					text_begin = instruction_end_index + 1;
+
+					if tok.is_function(FunctionName::RAW) {
+						InputComponent::check_raw_instruction(&tok)?;
+						// everything until '{END}' is plain text
+						let raw_end_index = self.find_raw_block_end(source)?;
+						self.append_plain_text(source, text_begin, raw_end_index);
+						text_begin = source.cursor as usize + 1;
+					} else {
+						self.tmodel.append(tok);
 					}
-					return;
+				},
+
+				'}' if source.peek() == Some('}') => {
+					// escaped '}': keep the first one as text, skip the second
+					self.append_plain_text(source, text_begin, index + 1);
+					source.next();
+					text_begin = index + 2;
+				},
+
+				'<' => self.skip_raw_element(source)?,
+
+				_ => {}
+			}
+		}
+
+		let total_end_index = source.view.to - source.view.from + 1;
+		self.append_plain_text(source, text_begin, total_end_index);
+		Ok(())
+	}
+
+	/// Appends plain text token from `begin` (inclusive) to `end` (exclusive), if it is not empty
+	fn append_plain_text(&mut self, source: &SourceView, begin: usize, end: usize) {
+		if end > begin {
+			self.tmodel.append(
+				Token {
+					ttype: TokenType::PlainText,
+					view: source.view.substring(begin, end - 1).unwrap()
 				}
+			);
+		}
+	}
+
+	/// # Finds end of `{# ... #}` comment
+	/// Must be called with the cursor on the `{` of the comment start.\
+	/// Comments may span multiple lines and contain braces. Cursor will be placed on the closing `}` of `#}`.
+	/// ## Exceptions
+	/// If the source ends before `#}` has been found, an `CompilationError` pointing at the comment start will be thrown.
+	fn find_comment_end(&self, source: &mut SourceView) -> Result<(), CompilationError> {
+		let comment_start_position = source.position.clone();
+		let comment_start_view = source.view.substring(source.cursor as usize, source.cursor as usize + 1).unwrap();
+		// skip '#' of comment start, so '{#}' is not closed by itself
+		source.next();
+		while let Some(c) = source.next() {
+			if c == '#' && source.peek() == Some('}') {
+				source.next();
+				return Ok(());
 			}
-			
 		}
+
+		Err(
+			CompilationError {
+				name: "ERR_UNCLOSED_COMMENT".to_string(),
+				desc: "comment start does not have a comment end".to_string(),
+				position: Some(comment_start_position),
+				view: Some(comment_start_view),
+				cause: format!("template '{}' ends inside of comment", self.id),
+				suggestion: "insert '#}' to close the comment".to_string()
+			}
+		)
 	}
 
+	/// # Checks that `{RAW}` stands on its own
+	/// ## Exceptions
+	/// Any word after `RAW` is an error, e.g. `{RAW json}`.
+	fn check_raw_instruction(tok: &Token) -> Result<(), CompilationError> {
+		if let TokenType::Instruction(instruction) = &tok.ttype {
+			if let Some(word) = instruction.words.iter().nth(1) {
+				return Err(
+					CompilationError {
+						name: "ERR_UNEXPECTED_WORD".to_string(),
+						desc: "cannot scan raw block".to_string(),
+						cause: format!("expected '}}' after RAW but found '{}'", word.view.cut()),
+						suggestion: format!("remove '{}'", word.view.cut()),
+						position: None,
+						view: Some(word.view.clone())
+					}
+				);
+			}
+		}
+		Ok(())
+	}
+
+	/// # Finds end of `{RAW}` block
+	/// Searches for the next `{END}` and returns the index of its `{` (exclusive end of the raw content).\
+	/// Cursor will be placed on the closing `}` of `{END}`.
+	/// ## Exceptions
+	/// If the source ends before `{END}` has been found, an `CompilationError` will be thrown.
+	fn find_raw_block_end(&self, source: &mut SourceView) -> Result<usize, CompilationError> {
+		let raw_start_position = source.position.clone();
+		let raw_start_view = source.view.substring(source.cursor as usize, source.cursor as usize).unwrap();
+		while let Some(raw_end_index) = InputComponent::find_next_instruction_start(source) {
+			if source.peek_matches("END}") {
+				source.nth(3);
+				return Ok(raw_end_index);
+			}
+		}
+
+		Err(
+			CompilationError {
+				name: "ERR_UNCLOSED_RAW_BLOCK".to_string(),
+				desc: "raw block does not have an end".to_string(),
+				position: Some(raw_start_position),
+				view: Some(raw_start_view),
+				cause: format!("template '{}' ends inside of {{RAW}} block", self.id),
+				suggestion: "insert '{END}' after the raw content".to_string()
+			}
+		)
+	}
+
+	/// # Skips contents of `<script>` and `<style>` elements
+	/// Must be called with the cursor on `<`. If a `<script>` or `<style>` element starts here
+	/// the cursor will be moved onto the `>` of its closing tag, so its contents stay plain text.\
+	/// Elements having the `data-ws` attribute opt in and will be scanned for instructions.\
+	/// Any other tag is left untouched.
+	/// ## Exceptions
+	/// If the source ends before the closing tag has been found, an `CompilationError` pointing at the opening tag will be thrown.
+	fn skip_raw_element(&self, source: &mut SourceView) -> Result<(), CompilationError> {
+		let element = if source.peek_matches("script") {
+			"script"
+		} else if source.peek_matches("style") {
+			"style"
+		} else {
+			return Ok(());
+		};
+
+		// tag name must end here, otherwise this is another element (e.g. <styles>)
+		let mut lookahead = source.clone();
+		lookahead.nth(element.len() - 1);
+		match lookahead.peek() {
+			Some(' '|'\t'|'\n'|'>') => {},
+			_ => return Ok(())
+		}
+
+		// read opening tag and check for opt in
+		let tag_begin = lookahead.cursor as usize;
+		let mut tag_end = tag_begin;
+		while let Some(c) = lookahead.next() {
+			if c == '>' {
+				tag_end = lookahead.cursor as usize;
+				break;
+			}
+		}
+		if tag_end == tag_begin {
+			return Ok(());
+		}
+		let tag = lookahead.view.substring(tag_begin, tag_end).unwrap();
+		if tag.cut().split(|c: char| c.is_whitespace() || c == '>' || c == '=').any(|attr| attr == "data-ws") {
+			return Ok(());
+		}
+
+		// skip contents until closing tag
+		let closing_tag = format!("/{}", element);
+		while let Some(c) = lookahead.next() {
+			if c == '<' && lookahead.peek_matches(&closing_tag) {
+				for c in lookahead.by_ref() {
+					if c == '>' {
+						*source = lookahead;
+						return Ok(());
+					}
+				}
+				break;
+			}
+		}
+
+		Err(
+			CompilationError {
+				name: "ERR_UNCLOSED_ELEMENT".to_string(),
+				desc: format!("<{}> element does not have a closing tag", element),
+				position: Some(source.position.clone()),
+				view: Some(source.view.substring(source.cursor as usize, tag_end).unwrap()),
+				cause: format!("template '{}' ends inside of <{}> element", self.id, element),
+				suggestion: format!("insert '</{}>' after the element contents", element)
+			}
+		)
+	}
 
 	/// # Finds next `{` in source.
 	/// ## Works
@@ -185,7 +455,7 @@ impl<'this> InputComponent<'this> {
 	/// ## Options
 	/// * If `{` is found its index will be returned\
 	/// * If source ends without any `{` then `None` will be returned
-	fn find_next_instruction_start<'call>(source: &mut SourceView<'this>) -> Option<usize> {
+	fn find_next_instruction_start(source: &mut SourceView) -> Option<usize> {
 		for c in source.into_iter() {
 			if c == '{' {
 				return Some(source.cursor as usize);
@@ -198,17 +468,21 @@ impl<'this> InputComponent<'this> {
 	/// # Finds next `}` in source.
 	/// ## Works
 	/// Searches for next `}` character and returns its index.\
-	/// A `}` under the cursor by the time this function is called will be skipped.
+	/// A `}` under the cursor by the time this function is called will be skipped,
+	/// so are `}` inside of string literals, e.g. of patterns like `'\d{5}'`.
 	/// ## Exceptions
 	/// This function-call means that an instruction is open, so it must be closed before the source ends.\
 	/// If in any case the source end before a `}` has been found, an `CompilationError` will be thrown.
-	fn find_next_instruction_end<'call>(source: &mut SourceView<'this>) -> Result<usize, CompilationError<'this>> {
+	fn find_next_instruction_end(source: &mut SourceView) -> Result<usize, CompilationError> {
 		// Construction is case of error
 		let instruction_start_position = source.position.clone();
 		let instruction_start_view = source.view.substring(source.cursor as usize, source.cursor as usize + 1).unwrap();
+		let mut in_literal = false;
 		for c in source.into_iter() {
-			if c == '}' {
-				return Ok(source.cursor as usize)
+			match c {
+				'\'' => in_literal = !in_literal,
+				'}' if !in_literal => return Ok(source.cursor as usize),
+				_ => {}
 			}
 		}
 
@@ -225,20 +499,17 @@ impl<'this> InputComponent<'this> {
 	}
 
 	/// # Finds next WORD begin
-	/// This could be an **identifier**, a **string literal** or an **instruction end**\
-	/// Type and index will be returned (inclusive)
+	/// This could be an **identifier**, a **string literal**, a **symbol** or an **instruction end**\
+	/// Type and index will be returned (inclusive)\
+	/// Identifiers may begin with `-` if `negative` is set, which it is for the numbers of `{VALIDATE ...}` rules
 	/// ## Exceptions
 	/// * invalid chars are not allowed
 	/// * unexpected end of source
-	fn find_instruction_word_begin(source: &mut SourceView<'this>) -> Result<WordBegin, CompilationError<'this>> {
+	fn find_instruction_word_begin(source: &mut SourceView, negative: bool) -> Result<WordBegin, CompilationError> {
 		
 		// Test if currently on instruction end
-		match source.current() {
-			Some(c) => if c == '}' {
-				return Ok(WordBegin::InstructionEnd(source.cursor as usize));
-			}
-
-			None => {}
+		if source.current() == Some('}') {
+			return Ok(WordBegin::InstructionEnd);
 		}
 
 		for c in source.into_iter() {
@@ -246,9 +517,12 @@ impl<'this> InputComponent<'this> {
 				// Skip those chars
 				' '|'\t'|'\n' => {},
 				// valid WORD start chars
-				'a'..='z'|'A'..='Z'|'_' => return Ok(WordBegin::Identifier(source.cursor as usize)),
+				'a'..='z'|'A'..='Z'|'0'..='9'|'_' => return Ok(WordBegin::Identifier(source.cursor as usize)),
+				'-' if negative => return Ok(WordBegin::Identifier(source.cursor as usize)),
+				// symbols are words on their own
+				c if is_symbol(c) => return Ok(WordBegin::Symbol(source.cursor as usize)),
 				// detected instruction end
-				'}' => return Ok(WordBegin::InstructionEnd(source.cursor as usize)),
+				'}' => return Ok(WordBegin::InstructionEnd),
 				// detected string literal
 				'\'' => return Ok(WordBegin::StringLiteral(source.cursor as usize)),
 				// invalid chars
@@ -278,29 +552,34 @@ impl<'this> InputComponent<'this> {
 	}
 
 	/// # Finds next WORD end
-	/// This could be a **whitespace**, **tab**, **newline** or `}` (exclusive)
+	/// This could be a **whitespace**, **tab**, **newline**, a **symbol** or `}` (exclusive)
 	/// ## In case of `}`
 	/// If `}` is detected this method will return the index before.\
 	/// In case: `{... WORD}`
 	/// Index of `WOR[D]` will be returned, but cursor is over `}` so 
-	/// the end of instruction `}` must be checked directly after this method, **not** at the follwing char.
+	/// the end of instruction `}` must be checked directly after this method, **not** at the follwing char.\
+	/// The same applies to symbols: `{... WORD=...}` leaves the cursor over `=`.
 	/// 
-	fn find_identifier_end(source: &mut SourceView<'this>) -> Result<usize, CompilationError<'this>> {
+	/// Keys of paths quoted inside of brackets, e.g. `user['first name']`, may contain any char but `'`.
+	fn find_identifier_end(source: &mut SourceView) -> Result<usize, CompilationError> {
+		let mut quoted = false;
+		let mut previous = None;
 		for c in source.into_iter() {
 			match c {
+				'\'' if quoted => quoted = false,
+				_ if quoted => {},
+				'\'' if previous == Some('[') => quoted = true,
 				' '|'\t'|'\n'|'}' => return Ok(source.cursor as usize - 1),
-				'a'..='z'|'A'..='Z'|'.'|'-'|'_' => {},
-				_ => return Err(
-					CompilationError {
-						name: "ERR_INVALID_CHAR_IN_WORD".to_string(),
-						desc: "cannot scan word in instruction".to_string(),
-						cause: format!("invalid character '{}' found in word", c),
-						suggestion: format!("remove character '{}' from word. Such chars are not allowed there", c),
-						position: Some(source.position.clone()),
-						view: Some(source.view.substring(source.cursor as usize, source.cursor as usize+1).unwrap())
-					}
-				)
+				// '!' only ends a word as part of '!='
+				'!' => return match source.peek() {
+					Some('=') => Ok(source.cursor as usize - 1),
+					_ => Err(InputComponent::invalid_char_in_word(source, c))
+				},
+				c if is_symbol(c) => return Ok(source.cursor as usize - 1),
+				'a'..='z'|'A'..='Z'|'0'..='9'|'.'|'-'|'_'|'?'|'['|']' => {},
+				_ => return Err(InputComponent::invalid_char_in_word(source, c))
 			}
+			previous = Some(c);
 		}
 
 		Err(
@@ -315,15 +594,25 @@ impl<'this> InputComponent<'this> {
 		)
 	}
 
+	fn invalid_char_in_word(source: &SourceView, c: char) -> CompilationError {
+		CompilationError {
+			name: "ERR_INVALID_CHAR_IN_WORD".to_string(),
+			desc: "cannot scan word in instruction".to_string(),
+			cause: format!("invalid character '{}' found in word", c),
+			suggestion: format!("remove character '{}' from word. Such chars are not allowed there", c),
+			position: Some(source.position.clone()),
+			view: Some(source.view.substring(source.cursor as usize, source.cursor as usize+1).unwrap())
+		}
+	}
+
 	/// # Finds next string literal end `'`
 	/// and will return its index (inclusive)
 	/// ## Exceptions
 	/// * unexpected end of source
-	fn find_string_literal_end(source: &mut SourceView<'this>) -> Result<usize, CompilationError<'this>> {
+	fn find_string_literal_end(source: &mut SourceView) -> Result<usize, CompilationError> {
 		for c in source.into_iter() {
-			match c {
-				'\'' => return Ok(source.cursor as usize),
-				_ => {}
+			if c == '\'' {
+				return Ok(source.cursor as usize);
 			}
 		}
 
@@ -339,16 +628,25 @@ impl<'this> InputComponent<'this> {
 		)
 	}
 
+	fn symbol_token(source: &SourceView, index: usize) -> Token {
+		let view = source.view.substring(index, index).unwrap();
+		Token {
+			ttype: TokenType::Symbol(view.char_at(0).unwrap()),
+			view
+		}
+	}
+
 	/// # Scans instruction to generate token
 	/// * `source` - contains source inbetween {...} including '{' and '}'
-	fn scan_instruction<'call>(mut source: SourceView<'this>) -> Result<Token<'this>, CompilationError<'this>> {
+	fn scan_instruction(mut source: SourceView) -> Result<Token, CompilationError> {
 		let mut wordlist: LinkedList<Token> = LinkedList::new();
 		// Skip '{'
 		source.next();
 		loop {
-			match InputComponent::find_instruction_word_begin(&mut source)? {
+			let validate = matches!(wordlist.front(), Some(Token { ttype: TokenType::Function(FunctionName::VALIDATE), .. }));
+			match InputComponent::find_instruction_word_begin(&mut source, validate)? {
 				// End of instruction => return collected words
-				WordBegin::InstructionEnd(start) => {
+				WordBegin::InstructionEnd => {
 					return Ok(Token{
 						ttype: TokenType::Instruction(InstructionToken {
 							words: wordlist
@@ -365,14 +663,18 @@ impl<'this> InputComponent<'this> {
 						}
 					);
 				},
+				WordBegin::Symbol(start) => {
+					wordlist.push_back(InputComponent::symbol_token(&source, start));
+				},
 				WordBegin::Identifier(start) => {
 					let identifier_end = InputComponent::find_identifier_end(&mut source)?;
 					wordlist.push_back(
-						Token {
-							ttype: TokenType::Identifier,
-							view: source.view.substring(start, identifier_end).unwrap()
-						}
+						Token::word_from_source(&source.view.substring(start, identifier_end).unwrap())
 					);
+					// identifier might have been ended by a symbol under the cursor
+					if source.current().is_some_and(is_symbol) {
+						wordlist.push_back(InputComponent::symbol_token(&source, source.cursor as usize));
+					}
 				}	
 			}
 		}
@@ -384,9 +686,26 @@ impl<'this> InputComponent<'this> {
 }
 
 #[cfg(test)]
+// the tests match single patterns to fail on unexpected results
+#[allow(clippy::single_match)]
 	mod tests {
     use crate::strings::sourceview::SourceView;
-    use super::{InputComponent, WordBegin};
+    use super::{InputComponent, TokenType, WordBegin};
+
+	/// Concatenates all plain text tokens of the scanned source
+	fn scanned_text(comp: &InputComponent) -> String {
+		comp.tmodel.tokenstream.iter()
+			.filter(|tok| matches!(tok.ttype, TokenType::PlainText))
+			.map(|tok| tok.view.cut().to_string())
+			.collect()
+	}
+
+	/// Counts instruction tokens of the scanned source
+	fn scanned_instructions(comp: &InputComponent) -> usize {
+		comp.tmodel.tokenstream.iter()
+			.filter(|tok| matches!(tok.ttype, TokenType::Instruction(_)))
+			.count()
+	}
 
 	#[test]
 	fn find_next_instruction_end() {
@@ -403,6 +722,14 @@ impl<'this> InputComponent<'this> {
 			Ok(_) => panic!(),
 			Err(_) => {}
 		}
+
+		// brackets inside of string literals do not end the instruction
+		source = SourceView::from_string("{a '}' b}");
+		source.next();
+		match InputComponent::find_next_instruction_end(&mut source) {
+			Ok(i) => assert_eq!(i, 8),
+			Err(_) => panic!()
+		}
 	}
 
 	#[test]
@@ -430,7 +757,7 @@ impl<'this> InputComponent<'this> {
 	fn find_instruction_word_begin() {
 		let mut source = SourceView::from_string("W ' .WORD");
 
-		match InputComponent::find_instruction_word_begin(&mut source) {
+		match InputComponent::find_instruction_word_begin(&mut source, false) {
 			Ok(opt) => match opt {
 				WordBegin::Identifier(i) => assert_eq!(i, 0),
 				_ => panic!()
@@ -439,7 +766,7 @@ impl<'this> InputComponent<'this> {
 			Err(_) => panic!()
 		};
 
-		match InputComponent::find_instruction_word_begin(&mut source) {
+		match InputComponent::find_instruction_word_begin(&mut source, false) {
 			Ok(opt) => match opt {
 				WordBegin::StringLiteral(i) => assert_eq!(2, i),
 				_ => panic!()
@@ -449,7 +776,7 @@ impl<'this> InputComponent<'this> {
 		}
 
 		// Should be Err
-		match InputComponent::find_instruction_word_begin(&mut source) {
+		match InputComponent::find_instruction_word_begin(&mut source, false) {
 			Ok(_) => panic!(),
 
 			Err(_) => {}
@@ -458,7 +785,7 @@ impl<'this> InputComponent<'this> {
 		source = SourceView::from_string("	  	");
 
 		// Error because unexpected end of source
-		match InputComponent::find_instruction_word_begin(&mut source) {
+		match InputComponent::find_instruction_word_begin(&mut source, false) {
 			Ok(_) => panic!(),
 			Err(_) => {}
 		}
@@ -486,6 +813,13 @@ impl<'this> InputComponent<'this> {
 			Ok(_) => panic!(),
 			Err(_) => {}
 		}
+
+		// Quoted keys may contain spaces and '}'
+		source = SourceView::from_string("user?.items[0]['a }b'].c}");
+		assert_eq!(InputComponent::find_identifier_end(&mut source).ok(), Some(23));
+
+		source = SourceView::from_string("user'name' ");
+		assert!(InputComponent::find_identifier_end(&mut source).is_err());
 	}
 
 	#[test]
@@ -503,5 +837,86 @@ impl<'this> InputComponent<'this> {
 			Err(_) => {}
 		}
 	}
-		
-}
\ No newline at end of file
+
+	#[test]
+	fn scan_escaped_braces() {
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("a {{b}} {c} d}} x"));
+
+		assert!(comp.is_ok());
+		assert_eq!(scanned_text(&comp), "a {b}  d} x");
+		assert_eq!(scanned_instructions(&comp), 1);
+	}
+
+	#[test]
+	fn scan_raw_block() {
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("<p>{RAW}{\"json\": {\"a\": 1}}{END}</p>"));
+
+		assert!(comp.is_ok());
+		assert_eq!(scanned_text(&comp), "<p>{\"json\": {\"a\": 1}}</p>");
+		assert_eq!(scanned_instructions(&comp), 0);
+
+		// raw block must be closed
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("{RAW} { "));
+		assert_eq!(comp.get_error().unwrap().name, "ERR_UNCLOSED_RAW_BLOCK");
+
+		// raw instruction does not take any words
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("{RAW json}{x}{END}"));
+		assert_eq!(comp.get_error().unwrap().name, "ERR_UNEXPECTED_WORD");
+	}
+
+	#[test]
+	fn scan_script_and_style_elements() {
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("<style>p {color: red}</style><SCRIPT type=\"x\">if (a) {b()}</SCRIPT>{x}"));
+
+		assert!(comp.is_ok());
+		assert_eq!(scanned_text(&comp), "<style>p {color: red}</style><SCRIPT type=\"x\">if (a) {b()}</SCRIPT>");
+		assert_eq!(scanned_instructions(&comp), 1);
+
+		// elements can opt in to instructions
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("<script data-ws>var a = '{x}';</script><styles>{y}</styles>"));
+
+		assert!(comp.is_ok());
+		assert_eq!(scanned_text(&comp), "<script data-ws>var a = '';</script><styles></styles>");
+		assert_eq!(scanned_instructions(&comp), 2);
+
+		// unclosed elements must not swallow the rest of the source
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("<script>var a = 1;{x}"));
+		assert_eq!(comp.get_error().unwrap().name, "ERR_UNCLOSED_ELEMENT");
+	}
+
+	#[test]
+	fn scan_comments() {
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("<p>{# note\n about {x} and {{braces}} #}text{#}#}</p>"));
+
+		assert!(comp.is_ok());
+		assert_eq!(scanned_text(&comp), "<p>text</p>");
+		assert_eq!(scanned_instructions(&comp), 0);
+
+		// comment must be closed
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("a\n {# note } #"));
+		let err = comp.get_error().unwrap();
+		assert_eq!(err.name, "ERR_UNCLOSED_COMMENT");
+		assert_eq!(err.view.as_ref().unwrap().cut(), "{#");
+	}
+
+	#[test]
+	fn scan_negative_numbers() {
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("{VALIDATE age MIN -1.5 MAX 130}"));
+		assert!(comp.is_ok());
+
+		// only rules of validations take negative numbers
+		let mut comp = InputComponent::new("test".to_string());
+		comp.scan(SourceView::from_string("{SET a = -1}"));
+		assert_eq!(comp.get_error().unwrap().name, "ERR_INVALID_WORD_BEGIN");
+	}
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeSet, HashMap, LinkedList};
+
+use crate::compiler::model::{CoreModel, Data, EventAction, Expression, FetchOperation, RequestBody, TemplateOperation};
+
+/**
+ * Indices of the requests a value is derived from
+ */
+type Origins = BTreeSet<usize>;
+
+/// # Analyzes data dependencies between the requests of each template
+/// Requests are numbered in order of appearance (nested requests follow the request containing them).
+/// A request depends on another one if its url, query, headers or body use the response of the other
+/// request, directly or through variables set from it, e.g.
+/// ```text
+/// {GET session FROM '/api/session'}{SET url = '/api/users/' + session.id}{GET user FROM url}
+/// ```
+/// Requests inside of conditions and loops also depend on the requests the condition or the looped data is derived from,
+/// as they are only sent once it is known whether and how often they are rendered.\
+/// Requests without dependencies are sent concurrently by the client,
+/// dependent requests as soon as the responses they depend on have arrived.
+pub struct SchedulerComponent {
+	/// origins of the variables by scope, innermost last
+	scopes: Vec<HashMap<String, Origins>>,
+	/// origins of the conditions and looped data enclosing the current operation, innermost last
+	enclosing: Vec<Origins>,
+	/// index of the next request of the current template
+	next: usize,
+	/// index of the next subscription of the current template
+	next_stream: usize,
+	/// index of the next route of the current template
+	next_route: usize,
+	/// index of the next event listener of the current template
+	next_listener: usize,
+	/// index of the next bound form control, controls are numbered across templates to identify them on a page
+	next_input: usize
+}
+
+impl SchedulerComponent {
+	pub fn new() -> SchedulerComponent {
+		Self {
+			scopes: Vec::new(),
+			enclosing: Vec::new(),
+			next: 0,
+			next_stream: 0,
+			next_route: 0,
+			next_listener: 0,
+			next_input: 0
+		}
+	}
+
+	/// Assigns `index` and `dependencies` of all requests and `index` of all subscriptions, routes, listeners and bound controls.
+	/// Routes are numbered after inheritance has been resolved, so routes of extending and extended templates do not collide.
+	/// Requests of listeners are numbered along with the other requests of their template.
+	pub fn schedule(&mut self, model: &mut CoreModel) {
+		for template in model.templates.iter_mut() {
+			self.scopes = vec![HashMap::new()];
+			self.enclosing = Vec::new();
+			self.next = 0;
+			self.next_stream = 0;
+			self.next_route = 0;
+			self.next_listener = 0;
+			self.schedule_operations(&mut template.operations);
+		}
+	}
+
+	fn schedule_operations(&mut self, operations: &mut LinkedList<TemplateOperation>) {
+		for operation in operations.iter_mut() {
+			self.schedule_operation(operation);
+		}
+	}
+
+	/// Operations of blocks, branches and loops define variables in a scope of their own
+	fn schedule_scoped(&mut self, operations: &mut LinkedList<TemplateOperation>, variables: HashMap<String, Origins>) {
+		self.scopes.push(variables);
+		self.schedule_operations(operations);
+		self.scopes.pop();
+	}
+
+	fn schedule_operation(&mut self, operation: &mut TemplateOperation) {
+		match operation {
+			TemplateOperation::Set(set) => {
+				let origins = self.expression_origins(&set.value);
+				self.scopes.last_mut().unwrap().insert(set.target.id.clone(), origins);
+			},
+			TemplateOperation::If(conditional) => {
+				self.enclosing.push(self.expression_origins(&conditional.condition));
+				self.schedule_scoped(&mut conditional.operations, HashMap::new());
+				self.schedule_scoped(&mut conditional.alternative, HashMap::new());
+				self.enclosing.pop();
+			},
+			TemplateOperation::Loop(repetition) => {
+				let items = self.origins(&repetition.items);
+				let mut variables = HashMap::new();
+				variables.insert(repetition.item.id.clone(), items.clone());
+				self.enclosing.push(items);
+				self.schedule_scoped(&mut repetition.operations, variables);
+				self.enclosing.pop();
+			},
+			TemplateOperation::Block(block) => self.schedule_scoped(&mut block.operations, HashMap::new()),
+			TemplateOperation::Call(call) => {
+				for slot in call.slots.iter_mut() {
+					self.schedule_scoped(&mut slot.operations, HashMap::new());
+				}
+			},
+			TemplateOperation::Fetch(fetch) => self.schedule_fetch(fetch),
+			// messages of streams do not depend on requests
+			TemplateOperation::Subscribe(subscribe) => {
+				subscribe.index = self.next_stream;
+				self.next_stream += 1;
+				self.scopes.last_mut().unwrap().insert(subscribe.target.id.clone(), Origins::new());
+			},
+			TemplateOperation::On(listener) => {
+				listener.index = self.next_listener;
+				self.next_listener += 1;
+				if let EventAction::Request(fetch) = &mut listener.action {
+					self.schedule_fetch(fetch);
+					// the response arrives once the event occurs, requests of the page do not wait for it
+					if let Some(target) = &fetch.target {
+						self.scopes.last_mut().unwrap().insert(target.id.clone(), Origins::new());
+					}
+				}
+			},
+			TemplateOperation::Bind(bind) => {
+				bind.index = self.next_input;
+				self.next_input += 1;
+			},
+			// the message is derived from the validated value
+			TemplateOperation::Validate(validate) => if let Some(error) = &validate.error {
+				let origins = self.origins(&Data::Variable(validate.target.clone(), None));
+				self.scopes.last_mut().unwrap().insert(error.id.clone(), origins);
+			},
+			TemplateOperation::Route(route) => {
+				route.index = self.next_route;
+				self.next_route += 1;
+			},
+			TemplateOperation::Print(_)|TemplateOperation::Insert(_)|TemplateOperation::Slot(_) => ()
+		}
+	}
+
+	fn schedule_fetch(&mut self, fetch: &mut FetchOperation) {
+		let mut origins = self.origins(&fetch.from);
+		for param in fetch.query.iter() {
+			origins.extend(self.origins(&param.value));
+		}
+		for header in fetch.headers.iter() {
+			origins.extend(self.origins(&header.value));
+		}
+		if let Some(RequestBody::Data(data)) = &fetch.body {
+			origins.extend(self.origins(data));
+		}
+		for enclosing in self.enclosing.iter() {
+			origins.extend(enclosing.iter().copied());
+		}
+
+		fetch.index = self.next;
+		fetch.dependencies = origins.into_iter().collect();
+		self.next += 1;
+
+		// the response is bound in the current scope, like the parser defines the target
+		if let Some(target) = &fetch.target {
+			self.scopes.last_mut().unwrap().insert(target.id.clone(), Origins::from([fetch.index]));
+		}
+		self.schedule_operations(&mut fetch.operations);
+		if let Some(loading) = &mut fetch.loading {
+			self.schedule_scoped(loading, HashMap::new());
+		}
+		if let Some(empty) = &mut fetch.empty {
+			self.schedule_scoped(empty, HashMap::new());
+		}
+		if let Some(error) = &mut fetch.error {
+			self.schedule_scoped(&mut error.operations, HashMap::new());
+		}
+	}
+
+	fn expression_origins(&self, expression: &Expression) -> Origins {
+		expression.all_operands().flat_map(|operand| self.origins(&operand.data)).collect()
+	}
+
+	/// Requests the data is derived from, parameters and literals have none
+	fn origins(&self, data: &Data) -> Origins {
+		match data {
+			Data::Variable(variable, _) => {
+				self.scopes.iter().rev()
+					.find_map(|scope| scope.get(&variable.id))
+					.cloned()
+					.unwrap_or_default()
+			},
+			_ => Origins::new()
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::LinkedList;
+
+	use crate::compiler::{build_model, model::TemplateOperation};
+
+	/// Index and dependencies of all requests of `index.ws`, in order of appearance
+	fn schedule(source: &'static str) -> Vec<(usize, Vec<usize>)> {
+		let mut sources = LinkedList::new();
+		sources.push_back(("index.ws".to_string(), source));
+		let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+
+		TemplateOperation::walk(&model.templates.front().unwrap().operations)
+			.filter_map(|operation| match operation {
+				TemplateOperation::Fetch(fetch) => Some((fetch.index, fetch.dependencies.iter().copied().collect())),
+				_ => None
+			})
+			.collect()
+	}
+
+	#[test]
+	fn independent_requests() {
+		assert_eq!(schedule("{GET a FROM '/api/a'}{GET b FROM '/api/b'}{POST a TO '/api/c'}"), vec![(0, vec![]), (1, vec![]), (2, vec![0])]);
+	}
+
+	#[test]
+	fn dependencies_through_variables() {
+		assert_eq!(schedule("{GET session FROM '/api/session'}{SET url = '/api/users/' + session.id}{GET user FROM url}\
+			{GET orders FROM '/api/orders' PARAMS user=user.id HEADERS Authorization=session.token}"),
+			vec![(0, vec![]), (1, vec![0]), (2, vec![0, 1])]);
+
+		// loop items are derived from the looped data
+		assert_eq!(schedule("{GET users FROM '/api/users'}{FOR user IN users}{GET details FROM user.url}{END}"),
+			vec![(0, vec![]), (1, vec![0])]);
+
+		// nested requests are numbered after the request containing them
+		assert_eq!(schedule("{GET a FROM '/api/a'}{GET b FROM a.next}{GET c FROM b.next}{LOADING}...{END}{LOADING}...{END}{GET d FROM '/api/d'}"),
+			vec![(0, vec![]), (1, vec![0]), (2, vec![1]), (3, vec![])]);
+	}
+
+	#[test]
+	fn requests_of_listeners_are_no_origins() {
+		assert_eq!(schedule("<form {ON submit POST form TO '/api/c' AS created}></form>{GET d FROM '/api/d' PARAMS id=created.id}"),
+			vec![(1, vec![])]);
+	}
+
+	#[test]
+	fn dependencies_respect_scopes() {
+		// `url` of the block is not visible after it, the outer `url` is a literal
+		assert_eq!(schedule("{GET a FROM '/api/a'}{SET url = '/api/b'}{IF a}{SET url = a.url}{GET b FROM url}{END}{GET c FROM url}"),
+			vec![(0, vec![]), (1, vec![0]), (2, vec![])]);
+	}
+
+	#[test]
+	fn dependencies_of_enclosing_operations() {
+		// the request is only sent if the condition holds
+		assert_eq!(schedule("{GET a FROM '/a'}{IF a.admin}{GET secret FROM '/admin'}{END}"),
+			vec![(0, vec![]), (1, vec![0])]);
+		assert_eq!(schedule("{GET a FROM '/a'}{GET b FROM '/b'}{IF a.admin}{ELSE}{FOR x IN b.items}{GET c FROM '/c'}{END}{END}"),
+			vec![(0, vec![]), (1, vec![]), (2, vec![0, 1])]);
+		assert_eq!(schedule("{GET a FROM '/a'}{FOR x IN a.items}{END}{GET b FROM '/b'}"),
+			vec![(0, vec![]), (1, vec![])]);
+	}
+}
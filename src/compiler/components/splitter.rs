@@ -0,0 +1,137 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compiler::model::{Chunk, CoreModel};
+
+/// # Splits the model into modules, which the client loads on demand
+/// Templates which are neither routed nor inserted are the pages of the application. They are compiled into
+/// the core module along with all templates they insert. Every other routed template gets a module of its own, containing
+/// the templates it inserts, directly or through other templates, e.g.
+/// ```text
+/// {ROUTE '/' USE home}{ROUTE '/users/:id' USE userpage}
+/// ```
+/// compiles `home` and `userpage` into two modules. Templates needed by several of these modules
+/// (like a card inserted by both) are moved into the core module, so they are loaded only once.
+/// Routes inside of a routed template get modules of their own as well.
+///
+/// Runs after the linker, so all inserted and routed templates exist.
+pub struct SplitterComponent<'m> {
+	/// inserted templates by template id
+	calls: HashMap<&'m str, Vec<&'m str>>
+}
+
+impl<'m> SplitterComponent<'m> {
+	pub fn new() -> SplitterComponent<'m> {
+		Self {
+			calls: HashMap::new()
+		}
+	}
+
+	/// Core module first, followed by one module per routed template in order of appearance.
+	/// Routed templates which are part of the core module do not get a module of their own.
+	pub fn split(&mut self, model: &'m CoreModel) -> Vec<Chunk> {
+		let mut routed: Vec<&str> = Vec::new();
+		for template in model.templates.iter() {
+			self.calls.insert(&template.id, template.calls());
+			for route in template.routes() {
+				if !routed.contains(&route.template.as_str()) {
+					routed.push(&route.template);
+				}
+			}
+		}
+
+		// pages are neither routed nor inserted by other templates
+		let inserted: HashSet<&str> = self.calls.values().flatten().copied().collect();
+		let pages = model.templates.iter().map(|template| template.id.as_str()).filter(|id| !routed.contains(id) && !inserted.contains(id));
+		let pages = self.reachable(pages);
+
+		let entries: Vec<(&str, HashSet<&str>)> = routed.iter()
+			.filter(|entry| !pages.contains(*entry))
+			.map(|entry| (*entry, self.reachable(std::iter::once(*entry))))
+			.collect();
+		// templates reached by one routed template only, all others are part of the core module
+		let exclusive = |index: usize, id: &str| !pages.contains(id)
+			&& entries.iter().enumerate().all(|(other, (_, templates))| (other == index) == templates.contains(id));
+
+		let mut chunks = vec![Chunk { entry: None, templates: Vec::new() }];
+		for (index, (entry, _)) in entries.iter().enumerate() {
+			if exclusive(index, entry) {
+				chunks.push(Chunk { entry: Some(entry.to_string()), templates: Vec::new() });
+			}
+		}
+		for template in model.templates.iter() {
+			// the entry of an exclusive template is exclusive as well, so its module exists
+			let chunk = match (0..entries.len()).find(|index| exclusive(*index, &template.id)) {
+				Some(owner) => chunks.iter().position(|chunk| chunk.entry.as_deref() == Some(entries[owner].0)).unwrap(),
+				None => 0
+			};
+			chunks[chunk].templates.push(template.id.clone());
+		}
+		chunks
+	}
+
+	/// Templates given and all templates they insert, directly or through other templates
+	fn reachable(&self, templates: impl Iterator<Item = &'m str>) -> HashSet<&'m str> {
+		let mut reached = HashSet::new();
+		let mut pending: Vec<&str> = templates.collect();
+		while let Some(id) = pending.pop() {
+			if reached.insert(id) {
+				pending.extend(self.calls.get(id).into_iter().flatten());
+			}
+		}
+		reached
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::LinkedList;
+
+	use crate::compiler::build_model;
+	use super::SplitterComponent;
+
+	/// Entry and templates of every module
+	fn split(sources: Vec<(&str, &'static str)>) -> Vec<(Option<String>, Vec<String>)> {
+		let sources = sources.into_iter().map(|(id, source)| (id.to_string(), source)).collect();
+		let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+		SplitterComponent::new().split(&model).into_iter().map(|chunk| (chunk.entry, chunk.templates)).collect()
+	}
+
+	fn ids(ids: &[&str]) -> Vec<String> {
+		ids.iter().map(|id| id.to_string()).collect()
+	}
+
+	#[test]
+	fn split_routes() {
+		let chunks = split(vec![
+			("index.ws", "{IMPORT './home.ws' AS home}{IMPORT './user.ws' AS user}{IMPORT './nav.ws' AS nav}\
+				{INSERT nav}{ROUTE '/' USE home}{ROUTE '/users/:id' USE user}"),
+			("home.ws", "{IMPORT './card.ws' AS card}{IMPORT './news.ws' AS news}{INSERT card}{INSERT news}"),
+			("user.ws", "{PARAMS id}{IMPORT './card.ws' AS card}{IMPORT './tabs.ws' AS tabs}{INSERT card}{ROUTE '/users/:id/posts' USE tabs}"),
+			("nav.ws", "<nav></nav>"),
+			("card.ws", "<div></div>"),
+			("news.ws", "<ul></ul>"),
+			("tabs.ws", "<ol></ol>")
+		]);
+
+		assert_eq!(chunks, vec![
+			(None, ids(&["index.ws", "nav.ws", "card.ws"])),
+			(Some("home.ws".to_string()), ids(&["home.ws", "news.ws"])),
+			(Some("user.ws".to_string()), ids(&["user.ws"])),
+			(Some("tabs.ws".to_string()), ids(&["tabs.ws"]))
+		]);
+	}
+
+	#[test]
+	fn routed_templates_inserted_by_pages_stay_in_core() {
+		let chunks = split(vec![
+			("index.ws", "{IMPORT './home.ws' AS home}{IMPORT './about.ws' AS about}{INSERT home}{ROUTE '/' USE home}{ROUTE '/about' USE about}"),
+			("home.ws", "<h1>Home</h1>"),
+			("about.ws", "{IMPORT './home.ws' AS home}{INSERT home}")
+		]);
+
+		assert_eq!(chunks, vec![
+			(None, ids(&["index.ws", "home.ws"])),
+			(Some("about.ws".to_string()), ids(&["about.ws"]))
+		]);
+	}
+}
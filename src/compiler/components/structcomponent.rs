@@ -0,0 +1,311 @@
+use std::collections::{HashMap, LinkedList};
+
+use string_builder::Builder;
+use crate::{compiler::{CompilationError, CompilationState, model::{CoreModel, Data, FetchOperation}}, schema::{Property, Schema}};
+use super::checker::load_schema;
+
+/**
+ * Words which cannot be used as field names without `r#`
+ */
+const KEYWORDS: &[&str] = &["as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof",
+    "unsized", "virtual", "yield"];
+
+/// # Generates Rust structs of the data fetched by the templates
+/// Every request with a schema (`SCHEMA` or `SAMPLE`) and a target variable gets a type named after the variable,
+/// e.g. `{GET user FROM '/api/user' SCHEMA './user.schema.json'}` becomes `pub struct User`. Nested objects become
+/// structs named after their path (`UserAddress`), items of arrays are named `...Item`. Optional properties and
+/// nullable values are `Option`s, data of any shape is a `serde_json::Value`.
+///
+/// The structs derive serde's `Serialize` and `Deserialize`, so backends written in Rust can use them to build
+/// their responses. As the `CheckerComponent` checks the paths of the templates against the same schemas,
+/// templates cannot use fields the structs do not have.
+pub struct StructComponent<'f> {
+    state: CompilationState,
+    /// contents of the files referenced by requests, by id
+    files: HashMap<String, &'f str>,
+    /// generated types in order of appearance
+    definitions: Vec<Definition>,
+    /// schemas of the generated responses by type name, identical responses share their types
+    responses: Vec<(String, Schema)>
+}
+
+/**
+ * Generated type, `fields` is empty for aliases
+ */
+struct Definition {
+    name: String,
+    doc: Option<String>,
+    /// aliased type of responses which are no objects
+    alias: Option<String>,
+    fields: Vec<String>
+}
+
+impl<'f> StructComponent<'f> {
+    /// * `files` - id (path) and content of the schemas and samples referenced by requests
+    pub fn new(files: LinkedList<(String, &'f str)>) -> StructComponent<'f> {
+        StructComponent {
+            state: CompilationState::new(),
+            files: files.into_iter().collect(),
+            definitions: Vec::new(),
+            responses: Vec::new()
+        }
+    }
+
+    /// Generation stops at the first error
+    pub fn generate(&mut self, model: &CoreModel) {
+        for fetch in model.templates.iter().flat_map(|template| template.requests()) {
+            if let Err(err) = self.generate_response(fetch) {
+                self.state.error(err);
+                return;
+            }
+        }
+    }
+
+    /// StructComponent is consumed and the Rust code returned
+    pub fn finish(self) -> Result<String, CompilationError> {
+        let mut lines = Builder::default();
+        lines.append("// Generated by websorcery from the schemas of the fetched data, changes are overwritten\n");
+        lines.append("use serde::{Deserialize, Serialize};\n");
+        for definition in self.definitions.iter() {
+            lines.append("\n");
+            if let Some(doc) = &definition.doc {
+                lines.append(format!("/// {}\n", doc));
+            }
+            match &definition.alias {
+                Some(alias) => lines.append(format!("pub type {} = {};\n", definition.name, alias)),
+                None => {
+                    lines.append("#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]\n");
+                    lines.append(format!("pub struct {} {{\n", definition.name));
+                    for field in definition.fields.iter() {
+                        lines.append(field.as_str());
+                    }
+                    lines.append("}\n");
+                }
+            }
+        }
+        self.state.finish(lines.string().unwrap())
+    }
+
+    fn generate_response(&mut self, fetch: &FetchOperation) -> Result<(), CompilationError> {
+        let (target, reference) = match (&fetch.target, &fetch.schema) {
+            (Some(target), Some(reference)) => (target, reference),
+            _ => return Ok(())
+        };
+        let schema = load_schema(&self.files, reference)?;
+
+        let name = type_name(&[&target.id]);
+        if self.responses.iter().any(|(existing, response)| *existing == name && *response == schema) {
+            return Ok(());
+        }
+        let name = self.unique_name(name);
+        self.responses.push((name.clone(), schema.clone()));
+
+        let url = match &fetch.from {
+            Data::Value(url) => url.clone(),
+            Data::Variable(variable, _) => format!("{{{}}}", variable)
+        };
+        let doc = format!("Response of `{} {}` (`{}`)", fetch.method.name(), url, reference.id);
+        match &schema {
+            Schema::Object { properties, .. } => self.generate_struct(name, Some(doc), properties),
+            schema => {
+                let index = self.definitions.len();
+                self.definitions.push(Definition { name: name.clone(), doc: Some(doc), alias: None, fields: Vec::new() });
+                self.definitions[index].alias = Some(self.rust_type(&name, schema));
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_struct(&mut self, name: String, doc: Option<String>, properties: &[Property]) {
+        let index = self.definitions.len();
+        self.definitions.push(Definition { name: name.clone(), doc, alias: None, fields: Vec::new() });
+
+        let mut idents: Vec<String> = Vec::new();
+        for property in properties {
+            let mut ident = field_name(&property.name);
+            while idents.contains(&ident) {
+                ident.push('_');
+            }
+            idents.push(ident.clone());
+
+            let mut field = String::new();
+            if ident.trim_start_matches("r#") != property.name {
+                field.push_str(&format!("    #[serde(rename = \"{}\")]\n", property.name.replace('\\', "\\\\").replace('"', "\\\"")));
+            }
+            let mut rust_type = self.rust_type(&type_name(&[&name, &property.name]), &property.schema);
+            if !property.required {
+                field.push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+                if !rust_type.starts_with("Option<") {
+                    rust_type = format!("Option<{}>", rust_type);
+                }
+            }
+            field.push_str(&format!("    pub {}: {},\n", ident, rust_type));
+            self.definitions[index].fields.push(field);
+        }
+    }
+
+    /// Type of the data, structs of objects are generated using the name
+    fn rust_type(&mut self, name: &str, schema: &Schema) -> String {
+        match schema {
+            Schema::Any => "serde_json::Value".to_string(),
+            Schema::Null => "Option<serde_json::Value>".to_string(),
+            Schema::Boolean => "bool".to_string(),
+            Schema::Number => "f64".to_string(),
+            Schema::Integer => "i64".to_string(),
+            Schema::String => "String".to_string(),
+            Schema::Array(items) => format!("Vec<{}>", self.rust_type(&format!("{}Item", name), items)),
+            Schema::Object { properties, .. } => {
+                let name = self.unique_name(name.to_string());
+                self.generate_struct(name.clone(), None, properties);
+                name
+            },
+            Schema::Union(members) => {
+                let present: Vec<&Schema> = members.iter().filter(|member| **member != Schema::Null).collect();
+                match present.as_slice() {
+                    [member] => format!("Option<{}>", self.rust_type(name, member)),
+                    // alternatives of different types cannot be told apart by serde without further attributes
+                    _ => "serde_json::Value".to_string()
+                }
+            }
+        }
+    }
+
+    fn unique_name(&self, name: String) -> String {
+        let taken = |candidate: &str| self.definitions.iter().any(|definition| definition.name == candidate);
+        let mut candidate = name.clone();
+        let mut number = 2;
+        while taken(&candidate) {
+            candidate = format!("{}{}", name, number);
+            number += 1;
+        }
+        candidate
+    }
+}
+
+/**
+ * Name of a type in UpperCamelCase, e.g. `user` and `home_address` become `UserHomeAddress`
+ */
+fn type_name(parts: &[&str]) -> String {
+    let mut name = String::new();
+    for word in parts.iter().flat_map(|part| part.split(|c: char| !c.is_ascii_alphanumeric())) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            name.push(first.to_ascii_uppercase());
+            name.extend(chars);
+        }
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert_str(0, "Data");
+    }
+    name
+}
+
+/**
+ * Name of a field in snake_case, e.g. `firstName` becomes `first_name`, `HTTPStatus` becomes `http_status`
+ * and `type` becomes `r#type`
+ */
+fn field_name(property: &str) -> String {
+    let chars: Vec<char> = property.chars().collect();
+    let mut name = String::new();
+    for (index, c) in chars.iter().copied().enumerate() {
+        let previous = index.checked_sub(1).map(|index| chars[index]);
+        let next = chars.get(index + 1);
+        // words start at capitals following lowercase letters or digits, and at the last capital of an acronym
+        let starts_word = previous.is_some_and(|previous| previous.is_ascii_lowercase() || previous.is_ascii_digit()
+            || previous.is_ascii_uppercase() && next.is_some_and(|next| next.is_ascii_lowercase()));
+        if c.is_ascii_uppercase() && starts_word {
+            name.push('_');
+        }
+        name.push(if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' });
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') || name.chars().all(|c| c == '_') {
+        name.insert_str(0, "field_");
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        name.insert_str(0, "r#");
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::LinkedList;
+
+    use crate::compiler::build_model;
+    use super::{field_name, StructComponent};
+
+    fn generate(sources: Vec<(&str, &'static str)>) -> Result<String, String> {
+        let sources = sources.into_iter().map(|(id, source)| (id.to_string(), source)).collect();
+        let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+
+        let mut files = LinkedList::new();
+        files.push_back(("pages/user.schema.json".to_string(), r#"{ "type": "object", "required": ["id", "address"], "properties": {
+            "id": { "type": "integer" },
+            "firstName": { "type": "string" },
+            "type": { "type": ["string", "null"] },
+            "address": { "type": ["object", "null"], "properties": { "city": { "type": "string" } }, "required": ["city"] },
+            "orders": { "type": "array", "items": { "type": "object", "properties": { "total": { "type": "number" } } } },
+            "meta": {}
+        } }"#));
+        files.push_back(("pages/tags.json".to_string(), r#"["a", "b"]"#));
+        let mut structs = StructComponent::new(files);
+        structs.generate(&model);
+        structs.finish().map_err(|err| err.name)
+    }
+
+    #[test]
+    fn generate_structs() {
+        let code = generate(vec![("pages/index.ws", "{GET user FROM '/api/user' SCHEMA './user.schema.json'}{GET tags FROM '/api/tags' SAMPLE './tags.json'}\
+            <button {ON click GET user FROM '/api/user' SCHEMA './user.schema.json'}>Reload</button>{GET news FROM '/api/news'}")]).unwrap();
+
+        assert!(code.starts_with("// Generated by websorcery from the schemas of the fetched data, changes are overwritten\nuse serde::{Deserialize, Serialize};\n"));
+        assert!(code.contains(&[
+            "/// Response of `GET /api/user` (`pages/user.schema.json`)",
+            "#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]",
+            "pub struct User {",
+            "    pub id: i64,",
+            "    #[serde(rename = \"firstName\")]",
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]",
+            "    pub first_name: Option<String>,",
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]",
+            "    pub r#type: Option<String>,",
+            "    pub address: Option<UserAddress>,",
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]",
+            "    pub orders: Option<Vec<UserOrdersItem>>,",
+            "    #[serde(default, skip_serializing_if = \"Option::is_none\")]",
+            "    pub meta: Option<serde_json::Value>,",
+            "}"
+        ].join("\n")));
+        assert!(code.contains("pub struct UserAddress {\n    pub city: String,\n}"));
+        assert!(code.contains("pub struct UserOrdersItem {\n    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    pub total: Option<f64>,\n}"));
+        assert!(code.contains("/// Response of `GET /api/tags` (`pages/tags.json`)\npub type Tags = Vec<String>;"));
+        // identical responses share their structs
+        assert_eq!(code.matches("pub struct User {").count(), 1);
+        assert!(!code.contains("News"));
+    }
+
+    #[test]
+    fn distinguish_responses() {
+        let code = generate(vec![
+            ("pages/index.ws", "{GET user FROM '/api/user' SCHEMA './user.schema.json'}"),
+            ("pages/other.ws", "{GET user FROM '/api/names' SAMPLE './tags.json'}")
+        ]).unwrap();
+        assert!(code.contains("pub struct User {"));
+        assert!(code.contains("pub type User2 = Vec<String>;"));
+
+        assert_eq!(generate(vec![("pages/index.ws", "{GET user FROM '/api/user' SCHEMA './missing.json'}")]).err().unwrap(), "ERR_MISSING_SCHEMA");
+    }
+
+    #[test]
+    fn convert_field_names() {
+        assert_eq!(field_name("userId"), "user_id");
+        assert_eq!(field_name("HTTPStatus"), "http_status");
+        assert_eq!(field_name("userID"), "user_id");
+        assert_eq!(field_name("URL"), "url");
+        assert_eq!(field_name("e-mail"), "e_mail");
+        assert_eq!(field_name("1st"), "field_1st");
+        assert_eq!(field_name("match"), "r#match");
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::LinkedList;
+
+use crate::strings::stringview::StringView;
+use super::CompilationError;
+
+/**
+ * Settings of a single environment, e.g. `[production]`
+ */
+pub struct Environment {
+    pub name: String,
+    /// prepended to all request urls starting with '/'
+    pub base_url: String,
+    /// whether routed templates are compiled into modules of their own (see `SplitterComponent`)
+    pub chunks: bool
+}
+
+/**
+ * Project configuration (`websorcery.conf`), e.g.
+ * ```text
+ * # data sources of the environments
+ * [development]
+ * base_url = http://localhost:8080
+ *
+ * [production]
+ * base_url = https://api.example.com
+ * chunks = true
+ * ```
+ */
+pub struct Config {
+    pub environments: LinkedList<Environment>
+}
+
+impl Config {
+    /// # Parses configuration
+    /// Empty lines and lines starting with '#' are ignored.
+    pub fn parse(source: &str) -> Result<Config, CompilationError> {
+        let mut config = Config { environments: LinkedList::new() };
+        let mut offset = 0;
+        for line in source.split('\n') {
+            // views count chars, not bytes
+            let length = line.chars().count();
+            let view = StringView::from_string(source, offset, offset + length.saturating_sub(1));
+            offset += length + 1;
+
+            let content = line.trim();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = content.strip_prefix('[').and_then(|section| section.strip_suffix(']')) {
+                let name = name.trim();
+                if config.get_environment(name).is_ok() {
+                    return Err(Config::error("ERR_DUPLICATE_ENVIRONMENT", format!("environment '{}' is configured more than once", name), "merge both sections into one".to_string(), view));
+                }
+                config.environments.push_back(Environment { name: name.to_string(), base_url: String::new(), chunks: false });
+                continue;
+            }
+
+            let (key, value) = match content.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(Config::error("ERR_CONFIG_SYNTAX", format!("line '{}' is neither a section nor a setting", content), "use '[environment]' or 'key = value'".to_string(), view))
+            };
+            let environment = match config.environments.back_mut() {
+                Some(environment) => environment,
+                None => return Err(Config::error("ERR_CONFIG_SYNTAX", format!("setting '{}' does not belong to an environment", key), "add a section like '[production]' before the setting".to_string(), view))
+            };
+            match key {
+                "base_url" => environment.base_url = value.trim_end_matches('/').to_string(),
+                "chunks" => environment.chunks = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(Config::error("ERR_INVALID_SETTING", format!("setting '{}' must be 'true' or 'false', not '{}'", key, value), format!("use '{} = true' or '{} = false'", key, key), view))
+                },
+                _ => return Err(Config::error("ERR_UNKNOWN_SETTING", format!("setting '{}' does not exist", key), "use one of the settings: base_url, chunks".to_string(), view))
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn get_environment(&self, name: &str) -> Result<&Environment, CompilationError> {
+        match self.environments.iter().find(|environment| environment.name == name) {
+            Some(environment) => Ok(environment),
+            None => Err(
+                CompilationError {
+                    name: "ERR_UNKNOWN_ENVIRONMENT".to_string(),
+                    desc: "cannot select environment".to_string(),
+                    cause: format!("environment '{}' is not configured", name),
+                    suggestion: format!("add section '[{}]' to the configuration", name),
+                    view: None,
+                    position: None
+                }
+            )
+        }
+    }
+
+    fn error(name: &str, cause: String, suggestion: String, view: StringView) -> CompilationError {
+        CompilationError {
+            name: name.to_string(),
+            desc: "cannot read configuration".to_string(),
+            cause,
+            suggestion,
+            view: Some(view),
+            position: None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn parse_environments() {
+        let config = Config::parse("# urls\n[development]\nbase_url = http://localhost:8080/\n\n[production]\n  base_url=https://api.example.com\nchunks = true\n").ok().unwrap();
+
+        assert_eq!(config.get_environment("development").ok().unwrap().base_url, "http://localhost:8080");
+        assert_eq!(config.get_environment("production").ok().unwrap().base_url, "https://api.example.com");
+        assert_eq!(config.get_environment("staging").err().unwrap().name, "ERR_UNKNOWN_ENVIRONMENT");
+        assert!(!config.get_environment("development").ok().unwrap().chunks);
+        assert!(config.get_environment("production").ok().unwrap().chunks);
+
+        assert_eq!(Config::parse("base_url = x").err().unwrap().name, "ERR_CONFIG_SYNTAX");
+        assert_eq!(Config::parse("[a]\nbase_url").err().unwrap().name, "ERR_CONFIG_SYNTAX");
+        assert_eq!(Config::parse("[a]\nurl = x").err().unwrap().name, "ERR_UNKNOWN_SETTING");
+        assert_eq!(Config::parse("[a]\nchunks = yes").err().unwrap().name, "ERR_INVALID_SETTING");
+        assert_eq!(Config::parse("[a]\n[a]").err().unwrap().name, "ERR_DUPLICATE_ENVIRONMENT");
+
+        // lines after non-ASCII chars are shown correctly
+        let err = Config::parse("# Übersicht\n[a]\nurl = x").err().unwrap();
+        assert_eq!(err.view.unwrap().cut(), "url = x");
+    }
+}
@@ -1,27 +1,62 @@
 
 pub mod output;
-mod model;
+pub mod model;
 pub mod components;
+pub mod symbols;
+pub mod config;
+pub mod sources;
 
 
 use std::collections::LinkedList;
 
 use crate::strings::{sourceview::SourcePosition, stringview::StringView};
+use components::{binder::BinderComponent, linker::LinkerComponent, parser::ParserComponent, scanner::InputComponent, scheduler::SchedulerComponent};
+use model::CoreModel;
+use sources::SourceDatabase;
 
-pub struct CompilationWarnings {
+#[derive(Clone)]
+pub struct CompilationWarning {
+    name: String,
+    cause: String,
+    suggestion: String,
+    view: Option<StringView>
+}
+
+impl output::ConsoleOutput for CompilationWarning {
+    fn get_short(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn get_cutout(&self) -> String {
+        match &self.view {
+            Some(view) => view.show_slice(10),
+            None => "(no source code available)".to_string()
+        }
+    }
 
+    fn get_message(&self) -> String {
+        format!("Cause: {}\nSuggestion: {}", self.cause, self.suggestion)
+    }
+
+    fn get_location(&self) -> String {
+        "[source]".to_string()
+    }
+
+    fn get_type(&self) -> String {
+        "\u{26A0} WARNING".to_string()
+    }
 }
 
-pub struct CompilationError<'this> {
+pub struct CompilationError {
     name: String,
     desc: String,
     cause: String,
     suggestion: String,
-    view: Option<StringView<'this>>,
+    view: Option<StringView>,
     position: Option<SourcePosition>
 }
 
-impl<'this> output::ConsoleOutput for CompilationError<'this> {
+impl output::ConsoleOutput for CompilationError {
     fn get_short(&self) -> String {
         self.name.to_string()
     }
@@ -50,19 +85,19 @@ impl<'this> output::ConsoleOutput for CompilationError<'this> {
 }
 
 
-enum CompilationStatus<'this> {
+enum CompilationStatus {
     OK,
-    Error(CompilationError<'this>)
+    Error(CompilationError)
 }
 
-pub struct CompilationState<'t> {
-    status: CompilationStatus<'t>,
-    warnings: LinkedList<CompilationWarnings>
+pub struct CompilationState {
+    status: CompilationStatus,
+    warnings: LinkedList<CompilationWarning>
 }
 
-impl<'this> CompilationState<'this> {
+impl CompilationState {
 
-    pub fn new() -> CompilationState<'this> {
+    pub fn new() -> CompilationState {
         Self {
             status: CompilationStatus::OK,
             warnings: LinkedList::new()
@@ -76,14 +111,59 @@ impl<'this> CompilationState<'this> {
         }
     }
 
-    pub fn error(&mut self, err: CompilationError<'this>) {
+    pub fn error(&mut self, err: CompilationError) {
         self.status = CompilationStatus::Error(err);
     }
 
-    pub fn get_error(&self) -> Option<&CompilationError<'this>> {
+    pub fn warn(&mut self, warning: CompilationWarning) {
+        self.warnings.push_back(warning);
+    }
+
+    /// Moves all warnings out of the state
+    pub fn take_warnings(&mut self) -> LinkedList<CompilationWarning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn get_error(&self) -> Option<&CompilationError> {
         match &self.status {
             CompilationStatus::OK => None,
             CompilationStatus::Error(err) => Some(err)
         }
     }
-}
\ No newline at end of file
+
+    /// State is consumed: returns `result` if no error occurred
+    pub fn finish<T>(self, result: T) -> Result<T, CompilationError> {
+        match self.status {
+            CompilationStatus::OK => Ok(result),
+            CompilationStatus::Error(err) => Err(err)
+        }
+    }
+}
+
+/// # Builds the core model of all templates
+/// Every source is scanned and parsed into a template, then inheritance and calls between templates are linked.
+/// * `sources` - id (path) and source code of every template
+/// * `warnings` - receives warnings of all templates
+///
+/// Use a `SourceDatabase` to build the model repeatedly, e.g. while sources are edited.
+pub fn build_model(sources: LinkedList<(String, &str)>, warnings: &mut LinkedList<CompilationWarning>) -> Result<CoreModel, CompilationError> {
+    let mut database = SourceDatabase::new();
+    for (id, source) in sources {
+        database.set(id, source);
+    }
+    database.build(warnings)
+}
+
+/// Links the parsed templates of the model, then schedules their requests and binds their nodes
+fn link_model(mut model: CoreModel) -> Result<CoreModel, CompilationError> {
+    let mut linker = LinkerComponent::new();
+    linker.inherit(&mut model);
+    if linker.is_ok() {
+        linker.link(&model);
+    }
+    let mut model = linker.finish(model)?;
+
+    SchedulerComponent::new().schedule(&mut model);
+    BinderComponent::new().bind(&mut model);
+    Ok(model)
+}
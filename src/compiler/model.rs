@@ -1,111 +1,994 @@
-use std::collections::LinkedList;
-use super::CompilationError;
+use std::{collections::LinkedList, ops::Range, time::Duration};
+use crate::strings::stringview::StringView;
+use super::symbols::{Scope, ScopeKind};
 
 /**
- * Nested reference to JSON element
+ * Step of a path into data
  */
-struct JsonElement<'t> {
-    sub: Option<&'t JsonElement<'t>>
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathKey {
+    /// member of an object, e.g. `.name` or `['first name']`
+    Property(String),
+    /// item of an array, e.g. `[0]`
+    Index(usize)
 }
 
 /**
- * Reference to a variables value
+ * Segment of a path, optional segments (`?.`) end the path with null instead of an error if the value before is null
  */
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathSegment {
+    pub key: PathKey,
+    pub optional: bool
+}
+
+/**
+ * Reference to a variables value, or to data inside of it, e.g. `user?.orders[0]['first name']`
+ */
+#[derive(Clone, Debug, PartialEq)]
 pub struct VariableReference {
-    id: String
+    /// name of the variable
+    pub id: String,
+    /// path into the value of the variable, empty for the value itself
+    pub path: Vec<PathSegment>
 }
 
-impl Clone for VariableReference {
-    fn clone(&self) -> Self {
-        VariableReference {
-            id: self.id.clone()
+impl VariableReference {
+    /**
+     * Reference to the value of a variable itself
+     */
+    pub fn new(id: &str) -> VariableReference {
+        VariableReference { id: id.to_string(), path: Vec::new() }
+    }
+
+    /**
+     * Parses a path of the form `root(.name|?.name|[index]|['key']|?.[...])*`.
+     * Names consist of letters, digits, '_' and '-', keys are any text except `'`.
+     */
+    pub fn parse(path: &str) -> Result<VariableReference, String> {
+        VariableReference::parse_spans(path).map(|(reference, _)| reference)
+    }
+
+    /**
+     * Positions (in characters) of the keys of the segments in the path, e.g. `name` of `.name` and `[0]` of `?.[0]`
+     */
+    pub fn spans(path: &str) -> Vec<Range<usize>> {
+        VariableReference::parse_spans(path).map(|(_, spans)| spans).unwrap_or_default()
+    }
+
+    fn parse_spans(path: &str) -> Result<(VariableReference, Vec<Range<usize>>), String> {
+        let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        let end = path.find(|c: char| !is_name(c)).unwrap_or(path.len());
+        if end == 0 {
+            return Err(format!("'{}' does not start with a variable name", path));
         }
+        let mut reference = VariableReference::new(&path[..end]);
+        let mut spans = Vec::new();
+        let mut rest = &path[end..];
+        while !rest.is_empty() {
+            let optional = rest.starts_with("?.");
+            let dotted = match (optional, rest.strip_prefix('.')) {
+                (true, _) => {
+                    rest = &rest[2..];
+                    !rest.starts_with('[')
+                },
+                (false, Some(name)) => {
+                    rest = name;
+                    true
+                },
+                (false, None) => false
+            };
+            let begin = path.len() - rest.len();
+            let key = if dotted {
+                let end = rest.find(|c: char| !is_name(c)).unwrap_or(rest.len());
+                if end == 0 {
+                    return Err(format!("name expected after '.' in '{}'", path));
+                }
+                let name = &rest[..end];
+                rest = &rest[end..];
+                PathKey::Property(name.to_string())
+            } else if let Some(index) = rest.strip_prefix('[') {
+                let (inner, after) = index.split_once(']').ok_or_else(|| format!("'[' is not closed in '{}'", path))?;
+                rest = after;
+                match inner.strip_prefix('\'').and_then(|key| key.strip_suffix('\'')) {
+                    Some(key) if !key.contains('\'') => PathKey::Property(key.to_string()),
+                    _ => PathKey::Index(inner.parse().map_err(|_| format!("'{}' is neither an index nor a quoted key in '{}'", inner, path))?)
+                }
+            } else {
+                return Err(format!("unexpected '{}' in '{}'", rest.chars().next().unwrap_or_default(), path));
+            };
+            let chars = |bytes: usize| path[..bytes].chars().count();
+            spans.push(chars(begin)..chars(path.len() - rest.len()));
+            reference.path.push(PathSegment { key, optional });
+        }
+        Ok((reference, spans))
+    }
+
+    /**
+     * Whether this reference points to the referenced data or to data inside of it
+     */
+    #[cfg(test)]
+    pub fn contains(&self, other: &VariableReference) -> bool {
+        self.id == other.id && other.path.len() >= self.path.len()
+            && self.path.iter().zip(other.path.iter()).all(|(segment, other)| segment.key == other.key)
+    }
+}
+
+/**
+ * Canonical form of the path, as understood by the runtime:
+ * names are joined by '.', indices and keys which are no names are written in brackets, e.g. `user?.orders[0]['first name']`
+ */
+impl std::fmt::Display for VariableReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.id)?;
+        for segment in self.path.iter() {
+            if segment.optional {
+                f.write_str("?")?;
+            }
+            match &segment.key {
+                PathKey::Property(name) if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+                    write!(f, ".{}", name)?,
+                key => {
+                    if segment.optional {
+                        f.write_str(".")?;
+                    }
+                    match key {
+                        PathKey::Property(name) => write!(f, "['{}']", name)?,
+                        PathKey::Index(index) => write!(f, "[{}]", index)?
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 }
 
 
 /**
  * Data/Values can be represented in multiple ways:
- * - it can come inside a json element (=path)
- * - reference to a variable containing the value
+ * - reference to a variable containing the value or to data inside of it, along with the path in the source (if there is one)
  * - the pure value as a String
  */
-enum Data<'t> {
-    Json(JsonElement<'t>),
-    Variable(VariableReference),
+#[derive(Clone)]
+pub enum Data {
+    Variable(VariableReference, Option<StringView>),
     Value(String)
 }
 
-pub struct PrintOperation<'t> {
-    content: Data<'t>
+/**
+ * Filter applied to a value, e.g. `items | length`
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    Length,
+    Upper,
+    Lower,
+    Trim
+}
+
+impl Filter {
+    pub fn from_name(name: &str) -> Option<Filter> {
+        match name {
+            "length" => Some(Filter::Length),
+            "upper" => Some(Filter::Upper),
+            "lower" => Some(Filter::Lower),
+            "trim" => Some(Filter::Trim),
+            _ => None
+        }
+    }
+}
+
+/**
+ * Single value of an expression, piped through its filters from left to right
+ */
+#[derive(Clone)]
+pub struct Operand {
+    pub data: Data,
+    pub filters: LinkedList<Filter>
+}
+
+/**
+ * Comparison of two sums in a condition, e.g. `items | length > 0`.
+ * Numbers (and text holding numbers) are compared by value, everything else is equal if its text is.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual
 }
 
+impl Comparison {
+    pub fn from_symbol(symbol: &str) -> Option<Comparison> {
+        match symbol {
+            "==" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            _ => None
+        }
+    }
 
-pub struct FetchOperation<'t> {
-    from: Data<'t>,
-    arguments: LinkedList<Data<'t>>
-} 
+    /// Orders only hold between numbers
+    pub fn is_order(&self) -> bool {
+        !matches!(self, Comparison::Equal|Comparison::NotEqual)
+    }
+}
 
-pub struct InsertOperation<'t> {
-    content: Data<'t>
+/**
+ * Operands joined by `+`, e.g. `'Hi ' + user.name`.
+ * Numbers are added, everything else is concatenated.
+ * Conditions may compare the sum with a second one, e.g. `user.age + 1 >= 18`.
+ */
+#[derive(Clone)]
+pub struct Expression {
+    pub operands: LinkedList<Operand>,
+    /// operator and operands of the right side of the comparison
+    pub comparison: Option<(Comparison, LinkedList<Operand>)>
 }
 
-pub struct CallOperation<'t> {
-    template: Data<'t>,
-    params: LinkedList<Data<'t>>
+impl Expression {
+    /// Operands of both sides of the comparison
+    pub fn all_operands(&self) -> impl Iterator<Item = &Operand> {
+        self.operands.iter().chain(self.comparison.iter().flat_map(|(_, operands)| operands.iter()))
+    }
 }
 
-pub enum TemplateOperation<'t> {
-    Print(PrintOperation<'t>),
-    Fetch(FetchOperation<'t>),
-    Insert(InsertOperation<'t>),
-    Call(CallOperation<'t>)
+#[derive(Clone)]
+pub struct PrintOperation {
+    pub content: Data
 }
 
 
-pub struct Template<'t>{
-    pub operations: LinkedList<TemplateOperation<'t>>,
-    pub variables: LinkedList<VariableReference>,
-    pub id: String
+/**
+ * HTTP method of a request
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum HttpMethod {
+    GET,
+    POST,
+    PUT,
+    PATCH,
+    DELETE
 }
 
-impl<'t> Template<'t> {
-    pub fn new(id: String) -> Template<'t> {
+impl HttpMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HttpMethod::GET => "GET",
+            HttpMethod::POST => "POST",
+            HttpMethod::PUT => "PUT",
+            HttpMethod::PATCH => "PATCH",
+            HttpMethod::DELETE => "DELETE"
+        }
+    }
+
+    /// Whether requests send data in their body, these are blocked while validation rules are violated
+    #[cfg(test)]
+    pub fn sends_body(&self) -> bool {
+        matches!(self, HttpMethod::POST|HttpMethod::PUT|HttpMethod::PATCH)
+    }
+}
+
+/**
+ * Custom header sent with a request, e.g. `HEADERS Authorization=session.token`
+ */
+#[derive(Clone)]
+pub struct Header {
+    pub name: String,
+    pub value: Data
+}
+
+/**
+ * Query parameter appended to the url of a request, e.g. `PARAMS id=route.id`
+ */
+#[derive(Clone)]
+pub struct QueryParam {
+    pub name: String,
+    pub value: Data
+}
+
+/**
+ * Body of a request: serialized data or the fields of a form
+ */
+#[derive(Clone)]
+pub enum RequestBody {
+    Data(Data),
+    /// id of the `<form>` element
+    Form(String)
+}
+
+/**
+ * `{ERROR e} ...` branch of a request, `e.status` and `e.message` describe the failure
+ */
+#[derive(Clone)]
+pub struct ErrorState {
+    pub variable: VariableReference,
+    pub operations: LinkedList<TemplateOperation>
+}
+
+/**
+ * JSON file describing the response of a request, e.g. `SCHEMA './user.schema.json'` or `SAMPLE './user.json'`
+ */
+#[derive(Clone)]
+pub struct SchemaReference {
+    /// path of the file, relative to the compiled sources like template ids
+    pub id: String,
+    /// whether the file contains sample data instead of a JSON Schema
+    pub sample: bool,
+    /// option of the request, used for error reporting
+    pub view: StringView
+}
+
+/**
+ * Request to a remote data source, e.g. `{GET user FROM '/api/user'}` or `{POST user TO '/api/users' AS created}`
+ */
+#[derive(Clone)]
+pub struct FetchOperation {
+    pub method: HttpMethod,
+    /// variable the response is bound to
+    pub target: Option<VariableReference>,
+    pub from: Data,
+    pub query: LinkedList<QueryParam>,
+    pub body: Option<RequestBody>,
+    pub headers: LinkedList<Header>,
+    /// cookies and authorization are sent to other origins, e.g. `WITH credentials`
+    pub credentials: bool,
+    /// successful responses are reused for this long, e.g. `CACHE 60s`
+    pub cache: Option<Duration>,
+    /// request is sent again periodically and re-renders its content, e.g. `REFRESH EVERY 10s`
+    pub refresh: Option<Duration>,
+    /// shape of the response, checked against the paths using it
+    pub schema: Option<SchemaReference>,
+    /// shown once the response has arrived, e.g. `{GET items FROM '/api'} ... {LOADING} ... {END}`
+    pub operations: LinkedList<TemplateOperation>,
+    /// `{LOADING}` branch, shown while the request is pending
+    pub loading: Option<LinkedList<TemplateOperation>>,
+    /// `{EMPTY}` branch, shown instead of the operations if the response is empty
+    pub empty: Option<LinkedList<TemplateOperation>>,
+    /// `{ERROR e}` branch, shown if the request failed
+    pub error: Option<ErrorState>,
+    /// position among the requests of the template, assigned by the scheduler
+    pub index: usize,
+    /// indices of the requests whose responses are needed to build this request, assigned by the scheduler
+    pub dependencies: LinkedList<usize>,
+    /// binding of the rendered branch of requests with states, assigned by the binder
+    pub binding: Option<usize>
+}
+
+impl FetchOperation {
+    pub fn new(method: HttpMethod, from: Data) -> FetchOperation {
         Self {
+            method,
+            target: None,
+            from,
+            query: LinkedList::new(),
+            body: None,
+            headers: LinkedList::new(),
+            credentials: false,
+            cache: None,
+            refresh: None,
+            schema: None,
             operations: LinkedList::new(),
-            variables: LinkedList::new(),
-            id
+            loading: None,
+            empty: None,
+            error: None,
+            index: 0,
+            dependencies: LinkedList::new(),
+            binding: None
         }
     }
 
-    pub fn get_variable_by_id(&self, id: String) -> Result<VariableReference, CompilationError> {
-        for var in self.variables.iter() {
-            if var.id.eq(&id) {
-                return Ok(var.clone())
+    /// Request has states if any of `{LOADING}`, `{EMPTY}` or `{ERROR}` is given
+    pub fn has_states(&self) -> bool {
+        self.loading.is_some() || self.empty.is_some() || self.error.is_some()
+    }
+}
+
+/**
+ * Protocol of a stream of messages
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transport {
+    /// `ws://` and `wss://` urls
+    WebSocket,
+    /// server-sent events, e.g. `FROM SSE '/stream'`
+    EventSource
+}
+
+/**
+ * How a message of a stream changes the subscribed variable
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StreamMode {
+    /// the variable holds the latest message
+    Replace,
+    /// the variable holds an array of all messages
+    Append
+}
+
+/**
+ * Variable bound to a stream of JSON messages, e.g. `{SUBSCRIBE prices FROM 'ws://localhost/prices'}`
+ * or `{SUBSCRIBE events FROM SSE '/stream' APPEND}`
+ */
+#[derive(Clone)]
+pub struct SubscribeOperation {
+    pub target: VariableReference,
+    pub transport: Transport,
+    pub from: Data,
+    pub mode: StreamMode,
+    /// position among the subscriptions of the template, assigned by the scheduler
+    pub index: usize
+}
+
+/**
+ * What happens when an event occurs
+ */
+#[derive(Clone)]
+pub enum EventAction {
+    /// handler registered by the client, e.g. `CALL refresh prices`
+    Call {
+        handler: String,
+        argument: Option<Data>
+    },
+    /// request sent once per event, its response is bound to the target if given
+    Request(Box<FetchOperation>)
+}
+
+/**
+ * Listener of the element whose tag contains it, e.g. `<button {ON click CALL refresh}>`
+ * or `<form {ON submit POST form TO '/api/save'}>`
+ */
+#[derive(Clone)]
+pub struct EventOperation {
+    /// name of the DOM event, e.g. `click`
+    pub event: String,
+    pub action: EventAction,
+    /// position among the listeners of the template, i.e. in its `ws_listener` table, assigned by the scheduler
+    pub index: usize
+}
+
+/**
+ * Form control reflecting a variable, which is updated whenever the event occurs, e.g. `<input {BIND input TO user.email}>`
+ */
+#[derive(Clone)]
+pub struct BindOperation {
+    /// name of the DOM event, e.g. `input` or `change`
+    pub event: String,
+    /// path of the updated value, its root variable is writable
+    pub target: VariableReference,
+    /// position among the bound controls of all templates, assigned by the scheduler
+    pub index: usize
+}
+
+/**
+ * Condition a value must meet before data is sent
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    /// value is neither missing nor blank
+    Required,
+    Email,
+    MinLength(usize),
+    MaxLength(usize),
+    /// regular expression the whole value must match, see `Pattern`
+    Pattern(String),
+    Min(f64),
+    Max(f64)
+}
+
+impl Rule {
+    /// Message shown if the rule is violated and no custom message is given
+    pub fn default_message(&self) -> String {
+        match self {
+            Rule::Required => "Required".to_string(),
+            Rule::Email => "Invalid email address".to_string(),
+            Rule::MinLength(length) => format!("At least {} characters", length),
+            Rule::MaxLength(length) => format!("At most {} characters", length),
+            Rule::Pattern(_) => "Invalid format".to_string(),
+            Rule::Min(min) => format!("At least {}", min),
+            Rule::Max(max) => format!("At most {}", max)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ValidationRule {
+    pub rule: Rule,
+    /// custom message, e.g. `REQUIRED 'Please enter your email'`
+    pub message: Option<String>
+}
+
+impl ValidationRule {
+    pub fn message(&self) -> String {
+        self.message.clone().unwrap_or_else(|| self.rule.default_message())
+    }
+}
+
+/**
+ * Rules of a value, checked before any `POST`, `PUT` or `PATCH` request of the page is sent,
+ * e.g. `{VALIDATE user.email REQUIRED EMAIL AS emailError}`
+ */
+#[derive(Clone)]
+pub struct ValidateOperation {
+    /// path of the validated value
+    pub target: VariableReference,
+    /// rules in order of declaration, the first violated rule gives the message
+    pub rules: Vec<ValidationRule>,
+    /// variable holding the message of the violated rule, null while the value is valid or untouched
+    pub error: Option<VariableReference>
+}
+
+/**
+ * Template rendered in place if the current path matches, e.g. `{ROUTE '/users/:id' USE userpage}`.
+ * Only the first matching route of a template is rendered, the fallback if none matches.
+ */
+#[derive(Clone)]
+pub struct RouteOperation {
+    /// pattern of the path, e.g. `/users/:id`, `None` for the fallback (`{ROUTE FALLBACK USE ...}`)
+    pub path: Option<String>,
+    /// id of the routed template, which receives the parameters of the path as its parameters
+    pub template: String,
+    /// position among the routes of the template, assigned by the scheduler
+    pub index: usize,
+    /// rendered template, assigned by the binder
+    pub binding: Option<usize>,
+    /// instruction of the route, used for error reporting
+    pub view: StringView
+}
+
+impl RouteOperation {
+    /// Names of the parameters of the path, e.g. `id` of `/users/:id`
+    pub fn params(&self) -> Vec<&str> {
+        self.path.iter()
+            .flat_map(|path| path.split('/'))
+            .filter_map(|segment| segment.strip_prefix(':'))
+            .collect()
+    }
+
+    /// # Values of the parameters if the route matches the path
+    /// Query and fragment of the path are ignored, so are empty segments, e.g. of trailing slashes.
+    /// Segments are percent-decoded before they are compared, e.g. `/users/J%C3%BCrgen` gives the id `Jürgen`.
+    /// The fallback does not match any path itself.
+    #[cfg(test)]
+    pub fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let mut segments = path.split('/').filter(|segment| !segment.is_empty()).map(percent_decode);
+        let mut params = Vec::new();
+        for pattern in self.path.as_ref()?.split('/').filter(|segment| !segment.is_empty()) {
+            let segment = segments.next()?;
+            match pattern.strip_prefix(':') {
+                Some(name) => params.push((name.to_string(), segment)),
+                None if percent_decode(pattern) == segment => (),
+                None => return None
+            }
+        }
+        match segments.next() {
+            Some(_) => None,
+            None => Some(params)
+        }
+    }
+}
+
+/// Segment of a path with `%XX` sequences replaced by the bytes they encode, invalid sequences are kept
+#[cfg(test)]
+fn percent_decode(segment: &str) -> String {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let decoded = match tail {
+            [high, low, ..] if byte == b'%' && high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+                std::str::from_utf8(&[*high, *low]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            },
+            _ => None
+        };
+        match decoded {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            },
+            None => {
+                bytes.push(byte);
+                rest = tail;
             }
         }
+    }
+    String::from_utf8(bytes).unwrap_or_else(|_| segment.to_string())
+}
+
+/**
+ * Data changing while a page is shown: the response of a request or the messages of a stream,
+ * identified by their index within the template
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Source {
+    Request(usize),
+    Stream(usize),
+    /// writable variable changed by form controls, by index within `Template::writable`
+    Input(usize),
+    /// current path, which changes when the client navigates
+    Route
+}
+
+/**
+ * Literal text or inserted value of an attribute, e.g. `/users/` and `user.id` of `href="/users/{user.id}"`
+ */
+#[derive(Clone)]
+pub enum AttributePart {
+    Text(String),
+    Value(Data)
+}
+
+#[derive(Clone)]
+pub enum BindingKind {
+    /// text node of an inserted value
+    Text,
+    /// attribute containing inserted values, its value is made of the parts
+    Attribute {
+        name: String,
+        parts: Vec<AttributePart>
+    },
+    /// markup whose structure changes, e.g. a loop, a branch or the states of a request
+    Region
+}
+
+/**
+ * Node of the rendered page which is patched when its sources change,
+ * instead of rendering the whole template again
+ */
+#[derive(Clone)]
+pub struct Binding {
+    pub kind: BindingKind,
+    pub sources: Vec<Source>,
+    /// index of the region containing the node, the node is rendered again with it
+    pub parent: Option<usize>
+}
+
+impl Binding {
+    /**
+     * Name the client finds the bound node by, e.g. `pages/index.ws:3`.
+     * Text nodes and regions are enclosed in the comments `<!--ws:name-->` and `<!--/ws:name-->`,
+     * elements list the names of their bound attributes in the attribute `data-ws-anchor`.
+     */
+    pub fn anchor(template: &str, index: usize) -> String {
+        format!("{}:{}", template, index)
+    }
+}
+
+#[derive(Clone)]
+pub struct InsertOperation {
+    pub content: Data,
+    /// binding of the text node or attribute, assigned by the binder
+    pub binding: Option<usize>
+}
+
+/**
+ * Named parameter passed to a template call
+ */
+#[derive(Clone)]
+pub struct CallParam {
+    pub name: String,
+    pub value: Data
+}
+
+/**
+ * Markup passed into a slot of the called template, e.g. `{FILL content} ... {END}`
+ */
+#[derive(Clone)]
+pub struct SlotContent {
+    pub name: String,
+    pub operations: LinkedList<TemplateOperation>
+}
+
+#[derive(Clone)]
+pub struct CallOperation {
+    /// id of the called template
+    pub template: Data,
+    pub params: LinkedList<CallParam>,
+    pub slots: LinkedList<SlotContent>,
+    /// instruction of the call, used for error reporting
+    pub view: StringView,
+    /// binding of the rendered markup, assigned by the binder
+    pub binding: Option<usize>
+}
+
+/**
+ * Place where the caller's markup is inserted, e.g. `{SLOT content}`
+ */
+#[derive(Clone)]
+pub struct SlotOperation {
+    pub name: String
+}
+
+/**
+ * Assigns the value of an expression to a variable, e.g. `{SET total = order.items | length}`
+ */
+#[derive(Clone)]
+pub struct SetOperation {
+    pub target: VariableReference,
+    pub value: Expression
+}
+
+/**
+ * `{IF condition} ... {ELSE} ... {END}`
+ */
+#[derive(Clone)]
+pub struct ConditionalOperation {
+    pub condition: Expression,
+    pub operations: LinkedList<TemplateOperation>,
+    /// operations of the `{ELSE}` branch
+    pub alternative: LinkedList<TemplateOperation>,
+    /// binding of the rendered branch, assigned by the binder
+    pub binding: Option<usize>
+}
+
+/**
+ * `{FOR item IN items} ... {END}`
+ */
+#[derive(Clone)]
+pub struct LoopOperation {
+    pub item: VariableReference,
+    pub items: Data,
+    pub operations: LinkedList<TemplateOperation>,
+    /// binding of the rendered items, assigned by the binder
+    pub binding: Option<usize>
+}
+
+/**
+ * Named region which can be overridden by extending templates, e.g. `{BLOCK content} ... {END}`
+ */
+#[derive(Clone)]
+pub struct BlockOperation {
+    pub name: String,
+    pub operations: LinkedList<TemplateOperation>,
+    /// opening instruction of the block, used for error reporting
+    pub view: StringView
+}
+
+#[derive(Clone)]
+pub enum TemplateOperation {
+    Print(PrintOperation),
+    Fetch(Box<FetchOperation>),
+    Subscribe(SubscribeOperation),
+    On(EventOperation),
+    Bind(BindOperation),
+    Validate(ValidateOperation),
+    Route(RouteOperation),
+    Insert(InsertOperation),
+    Call(CallOperation),
+    Slot(SlotOperation),
+    Block(BlockOperation),
+    Set(SetOperation),
+    If(ConditionalOperation),
+    Loop(LoopOperation)
+}
+
+impl TemplateOperation {
+    /// Operations nested inside of this operation (bodies, branches, blocks and slot contents)
+    pub fn nested_operations(&self) -> Vec<&LinkedList<TemplateOperation>> {
+        match self {
+            TemplateOperation::Call(call) => call.slots.iter().map(|slot| &slot.operations).collect(),
+            TemplateOperation::Block(block) => vec![&block.operations],
+            TemplateOperation::If(conditional) => vec![&conditional.operations, &conditional.alternative],
+            TemplateOperation::Loop(repetition) => vec![&repetition.operations],
+            TemplateOperation::Fetch(fetch) => std::iter::once(&fetch.operations)
+                .chain(fetch.loading.as_ref())
+                .chain(fetch.empty.as_ref())
+                .chain(fetch.error.as_ref().map(|error| &error.operations))
+                .collect(),
+            _ => Vec::new()
+        }
+    }
+
+    pub fn nested_operations_mut(&mut self) -> Vec<&mut LinkedList<TemplateOperation>> {
+        match self {
+            TemplateOperation::Call(call) => call.slots.iter_mut().map(|slot| &mut slot.operations).collect(),
+            TemplateOperation::Block(block) => vec![&mut block.operations],
+            TemplateOperation::If(conditional) => vec![&mut conditional.operations, &mut conditional.alternative],
+            TemplateOperation::Loop(repetition) => vec![&mut repetition.operations],
+            TemplateOperation::Fetch(fetch) => std::iter::once(&mut fetch.operations)
+                .chain(fetch.loading.as_mut())
+                .chain(fetch.empty.as_mut())
+                .chain(fetch.error.as_mut().map(|error| &mut error.operations))
+                .collect(),
+            _ => Vec::new()
+        }
+    }
+
+    /// All operations of the list and those nested inside of them, each operation in front of its nested ones
+    pub fn walk(operations: &LinkedList<TemplateOperation>) -> Walk<'_> {
+        Walk {
+            stack: vec![operations.iter()],
+            nested: 0
+        }
+    }
+}
+
+/**
+ * Iterator over operations in order of appearance, including nested ones (see `TemplateOperation::walk`)
+ */
+pub struct Walk<'a> {
+    stack: Vec<std::collections::linked_list::Iter<'a, TemplateOperation>>,
+    /// number of operation lists nested inside of the operation returned last
+    nested: usize
+}
 
-        Err(
-            CompilationError {
-                name: "ERR_UNDEFINED_VARIABLE".to_string(), 
-                cause: format!("variable '{}' has not been defined in template '{}'", id, self.id), 
-                suggestion: format!("define variable '{}' before its use", id), 
-                desc: format!("cannot use variable '{}'", id),
-                view: None,
-                position: None
+impl Walk<'_> {
+    /// Skips the operations nested inside of the operation returned last
+    pub fn skip_nested(&mut self) {
+        self.stack.truncate(self.stack.len() - self.nested);
+        self.nested = 0;
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = &'a TemplateOperation;
+
+    fn next(&mut self) -> Option<&'a TemplateOperation> {
+        while let Some(operations) = self.stack.last_mut() {
+            if let Some(operation) = operations.next() {
+                let nested = operation.nested_operations();
+                self.nested = nested.len();
+                self.stack.extend(nested.into_iter().rev().map(|operations| operations.iter()));
+                return Some(operation);
             }
-        )
+            self.stack.pop();
+        }
+        self.nested = 0;
+        None
     }
 }
 
-pub struct CoreModel<'t> {
-    pub templates: LinkedList<Template<'t>>,
+
+/**
+ * Imported template, e.g. `{IMPORT './card.ws' AS card}`
+ */
+#[derive(Clone)]
+pub struct ImportDeclaration {
+    /// id of the imported template (path resolved relative to the importing template)
+    pub template: String,
+    pub alias: String
+}
+
+/**
+ * Extended template, e.g. `{EXTENDS './base.ws'}`
+ */
+#[derive(Clone)]
+pub struct ExtendsDeclaration {
+    /// id of the extended template (path resolved relative to the extending template)
+    pub template: String,
+    pub view: StringView
+}
+
+/**
+ * Variable used by an extending template, which has not been defined by the template itself.
+ * It must be defined by the extended template.
+ */
+#[derive(Clone)]
+pub struct UnresolvedVariable {
+    pub variable: VariableReference,
+    /// outermost `{BLOCK}` using the variable, the variable must be visible where the extended template declares it
+    pub block: Option<String>,
+    pub view: StringView
 }
 
-impl<'t> CoreModel<'t> {
-    pub fn new() -> CoreModel<'t> {
+#[derive(Clone)]
+pub struct Template {
+    pub operations: LinkedList<TemplateOperation>,
+    /// variables of the template scope
+    pub variables: Scope,
+    /// parameters declared in the `{PARAMS ...}` header
+    pub params: LinkedList<VariableReference>,
+    pub imports: LinkedList<ImportDeclaration>,
+    pub extends: Option<ExtendsDeclaration>,
+    pub unresolved: LinkedList<UnresolvedVariable>,
+    /// nodes depending on data which changes while the page is shown, assigned by the binder
+    pub bindings: Vec<Binding>,
+    /// variables changed by form controls using `{BIND}`
+    pub writable: LinkedList<VariableReference>,
+    pub id: String
+}
+
+impl Template {
+    pub fn new(id: String) -> Template {
+        Self {
+            operations: LinkedList::new(),
+            variables: Scope::new(ScopeKind::Template),
+            params: LinkedList::new(),
+            imports: LinkedList::new(),
+            extends: None,
+            unresolved: LinkedList::new(),
+            bindings: Vec::new(),
+            writable: LinkedList::new(),
+            id
+        }
+    }
+
+    pub fn get_import_by_alias(&self, alias: &str) -> Option<&ImportDeclaration> {
+        self.imports.iter().find(|import| import.alias == alias)
+    }
+
+    /// Requests of the template in order of appearance, including nested ones and those sent by listeners
+    pub fn requests(&self) -> Vec<&FetchOperation> {
+        TemplateOperation::walk(&self.operations)
+            .filter_map(|operation| match operation {
+                TemplateOperation::Fetch(fetch) => Some(fetch.as_ref()),
+                TemplateOperation::On(EventOperation { action: EventAction::Request(fetch), .. }) => Some(fetch.as_ref()),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Ids of the templates inserted by the template in order of appearance, including nested calls
+    pub fn calls(&self) -> Vec<&str> {
+        TemplateOperation::walk(&self.operations)
+            .filter_map(|operation| match operation {
+                TemplateOperation::Call(CallOperation { template: Data::Value(id), .. }) => Some(id.as_str()),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Names of the `{SLOT ...}`s declared by the template in order of appearance, including nested ones
+    pub fn slots(&self) -> Vec<&str> {
+        TemplateOperation::walk(&self.operations)
+            .filter_map(|operation| match operation {
+                TemplateOperation::Slot(slot) => Some(slot.name.as_str()),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Routes of the template in order of appearance, including nested ones
+    pub fn routes(&self) -> Vec<&RouteOperation> {
+        TemplateOperation::walk(&self.operations)
+            .filter_map(|operation| match operation {
+                TemplateOperation::Route(route) => Some(route),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Route of the template rendered for the path: the first matching one, otherwise the fallback
+    #[cfg(test)]
+    pub fn active_route(&self, path: &str) -> Option<&RouteOperation> {
+        let routes = self.routes();
+        routes.iter().find(|route| route.matches(path).is_some())
+            .or_else(|| routes.iter().find(|route| route.path.is_none()))
+            .copied()
+    }
+}
+
+/**
+ * Module of the compiled client. The core module contains the runtime, the pages and all templates
+ * used by more than one route, every other module the templates only needed by a single routed template.
+ * The client loads these modules on demand, once their route matches.
+ */
+pub struct Chunk {
+    /// id of the routed template the module is loaded for, `None` for the core module
+    pub entry: Option<String>,
+    /// ids of the templates compiled into the module, in order of the model
+    pub templates: Vec<String>
+}
+
+pub struct CoreModel {
+    pub templates: LinkedList<Template>,
+}
+
+impl CoreModel {
+    pub fn new() -> CoreModel {
         CoreModel {
             templates: LinkedList::new()
         }
     }
+
+    pub fn get_template_by_id(&self, id: &str) -> Option<&Template> {
+        self.templates.iter().find(|temp| temp.id == id)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,221 @@
+use std::{any::Any, collections::{HashMap, LinkedList}, num::NonZeroUsize, sync::Arc, thread};
+
+use crate::strings::sourceview::SourceView;
+use super::{CompilationError, CompilationWarning, InputComponent, ParserComponent, link_model, model::{CoreModel, Template}};
+
+/**
+ * Template parsed from a source, along with the text it was parsed from and the warnings of parsing it
+ */
+#[derive(Clone)]
+struct ParsedTemplate {
+    source: Arc<str>,
+    template: Template,
+    warnings: LinkedList<CompilationWarning>
+}
+
+/// # Sources of all templates and the templates parsed from them
+/// Views into the sources (see `StringView`) share the text of their source, so templates and errors do not borrow
+/// from the database and can be kept or sent to other threads.
+///
+/// Templates are scanned and parsed independently of each other: a build parses all sources changed since the
+/// previous build in parallel and reuses the templates of unchanged sources. Linking and everything after it
+/// depends on all templates, so it runs for every build.
+pub struct SourceDatabase {
+    /// id (path) and text of every source, in order of adding
+    sources: Vec<(String, Arc<str>)>,
+    /// templates of the previous builds by id
+    parsed: HashMap<String, ParsedTemplate>,
+    /// ids of the templates parsed by the last build, in order of the sources
+    reparsed: Vec<String>
+}
+
+impl SourceDatabase {
+    pub fn new() -> SourceDatabase {
+        Self {
+            sources: Vec::new(),
+            parsed: HashMap::new(),
+            reparsed: Vec::new()
+        }
+    }
+
+    /// Adds the source of a template, or replaces it if the template has been added before
+    pub fn set(&mut self, id: String, text: &str) {
+        match self.sources.iter_mut().find(|(source, _)| *source == id) {
+            Some((_, source)) if **source == *text => {},
+            Some((_, source)) => *source = Arc::from(text),
+            None => self.sources.push((id, Arc::from(text)))
+        }
+    }
+
+    /// Removes the source of a template, e.g. after its file has been deleted
+    pub fn remove(&mut self, id: &str) {
+        self.sources.retain(|(source, _)| source != id);
+        self.parsed.remove(id);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.sources.iter().find(|(source, _)| source == id).map(|(_, text)| text.as_ref())
+    }
+
+    /// Ids of the templates parsed by the last build, all other templates have been reused
+    pub fn reparsed(&self) -> &[String] {
+        &self.reparsed
+    }
+
+    /// # Builds the core model of all templates
+    /// Errors are reported for the first template (in order of adding) which cannot be parsed.
+    /// * `warnings` - receives warnings of all templates, including the reused ones
+    pub fn build(&mut self, warnings: &mut LinkedList<CompilationWarning>) -> Result<CoreModel, CompilationError> {
+        let changed: Vec<(String, Arc<str>)> = self.sources.iter()
+            .filter(|(id, text)| self.parsed.get(id).is_none_or(|parsed| parsed.source != *text))
+            .cloned()
+            .collect();
+        self.reparsed = changed.iter().map(|(id, _)| id.clone()).collect();
+
+        let mut errors = HashMap::new();
+        for (id, result) in SourceDatabase::parse_all(changed) {
+            match result {
+                Ok(parsed) => {
+                    self.parsed.insert(id, parsed);
+                },
+                Err(err) => {
+                    self.parsed.remove(&id);
+                    errors.insert(id, err);
+                }
+            }
+        }
+
+        let mut model = CoreModel::new();
+        for (id, _) in self.sources.iter() {
+            if let Some(err) = errors.remove(id) {
+                return Err(err);
+            }
+            let parsed = &self.parsed[id];
+            warnings.extend(parsed.warnings.iter().cloned());
+            model.templates.push_back(parsed.template.clone());
+        }
+        link_model(model)
+    }
+
+    /// # Parses the sources on all available cores, results are in order of the sources
+    /// If a thread fails, all sources it parsed fail with an error, so they are parsed again by the next build.
+    fn parse_all(sources: Vec<(String, Arc<str>)>) -> Vec<(String, Result<ParsedTemplate, CompilationError>)> {
+        if sources.is_empty() {
+            return Vec::new();
+        }
+        let workers = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let size = sources.len().div_ceil(workers);
+        thread::scope(|scope| {
+            let handles: Vec<_> = sources.chunks(size).map(|chunk| (chunk, scope.spawn(move || {
+                chunk.iter().map(|(id, text)| (id.clone(), SourceDatabase::parse(id, text))).collect::<Vec<_>>()
+            }))).collect();
+            handles.into_iter().flat_map(|(chunk, handle)| handle.join().unwrap_or_else(|panic| {
+                chunk.iter().map(|(id, _)| (id.clone(), Err(SourceDatabase::failed_parser(id, panic.as_ref())))).collect()
+            })).collect()
+        })
+    }
+
+    fn failed_parser(id: &str, panic: &(dyn Any + Send)) -> CompilationError {
+        let message = panic.downcast_ref::<&str>().copied()
+            .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown failure");
+        CompilationError {
+            name: "ERR_PARSER_FAILED".to_string(),
+            desc: "cannot parse template".to_string(),
+            cause: format!("the parser stopped while parsing '{}': {}", id, message),
+            suggestion: "this is a bug in the compiler, please report it together with the template".to_string(),
+            position: None,
+            view: None
+        }
+    }
+
+    fn parse(id: &str, source: &Arc<str>) -> Result<ParsedTemplate, CompilationError> {
+        let mut input = InputComponent::new(id.to_string());
+        input.scan(SourceView::from_string(source.clone()));
+
+        let mut parser = ParserComponent::new(id.to_string());
+        parser.parse(input.finish()?);
+        let warnings = parser.take_warnings();
+        Ok(ParsedTemplate { source: source.clone(), template: parser.finish()?, warnings })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::LinkedList, thread};
+
+    use crate::compiler::model::{CoreModel, Data, PrintOperation, TemplateOperation};
+    use super::SourceDatabase;
+
+    /// Plain text of all templates
+    fn texts(model: &CoreModel) -> Vec<String> {
+        model.templates.iter().flat_map(|template| template.operations.iter()).filter_map(|operation| match operation {
+            TemplateOperation::Print(PrintOperation { content: Data::Value(text) }) => Some(text.clone()),
+            _ => None
+        }).collect()
+    }
+
+    #[test]
+    fn reuse_unchanged_templates() {
+        let mut database = SourceDatabase::new();
+        database.set("index.ws".to_string(), "{IMPORT './card.ws' AS card}<main>{INSERT card}</main>");
+        database.set("card.ws".to_string(), "<div>card</div>");
+        database.set("about.ws".to_string(), "<p>about</p>");
+
+        let model = database.build(&mut LinkedList::new()).ok().unwrap();
+        assert_eq!(database.reparsed(), &["index.ws", "card.ws", "about.ws"]);
+        assert_eq!(texts(&model), vec!["<main>", "</main>", "<div>card</div>", "<p>about</p>"]);
+
+        database.set("card.ws".to_string(), "<div>new card</div>");
+        database.set("about.ws".to_string(), "<p>about</p>");
+        let model = database.build(&mut LinkedList::new()).ok().unwrap();
+        assert_eq!(database.reparsed(), &["card.ws"]);
+        assert_eq!(texts(&model), vec!["<main>", "</main>", "<div>new card</div>", "<p>about</p>"]);
+
+        database.remove("about.ws");
+        assert!(database.get("about.ws").is_none());
+        assert_eq!(database.build(&mut LinkedList::new()).ok().unwrap().templates.len(), 2);
+        assert!(database.reparsed().is_empty());
+    }
+
+    #[test]
+    fn report_errors_in_order() {
+        let mut database = SourceDatabase::new();
+        database.set("a.ws".to_string(), "<p>a</p>");
+        database.set("b.ws".to_string(), "{user}");
+        database.set("c.ws".to_string(), "{END}");
+        assert_eq!(database.build(&mut LinkedList::new()).err().unwrap().name, "ERR_UNDEFINED_VARIABLE");
+
+        // templates which failed are parsed again, even if their source is unchanged
+        database.set("b.ws".to_string(), "{SET user = 'Ada'}{user}");
+        assert_eq!(database.build(&mut LinkedList::new()).err().unwrap().name, "ERR_UNEXPECTED_END");
+        assert_eq!(database.reparsed(), &["b.ws", "c.ws"]);
+
+        database.set("c.ws".to_string(), "<p>c</p>");
+        let mut warnings = LinkedList::new();
+        database.set("a.ws".to_string(), "{SET x = 1}{SET x = 2}{x}");
+        assert!(database.build(&mut warnings).is_ok());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn report_failed_parsers() {
+        let panic = thread::spawn(|| panic!("index out of bounds")).join().err().unwrap();
+        let err = SourceDatabase::failed_parser("index.ws", panic.as_ref());
+        assert_eq!(err.name, "ERR_PARSER_FAILED");
+        assert_eq!(err.cause, "the parser stopped while parsing 'index.ws': index out of bounds");
+    }
+
+    #[test]
+    fn build_on_other_threads() {
+        let mut database = SourceDatabase::new();
+        database.set("index.ws".to_string(), "<p>index</p>");
+        // models and errors do not borrow from the database, so they can be moved to other threads
+        let model = thread::scope(|scope| scope.spawn(|| database.build(&mut LinkedList::new()).ok().unwrap()).join().unwrap());
+        database.set("index.ws".to_string(), "{user}");
+        let err = thread::spawn(move || database.build(&mut LinkedList::new()).err().unwrap()).join().unwrap();
+
+        assert_eq!(texts(&model), vec!["<p>index</p>"]);
+        assert_eq!(err.name, "ERR_UNDEFINED_VARIABLE");
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use super::{CompilationError, model::VariableReference};
+
+/**
+ * Kind of region a scope belongs to
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScopeKind {
+    /// top level of a template
+    Template,
+    /// `{BLOCK}`, `{FILL}` and the branches of `{IF}`
+    Block,
+    /// body of `{FOR}`
+    Loop,
+    /// action of `{ON}`, containing the fields of the event's `form`
+    Handler
+}
+
+/**
+ * How a variable has been introduced
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    /// `{GET}` and `{SET}`
+    Variable,
+    /// declared in the `{PARAMS ...}` header
+    Param,
+    /// item of a `{FOR}` loop, cannot be reassigned inside of its loop
+    LoopItem
+}
+
+#[derive(Clone)]
+pub struct Symbol {
+    pub variable: VariableReference,
+    pub kind: SymbolKind
+}
+
+/**
+ * Variables defined in a single scope, in order of their definition
+ */
+#[derive(Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    symbols: Vec<Symbol>,
+    index: HashMap<String, usize>
+}
+
+impl Scope {
+    pub fn new(kind: ScopeKind) -> Scope {
+        Self {
+            kind,
+            symbols: Vec::new(),
+            index: HashMap::new()
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Symbol> {
+        self.index.get(id).map(|&i| &self.symbols[i])
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.index.contains_key(id)
+    }
+
+    /// Adds symbol to the scope, returns false if the scope already contains a variable of that name
+    pub fn insert(&mut self, symbol: Symbol) -> bool {
+        if self.contains(&symbol.variable.id) {
+            return false;
+        }
+        self.index.insert(symbol.variable.id.clone(), self.symbols.len());
+        self.symbols.push(symbol);
+        true
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// Error for a variable which is not defined in this scope
+    pub fn undefined_variable(&self, id: &str, template: &str) -> CompilationError {
+        undefined_variable(id, template, self.symbols.iter().map(|symbol| symbol.variable.id.as_str()))
+    }
+}
+
+/**
+ * Result of a definition in the symbol table
+ */
+#[derive(Debug, PartialEq)]
+pub enum Definition {
+    New,
+    /// variable of the same name already exists in the innermost scope, its value is replaced
+    Redefined,
+    /// variable of the same name exists in an outer scope and is hidden
+    Shadowed(ScopeKind),
+    /// variable is the item of the loop it is defined in, nothing has been defined
+    LoopItem
+}
+
+/**
+ * Nested scopes visible at the current position of a template. The outermost scope is the template scope.
+ */
+pub struct SymbolTable {
+    scopes: Vec<Scope>
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        Self {
+            scopes: vec![Scope::new(ScopeKind::Template)]
+        }
+    }
+
+    pub fn enter(&mut self, kind: ScopeKind) {
+        self.scopes.push(Scope::new(kind));
+    }
+
+    /// Leaves the innermost scope, the template scope is never left
+    pub fn leave(&mut self) -> Option<Scope> {
+        if self.scopes.len() > 1 {
+            self.scopes.pop()
+        } else {
+            None
+        }
+    }
+
+    /// # Defines variable in the innermost scope
+    /// Inner scopes may shadow variables of outer scopes, but loop items cannot be reassigned inside of their loop.
+    pub fn define(&mut self, variable: VariableReference, kind: SymbolKind) -> Definition {
+        let shadowed = self.scopes.iter().rev().skip(1)
+            .find(|scope| scope.contains(&variable.id))
+            .map(|scope| scope.kind);
+
+        let scope = self.scopes.last_mut().unwrap();
+        match scope.get(&variable.id) {
+            Some(symbol) if symbol.kind == SymbolKind::LoopItem => Definition::LoopItem,
+            Some(_) => Definition::Redefined,
+            None => {
+                scope.insert(Symbol { variable, kind });
+                match shadowed {
+                    Some(kind) => Definition::Shadowed(kind),
+                    None => Definition::New
+                }
+            }
+        }
+    }
+
+    /// Finds the innermost visible variable of that name
+    pub fn lookup(&self, id: &str) -> Option<&Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(id))
+    }
+
+    /// Error for a variable which is not visible, suggesting similarly named variables in scope
+    pub fn undefined_variable(&self, id: &str, template: &str) -> CompilationError {
+        undefined_variable(id, template, self.scopes.iter().flat_map(|scope| scope.iter()).map(|symbol| symbol.variable.id.as_str()))
+    }
+
+    /// Consumes the table and returns its template scope
+    pub fn into_template_scope(mut self) -> Scope {
+        self.scopes.swap_remove(0)
+    }
+}
+
+fn undefined_variable<'n>(id: &str, template: &str, names: impl Iterator<Item = &'n str>) -> CompilationError {
+    let mut similar: Vec<&str> = names.filter(|name| is_similar(id, name)).collect();
+    similar.sort_unstable();
+    similar.dedup();
+
+    CompilationError {
+        name: "ERR_UNDEFINED_VARIABLE".to_string(),
+        desc: format!("cannot use variable '{}'", id),
+        cause: format!("variable '{}' has not been defined in template '{}'", id, template),
+        suggestion: if similar.is_empty() {
+            format!("define variable '{}' before its use", id)
+        } else {
+            format!("did you mean {}? Otherwise define variable '{}' before its use", similar.iter().map(|name| format!("'{}'", name)).collect::<Vec<String>>().join(", "), id)
+        },
+        view: None,
+        position: None
+    }
+}
+
+/// Names are similar if they only differ in case or by a few edits (about one per three characters)
+pub fn is_similar(a: &str, b: &str) -> bool {
+    if a.eq_ignore_ascii_case(b) {
+        return true;
+    }
+    let allowed = (a.chars().count().max(b.chars().count()) / 3).max(1);
+    edit_distance(&a.to_lowercase(), &b.to_lowercase()) <= allowed
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::model::VariableReference;
+    use super::{Definition, ScopeKind, SymbolKind, SymbolTable, edit_distance};
+
+    fn variable(id: &str) -> VariableReference {
+        VariableReference::new(id)
+    }
+
+    #[test]
+    fn nested_scopes() {
+        let mut symbols = SymbolTable::new();
+        assert_eq!(symbols.define(variable("user"), SymbolKind::Param), Definition::New);
+
+        symbols.enter(ScopeKind::Loop);
+        assert_eq!(symbols.define(variable("item"), SymbolKind::LoopItem), Definition::New);
+        assert_eq!(symbols.define(variable("item"), SymbolKind::Variable), Definition::LoopItem);
+        assert_eq!(symbols.define(variable("user"), SymbolKind::Variable), Definition::Shadowed(ScopeKind::Template));
+        assert_eq!(symbols.lookup("user").unwrap().kind, SymbolKind::Variable);
+
+        symbols.enter(ScopeKind::Block);
+        assert_eq!(symbols.define(variable("item"), SymbolKind::Variable), Definition::Shadowed(ScopeKind::Loop));
+        assert_eq!(symbols.define(variable("item"), SymbolKind::Variable), Definition::Redefined);
+        assert_eq!(symbols.leave().unwrap().iter().count(), 1);
+
+        assert_eq!(symbols.leave().unwrap().kind, ScopeKind::Loop);
+        assert!(symbols.leave().is_none());
+        assert!(symbols.lookup("item").is_none());
+        assert_eq!(symbols.lookup("user").unwrap().kind, SymbolKind::Param);
+        assert_eq!(symbols.into_template_scope().iter().count(), 1);
+    }
+
+    #[test]
+    fn suggest_similar_variables() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+
+        let mut symbols = SymbolTable::new();
+        symbols.define(variable("user"), SymbolKind::Variable);
+        symbols.define(variable("orders"), SymbolKind::Variable);
+        symbols.enter(ScopeKind::Loop);
+        symbols.define(variable("Users"), SymbolKind::LoopItem);
+
+        let err = symbols.undefined_variable("usr", "index.ws");
+        assert_eq!(err.name, "ERR_UNDEFINED_VARIABLE");
+        assert!(err.suggestion.starts_with("did you mean 'user'?"));
+        assert!(symbols.undefined_variable("users", "index.ws").suggestion.starts_with("did you mean 'Users', 'user'?"));
+        assert!(symbols.undefined_variable("price", "index.ws").suggestion.starts_with("define variable 'price'"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use crate::{compiler::model::{CoreModel, Data, FetchOperation, HttpMethod}, json::Json, schema::Schema};
+
+/**
+ * Urls of all `GET` requests of the model in order of appearance, without duplicates.
+ * Requests whose url is built at runtime (e.g. from a variable) are left out, they cannot be matched with fixtures.
+ */
+pub fn get_sources(model: &CoreModel) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for fetch in model.templates.iter().flat_map(|template| template.requests()) {
+        if let FetchOperation { method: HttpMethod::GET, from: Data::Value(url), .. } = fetch {
+            if !urls.contains(url) {
+                urls.push(url.clone());
+            }
+        }
+    }
+    urls
+}
+
+/**
+ * Path of the fixtures and the schema of a url, without scheme, query and fragment,
+ * e.g. `/api/users?page=1` becomes `api/users` and `https://example.com/users` becomes `example.com/users`
+ */
+pub fn source_path(url: &str) -> String {
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.split(['?', '#']).next().unwrap_or_default();
+    let segments: Vec<&str> = url.split('/').filter(|segment| !segment.is_empty() && *segment != "." && *segment != "..").collect();
+    match segments.is_empty() {
+        true => "index".to_string(),
+        false => segments.join("/")
+    }
+}
+
+/**
+ * Reads the samples of a source, i.e. `<fixtures>/<path>.json` and all `*.json` files in `<fixtures>/<path>/`, ordered by name
+ */
+pub fn read_samples(fixtures: &Path, path: &str) -> io::Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    let single = fixtures.join(format!("{}.json", path));
+    if single.is_file() {
+        files.push(single);
+    }
+    let directory = fixtures.join(path);
+    if directory.is_dir() {
+        let mut samples: Vec<PathBuf> = fs::read_dir(&directory)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<_>>()?;
+        samples.retain(|sample| sample.is_file() && sample.extension().is_some_and(|extension| extension == "json"));
+        samples.sort();
+        files.extend(samples);
+    }
+    files.into_iter().map(|file| fs::read_to_string(&file).map(|content| (file, content))).collect()
+}
+
+/**
+ * Infers the schema of all samples of a source, `None` if there are no samples.
+ * Fields missing in some of the samples are optional, fields of different types in different samples allow each type.
+ */
+pub fn infer_schema(samples: &[(PathBuf, String)]) -> Result<Option<Schema>, String> {
+    let mut schema: Option<Schema> = None;
+    for (file, content) in samples {
+        let sample = Json::parse(content).map_err(|cause| format!("sample '{}' is invalid: {}", file.display(), cause))?;
+        let inferred = Schema::from_sample(&sample);
+        schema = Some(match schema {
+            Some(schema) => schema.merge(inferred),
+            None => inferred
+        });
+    }
+    Ok(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::LinkedList, fs};
+
+    use crate::compiler::build_model;
+    use super::{get_sources, infer_schema, read_samples, source_path};
+
+    #[test]
+    fn find_sources() {
+        let mut sources = LinkedList::new();
+        sources.push_back(("index.ws".to_string(), "{GET user FROM '/api/user'}{SET url = '/api/users/' + user.id}{GET details FROM url}\
+            {IF user}{GET news FROM 'https://example.com/news?page=1'}{END}<button {ON click GET user FROM '/api/user'}>x</button>\
+            {POST user TO '/api/user'}<a {ON click GET more FROM '/api/more'}>y</a>"));
+        let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+
+        assert_eq!(get_sources(&model), vec!["/api/user", "https://example.com/news?page=1", "/api/more"]);
+        assert_eq!(source_path("/api/user"), "api/user");
+        assert_eq!(source_path("https://example.com/news?page=1"), "example.com/news");
+        assert_eq!(source_path("/../api//x#top"), "api/x");
+        assert_eq!(source_path("/"), "index");
+    }
+
+    #[test]
+    fn infer_from_fixtures() {
+        let fixtures = std::env::temp_dir().join(format!("websorcery-fixtures-{}", std::process::id()));
+        fs::create_dir_all(fixtures.join("api/users")).unwrap();
+        fs::write(fixtures.join("api/users.json"), r#"[{ "id": 1, "name": "Ada" }]"#).unwrap();
+        fs::write(fixtures.join("api/users/b.json"), r#"[{ "id": 2, "name": null, "admin": true }]"#).unwrap();
+        fs::write(fixtures.join("api/users/notes.txt"), "not a sample").unwrap();
+        fs::write(fixtures.join("api/broken.json"), "{").unwrap();
+
+        let samples = read_samples(&fixtures, "api/users").unwrap();
+        assert_eq!(samples.len(), 2);
+        let schema = infer_schema(&samples).unwrap().unwrap();
+        assert_eq!(schema.to_json().serialize(), "{\"type\":\"array\",\"items\":{\"type\":\"object\",\"properties\":{\
+            \"id\":{\"type\":\"number\"},\"name\":{\"type\":[\"string\",\"null\"]},\"admin\":{\"type\":\"boolean\"}},\
+            \"required\":[\"id\",\"name\"],\"additionalProperties\":false}}");
+
+        assert_eq!(infer_schema(&read_samples(&fixtures, "api/none").unwrap()), Ok(None));
+        assert!(infer_schema(&read_samples(&fixtures, "api/broken").unwrap()).err().unwrap().contains("broken.json' is invalid: "));
+        fs::remove_dir_all(&fixtures).unwrap();
+    }
+}
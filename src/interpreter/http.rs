@@ -0,0 +1,226 @@
+use std::{io::{Read, Write}, net::TcpStream, time::Duration};
+
+use super::{DataSource, Request, Response, Stream, stream::StreamClient};
+
+/**
+ * Sends requests over HTTP/1.1, e.g. to a development server.
+ * Requests block until the response has arrived, failures are answered with status 0.
+ * Streams are read in the background, their events are taken from `streams()`.
+ * Only plain `http://` and `ws://` urls are supported.
+ */
+pub struct HttpSource {
+    timeout: Duration,
+    streams: StreamClient
+}
+
+impl HttpSource {
+    pub fn new() -> HttpSource {
+        Self {
+            timeout: Duration::from_secs(10),
+            streams: StreamClient::new()
+        }
+    }
+
+    pub fn streams(&self) -> &StreamClient {
+        &self.streams
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn send(&self, request: &Request) -> Result<Response, String> {
+        let (host, path) = split_url(&request.url, "http://")?;
+        let mut stream = TcpStream::connect(host).map_err(|err| err.to_string())?;
+        stream.set_read_timeout(Some(self.timeout)).map_err(|err| err.to_string())?;
+
+        let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n", request.method.name(), path, host);
+        for (name, value) in &request.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if let Some(body) = &request.body {
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        head.push_str("\r\n");
+        head.push_str(request.body.as_deref().unwrap_or_default());
+        stream.write_all(head.as_bytes()).map_err(|err| err.to_string())?;
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).map_err(|err| err.to_string())?;
+        parse_response(&raw)
+    }
+}
+
+impl DataSource for HttpSource {
+    fn fetch(&mut self, request: &Request) -> Option<Response> {
+        Some(self.send(request).unwrap_or_else(|_| Response::new(0, "")))
+    }
+
+    fn subscribe(&mut self, stream: &Stream) {
+        self.streams.connect(stream);
+    }
+}
+
+/// Splits `http://host:port/path?query` into `host:port` and `/path?query`
+pub(super) fn split_url<'u>(url: &'u str, scheme: &str) -> Result<(&'u str, &'u str), String> {
+    let rest = url.strip_prefix(scheme).ok_or(format!("unsupported url '{}'", url))?;
+    match rest.find('/') {
+        Some(index) => Ok((&rest[..index], &rest[index..])),
+        None => Ok((rest, "/"))
+    }
+}
+
+/// Parses status line, headers and body, which may be chunked
+fn parse_response(raw: &[u8]) -> Result<Response, String> {
+    let end = raw.windows(4).position(|window| window == b"\r\n\r\n").ok_or("incomplete response head")?;
+    let head = std::str::from_utf8(&raw[..end]).map_err(|_| "invalid response head")?;
+    let mut lines = head.split("\r\n");
+
+    let status = lines.next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or("invalid status line")?;
+    let mut response = Response::new(status, "");
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            response = response.with_header(name.trim(), value.trim());
+        }
+    }
+
+    let body = &raw[end + 4..];
+    let body = match response.header("Transfer-Encoding") {
+        Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => decode_chunked(body)?,
+        _ => body.to_vec()
+    };
+    response.body = String::from_utf8(body).map_err(|_| "invalid UTF-8 in response body")?;
+    Ok(response)
+}
+
+fn decode_chunked(mut raw: &[u8]) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    loop {
+        let line = raw.windows(2).position(|window| window == b"\r\n").ok_or("incomplete chunk")?;
+        let size = std::str::from_utf8(&raw[..line]).ok()
+            .and_then(|size| usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16).ok())
+            .ok_or("invalid chunk size")?;
+        if size == 0 {
+            return Ok(body);
+        }
+        let chunk = raw.get(line + 2..line + 2 + size).ok_or("incomplete chunk")?;
+        body.extend_from_slice(chunk);
+        raw = raw.get(line + 4 + size..).ok_or("incomplete chunk")?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::LinkedList, io::{Read, Write}, net::TcpListener, sync::{Arc, Mutex}, thread, time::Duration};
+
+    use crate::{compiler::{build_model, model::HttpMethod}, interpreter::{DataSource, Interpreter, Request, tests::strip_anchors}};
+    use super::{HttpSource, parse_response};
+
+    /**
+     * Local stand-in for an HTTP server, answering every request with `respond(head)`
+     */
+    struct MockServer {
+        url: String,
+        /// heads of all received requests
+        requests: Arc<Mutex<Vec<String>>>
+    }
+
+    impl MockServer {
+        fn start(respond: fn(&str) -> String) -> MockServer {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let received = requests.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut head = Vec::new();
+                    let mut byte = [0; 1];
+                    while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+                        head.push(byte[0]);
+                    }
+                    let head = String::from_utf8(head).unwrap();
+                    let response = respond(&head);
+                    received.lock().unwrap().push(head);
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+            });
+            MockServer { url, requests }
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    /// Serves `{"n": 1}` with an ETag, unchanged data is answered with `304 Not Modified`
+    fn versioned(head: &str) -> String {
+        if head.contains("If-None-Match: \"v1\"") {
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\n\r\n".to_string()
+        } else {
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 8\r\n\r\n{\"n\": 1}".to_string()
+        }
+    }
+
+    #[test]
+    fn send_requests() {
+        let server = MockServer::start(|head| match head.starts_with("GET /chunked ") {
+            true => "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n[1, \r\n2\r\n2]\r\n0\r\n\r\n".to_string(),
+            false => "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n\r\n{\"message\": \"missing\"}".to_string()
+        });
+        let mut source = HttpSource::new();
+        let request = |path: &str| Request { method: HttpMethod::GET, url: format!("{}{}", server.url, path), headers: vec![("Accept".to_string(), "application/json".to_string())], body: None, credentials: false };
+
+        let response = source.fetch(&request("/chunked")).unwrap();
+        assert_eq!((response.status, response.body.as_str()), (200, "[1, 2]"));
+        let response = source.fetch(&request("/users?id=1")).unwrap();
+        assert_eq!((response.status, response.header("content-type")), (404, Some("application/json")));
+        assert!(server.requests()[1].starts_with("GET /users?id=1 HTTP/1.1\r\n"));
+        assert!(server.requests()[1].contains("Accept: application/json\r\n"));
+
+        source.set_timeout(Duration::from_millis(100));
+        assert_eq!(source.fetch(&Request { url: "http://127.0.0.1:1/".to_string(), ..request("") }).unwrap().status, 0);
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+
+    #[test]
+    fn cache_responses() {
+        let server = MockServer::start(versioned);
+        let mut sources = LinkedList::new();
+        sources.push_back(("index.ws".to_string(), "{GET stats FROM '/stats' CACHE 60s}{stats.n}"));
+        sources.push_back(("live.ws".to_string(), "{GET stats FROM '/stats'}{stats.n}"));
+        let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+
+        let mut source = HttpSource::new();
+        let mut interpreter = Interpreter::new(&model);
+        interpreter.set_base_url(&server.url);
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "1");
+
+        // fresh responses are reused by later pages
+        interpreter.advance(Duration::from_secs(30));
+        interpreter.reload();
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "1");
+        assert_eq!(server.requests().len(), 1);
+
+        // stale responses are revalidated, 304 renews them
+        interpreter.advance(Duration::from_secs(31));
+        interpreter.reload();
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "1");
+        assert_eq!(server.requests().len(), 2);
+        assert!(server.requests()[1].contains("If-None-Match: \"v1\"\r\n"));
+
+        interpreter.advance(Duration::from_secs(59));
+        interpreter.reload();
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "1");
+        assert_eq!(server.requests().len(), 2);
+
+        // requests without CACHE are sent by every page, but revalidated as well
+        interpreter.reload();
+        assert_eq!(strip_anchors(interpreter.render("live.ws", &mut source).unwrap()), "1");
+        assert_eq!(server.requests().len(), 3);
+        assert!(server.requests()[2].contains("If-None-Match: \"v1\"\r\n"));
+    }
+}
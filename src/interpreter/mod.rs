@@ -0,0 +1,1396 @@
+pub mod http;
+pub mod stream;
+
+use std::{collections::{HashMap, LinkedList}, time::Duration};
+
+use crate::{compiler::model::{AttributePart, Binding, BindingKind, BindOperation, Comparison, CoreModel, Data, EventAction, EventOperation, Expression, FetchOperation, Filter, Operand, HttpMethod, PathKey, RequestBody, Rule, SlotContent, StreamMode, SubscribeOperation, Template, TemplateOperation, Transport, ValidateOperation, ValidationRule, VariableReference}, json::{Json, Key}, pattern::Pattern};
+
+/**
+ * Request as it is sent by the client
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Request {
+    pub method: HttpMethod,
+    /// complete url, including base url and encoded query
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    pub credentials: bool
+}
+
+/**
+ * Response of a request, status 0 stands for a network failure
+ */
+#[derive(Clone, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String
+}
+
+impl Response {
+    pub fn new(status: u16, body: &str) -> Response {
+        Response { status, headers: Vec::new(), body: body.to_string() }
+    }
+
+    pub fn ok(body: &str) -> Response {
+        Response::new(200, body)
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Response {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Value of a header, names are case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+}
+
+/**
+ * Request sent by the current page
+ */
+struct PageRequest {
+    /// latest response, `None` until the first one has arrived
+    response: Option<Response>,
+    /// the request has been sent (again) and its response did not arrive yet
+    pending: bool,
+    /// time the request was sent last
+    sent: Duration,
+    /// variable the response is bound to, used to refresh the request
+    target: Option<String>,
+    /// the request is sent again when it is rendered next
+    stale: bool
+}
+
+/**
+ * Messages of an open stream
+ */
+struct StreamState {
+    /// latest message, null until the first one has arrived
+    latest: Json,
+    /// all messages, only collected if the stream is subscribed using `APPEND`
+    messages: Vec<Json>,
+    appending: bool
+}
+
+/**
+ * Successful response of a `GET` request, kept across pages
+ */
+struct CacheEntry {
+    response: Response,
+    /// time the response was received or revalidated
+    stored: Duration
+}
+
+/**
+ * Stream of JSON messages a variable is subscribed to
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Stream {
+    pub transport: Transport,
+    /// complete url, including base url
+    pub url: String
+}
+
+/**
+ * Answers the requests of the interpreter, e.g. a server or a mock in tests
+ */
+pub trait DataSource {
+    /// # Sends request
+    /// Returns the response if it is available at once, otherwise `None`:
+    /// the request is pending until its response is passed to `Interpreter::receive`.
+    fn fetch(&mut self, request: &Request) -> Option<Response>;
+
+    /// # Opens stream
+    /// Its messages are passed to `Interpreter::message` as they arrive.
+    fn subscribe(&mut self, _stream: &Stream) {}
+}
+
+/**
+ * Change of a bound node since the template was rendered last, see `Interpreter::update`
+ */
+#[derive(Debug, PartialEq)]
+pub enum Patch {
+    /// text node shows new text
+    Text { binding: usize, text: String },
+    /// attribute has a new value
+    Attribute { binding: usize, name: String, value: String },
+    /// region is replaced by new HTML
+    Region { binding: usize, html: String }
+}
+
+/**
+ * Handler of a listener to be called by the host, see `Interpreter::dispatch`
+ */
+#[derive(Debug, PartialEq)]
+pub struct HandlerCall {
+    pub handler: String,
+    /// null if the handler is called without argument
+    pub argument: Json
+}
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub name: String,
+    pub cause: String
+}
+
+/**
+ * Variables and slot contents visible while rendering a template
+ */
+#[derive(Clone)]
+struct Frame<'m> {
+    scopes: Vec<HashMap<String, Json>>,
+    /// slot contents passed by the caller and the caller's frame they are rendered in
+    slots: Option<(&'m LinkedList<SlotContent>, Box<Frame<'m>>)>,
+    /// requests of the template that have responded, by index, and whether they succeeded
+    responded: HashMap<usize, bool>,
+    /// bindings of the rendered page, empty in frames of called templates
+    bindings: &'m [Binding],
+    /// id of the rendered page, names the anchors of its bindings
+    page: &'m str,
+    /// index of the route of the template matching the current path
+    route: Option<usize>
+}
+
+impl<'m> Frame<'m> {
+    fn new(variables: HashMap<String, Json>) -> Frame<'m> {
+        Self {
+            scopes: vec![variables],
+            slots: None,
+            responded: HashMap::new(),
+            bindings: &[],
+            page: "",
+            route: None
+        }
+    }
+
+    /// Value at path, e.g. `user.address.city`. Undefined values are null.
+    fn lookup(&self, variable: &VariableReference) -> Json {
+        let value = self.scopes.iter().rev().find_map(|scope| scope.get(&variable.id));
+        value.and_then(|value| value.get_path(&path_keys(variable)))
+            .cloned().unwrap_or(Json::Null)
+    }
+
+    /// Defines variable in the innermost scope
+    fn define(&mut self, id: &str, value: Json) {
+        self.scopes.last_mut().unwrap().insert(id.to_string(), value);
+    }
+
+    /// Replaces value at path, e.g. `user.email`, in the innermost scope defining its root
+    fn assign(&mut self, variable: &VariableReference, value: Json) {
+        if let Some(current) = self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(&variable.id)) {
+            current.set_path(&path_keys(variable), value);
+        }
+    }
+}
+
+/**
+ * Keys of the path into the value of the variable, properties are members of objects and indices items of arrays
+ */
+fn path_keys(variable: &VariableReference) -> Vec<Key<'_>> {
+    variable.path.iter().map(|segment| match &segment.key {
+        PathKey::Property(name) => Key::Member(name),
+        PathKey::Index(index) => Key::Item(*index)
+    }).collect()
+}
+
+/**
+ * Renders templates of a core model, fetching data from a data source.
+ * It is the reference for the behaviour of generated clients.
+ */
+pub struct Interpreter<'m> {
+    model: &'m CoreModel,
+    /// prepended to urls starting with '/'
+    base_url: String,
+    /// fields of forms by id
+    forms: HashMap<String, Vec<(String, String)>>,
+    /// requests sent by the page and their responses
+    responses: HashMap<Request, PageRequest>,
+    cache: HashMap<Request, CacheEntry>,
+    /// streams subscribed to, they stay open across pages
+    streams: HashMap<Stream, StreamState>,
+    /// time since the interpreter was created, advanced by the host
+    now: Duration,
+    /// values of the bound nodes as rendered last, by binding
+    bound: HashMap<usize, String>,
+    /// listeners as rendered last by key, along with the variables visible to them
+    listeners: HashMap<String, (&'m EventOperation, Frame<'m>)>,
+    /// requests sent by listeners, by index of the request
+    triggered: HashMap<usize, Request>,
+    /// bound form controls as rendered last by key, along with the value they show
+    inputs: HashMap<String, (&'m BindOperation, Json)>,
+    /// values written by form controls by path, in order of writing
+    written: Vec<(VariableReference, Json)>,
+    /// validations as rendered last, along with the variables visible to them
+    validations: Vec<(&'m ValidateOperation, Frame<'m>)>,
+    /// a request has been blocked by violated rules, so all error messages are shown
+    submitted: bool,
+    /// current path, which selects the rendered routes
+    path: String,
+    /// previous paths, most recent last
+    history: Vec<String>
+}
+
+impl<'m> Interpreter<'m> {
+    pub fn new(model: &'m CoreModel) -> Interpreter<'m> {
+        Self {
+            model,
+            base_url: String::new(),
+            forms: HashMap::new(),
+            responses: HashMap::new(),
+            cache: HashMap::new(),
+            streams: HashMap::new(),
+            now: Duration::ZERO,
+            bound: HashMap::new(),
+            listeners: HashMap::new(),
+            triggered: HashMap::new(),
+            inputs: HashMap::new(),
+            written: Vec::new(),
+            validations: Vec::new(),
+            submitted: false,
+            path: "/".to_string(),
+            history: Vec::new()
+        }
+    }
+
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.base_url = base_url.to_string();
+    }
+
+    pub fn set_form(&mut self, id: &str, fields: Vec<(String, String)>) {
+        self.forms.insert(id.to_string(), fields);
+    }
+
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.now += elapsed;
+    }
+
+    /// # Starts a new page
+    /// Responses and inputs of the previous page are dropped, cached responses are kept.
+    pub fn reload(&mut self) {
+        self.responses.clear();
+        self.triggered.clear();
+        self.written.clear();
+        self.submitted = false;
+    }
+
+    /// # Navigates to a path, like `history.pushState`
+    /// The page stays the same, the next rendering shows the routes matching the path.
+    pub fn navigate(&mut self, path: &str) {
+        let previous = std::mem::replace(&mut self.path, path.to_string());
+        self.history.push(previous);
+    }
+
+    /// Goes back to the previous path, returns false if there is none
+    pub fn back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(path) => {
+                self.path = path;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// # Whether a click on a link navigates within the page
+    /// Links to paths of the own origin are intercepted if a route of the model matches them, fallbacks do not count.
+    /// Other links, e.g. to other origins or pages that are not routed, are followed by the browser.
+    pub fn intercepts(&self, href: &str) -> bool {
+        href.starts_with('/') && !href.starts_with("//") && self.model.templates.iter()
+            .flat_map(|template| template.routes())
+            .any(|route| route.matches(href).is_some())
+    }
+
+    /// Response of a pending request has arrived, the next rendering shows it
+    pub fn receive(&mut self, request: &Request, response: Response) {
+        let request = without_revalidation(request);
+        let response = self.store(&request, response);
+        let sent = self.now;
+        let page = self.responses.entry(request).or_insert(PageRequest { response: None, pending: true, sent, target: None, stale: false });
+        page.response = Some(response);
+        page.pending = false;
+    }
+
+    /// # Message of a stream has arrived
+    /// The next rendering shows it, messages of unknown streams are ignored.
+    pub fn message(&mut self, stream: &Stream, text: &str) -> Result<(), RuntimeError> {
+        let message = Json::parse(text).map_err(|cause| RuntimeError {
+            name: "ERR_INVALID_MESSAGE".to_string(),
+            cause: format!("message of '{}' is not valid JSON: {}", stream.url, cause)
+        })?;
+        if let Some(state) = self.streams.get_mut(stream) {
+            if state.appending {
+                state.messages.push(message.clone());
+            }
+            state.latest = message;
+        }
+        Ok(())
+    }
+
+    /// # Refreshes the data of a variable
+    /// Requests bound to the variable are sent again when the page is updated next, bypassing the cache.
+    /// Until their new responses arrive, the previous ones are shown.\
+    /// The page is not replaced: `update` computes it again, but only returns patches of the nodes showing the new data.
+    pub fn refresh(&mut self, target: &str) {
+        for page in self.responses.values_mut() {
+            if page.target.as_deref() == Some(target) {
+                page.stale = true;
+            }
+        }
+    }
+
+    /// # Renders template into HTML
+    /// Requests are sent to `source` once, when they are rendered for the first time and the requests they
+    /// depend on have responded. Independent requests are thereby pending at the same time.
+    /// Pending requests show their `{LOADING}` branch until their response is received,
+    /// the page is rendered again whenever a response arrives.
+    pub fn render(&mut self, template: &str, source: &mut dyn DataSource) -> Result<String, RuntimeError> {
+        let template = self.get_template(template)?;
+        let mut output = String::new();
+        let mut frame = Frame::new(HashMap::new());
+        frame.bindings = &template.bindings;
+        frame.page = &template.id;
+        frame.route = template.active_route(&self.path).map(|route| route.index);
+        self.bound.clear();
+        self.listeners.clear();
+        self.inputs.clear();
+        self.validations.clear();
+        self.render_operations(&template.operations, &mut frame, source, &mut output)?;
+        Ok(output)
+    }
+
+    /// # Renders template again and returns the bound nodes that changed
+    /// The template must be the one rendered last. Nodes within a replaced region are not patched themselves,
+    /// nodes that are not shown anymore neither.
+    pub fn update(&mut self, template: &str, source: &mut dyn DataSource) -> Result<Vec<Patch>, RuntimeError> {
+        let previous = std::mem::take(&mut self.bound);
+        self.render(template, source)?;
+
+        let mut patched = vec![false; self.get_template(template)?.bindings.len()];
+        let mut patches = Vec::new();
+        for (index, binding) in self.get_template(template)?.bindings.iter().enumerate() {
+            if binding.parent.is_some_and(|parent| patched[parent]) {
+                patched[index] = true;
+                continue;
+            }
+            let value = match self.bound.get(&index) {
+                Some(value) if previous.get(&index) != Some(value) => value.clone(),
+                _ => continue
+            };
+            patched[index] = true;
+            patches.push(match &binding.kind {
+                BindingKind::Text => Patch::Text { binding: index, text: value },
+                BindingKind::Attribute { name, .. } => Patch::Attribute { binding: index, name: name.clone(), value },
+                BindingKind::Region => Patch::Region { binding: index, html: value }
+            });
+        }
+        Ok(patches)
+    }
+
+    /// # Handles event of a listener
+    /// * `listener` - key of the listener, as rendered into the `data-ws-on-<event>` attribute
+    /// * `form` - fields of the form the element belongs to
+    ///
+    /// Requests are sent at once, their responses are shown when the page is rendered next.
+    /// Requests sending data (`POST`, `PUT` and `PATCH`) are not sent while a validation rule of the page
+    /// is violated, instead the error messages of all validations are shown from then on.
+    /// `refresh` and `navigate` are handled by the interpreter, other handlers are returned to be called by the host.
+    pub fn dispatch(&mut self, listener: &str, form: Vec<(String, String)>, source: &mut dyn DataSource) -> Result<Option<HandlerCall>, RuntimeError> {
+        let (operation, mut frame) = self.listeners.get(listener).cloned().ok_or_else(|| RuntimeError {
+            name: "ERR_UNKNOWN_LISTENER".to_string(),
+            cause: format!("listener '{}' has not been rendered", listener)
+        })?;
+        let form = Json::Object(form.into_iter().map(|(name, value)| (name, Json::String(value))).collect());
+        for (path, value) in &self.written {
+            frame.assign(path, value.clone());
+        }
+        frame.scopes.push(HashMap::from([("form".to_string(), form)]));
+
+        match &operation.action {
+            EventAction::Call { handler, argument } if handler == "refresh" => {
+                match argument {
+                    Some(Data::Variable(variable, _)) => self.refresh(&variable.id),
+                    Some(argument) => self.refresh(&Interpreter::value(argument, &frame).to_text()),
+                    None => self.responses.values_mut().for_each(|page| page.stale = true)
+                }
+                Ok(None)
+            },
+            EventAction::Call { handler, argument: Some(argument) } if handler == "navigate" => {
+                self.navigate(&Interpreter::value(argument, &frame).to_text());
+                Ok(None)
+            },
+            EventAction::Call { handler, argument } => Ok(Some(HandlerCall {
+                handler: handler.clone(),
+                argument: argument.as_ref().map_or(Json::Null, |argument| Interpreter::value(argument, &frame))
+            })),
+            EventAction::Request(fetch) if fetch.method.sends_body() && !self.is_valid() => {
+                self.submitted = true;
+                Ok(None)
+            },
+            EventAction::Request(fetch) => {
+                let request = self.build_request(fetch, &frame);
+                let previous = self.responses.get(&request).and_then(|page| page.response.clone());
+                let response = source.fetch(&request).map(|response| self.store(&request, response));
+                let page = PageRequest {
+                    pending: response.is_none(),
+                    response: response.or(previous),
+                    sent: self.now,
+                    target: None,
+                    stale: false
+                };
+                self.responses.insert(request.clone(), page);
+                self.triggered.insert(fetch.index, request);
+                Ok(None)
+            }
+        }
+    }
+
+    /// # Value of a bound form control has changed
+    /// * `input` - key of the control, as rendered into the `data-ws-bind` attribute
+    ///
+    /// The value replaces the bound one until the page is reloaded, even if the request it came from is refreshed.
+    /// Text is converted to a number or boolean if the bound value is one.
+    pub fn input(&mut self, input: &str, text: &str) -> Result<(), RuntimeError> {
+        let (bind, current) = self.inputs.get(input).ok_or_else(|| RuntimeError {
+            name: "ERR_UNKNOWN_INPUT".to_string(),
+            cause: format!("input '{}' has not been rendered", input)
+        })?;
+        let value = match (current, text) {
+            (Json::Number(_), text) if text.trim().parse::<f64>().is_ok() => Json::Number(text.trim().parse().unwrap()),
+            (Json::Bool(_), "true"|"false") => Json::Bool(text == "true"),
+            _ => Json::String(text.to_string())
+        };
+
+        let target = bind.target.clone();
+        self.written.retain(|(written, _)| !(written.contains(&target) && target.contains(written)));
+        self.written.push((target, value));
+        Ok(())
+    }
+
+    /// Whether the values of all rendered validations meet their rules, including values written since
+    fn is_valid(&self) -> bool {
+        self.validations.iter().all(|(validate, frame)| {
+            let mut frame = frame.clone();
+            for (path, value) in &self.written {
+                frame.assign(path, value.clone());
+            }
+            violated_rule(&validate.rules, &frame.lookup(&validate.target)).is_none()
+        })
+    }
+
+    fn get_template(&self, id: &str) -> Result<&'m Template, RuntimeError> {
+        self.model.get_template_by_id(id).ok_or_else(|| RuntimeError {
+            name: "ERR_MISSING_TEMPLATE".to_string(),
+            cause: format!("template '{}' does not exist", id)
+        })
+    }
+
+    fn render_operations(&mut self, operations: &'m LinkedList<TemplateOperation>, frame: &mut Frame<'m>, source: &mut dyn DataSource, output: &mut String) -> Result<(), RuntimeError> {
+        for operation in operations {
+            self.render_operation(operation, frame, source, output)?;
+        }
+        Ok(())
+    }
+
+    /// Renders operations in a scope of their own
+    fn render_scoped(&mut self, operations: &'m LinkedList<TemplateOperation>, variables: HashMap<String, Json>, frame: &mut Frame<'m>, source: &mut dyn DataSource, output: &mut String) -> Result<(), RuntimeError> {
+        frame.scopes.push(variables);
+        let result = self.render_operations(operations, frame, source, output);
+        frame.scopes.pop();
+        result
+    }
+
+    fn render_operation(&mut self, operation: &'m TemplateOperation, frame: &mut Frame<'m>, source: &mut dyn DataSource, output: &mut String) -> Result<(), RuntimeError> {
+        let region = match operation {
+            TemplateOperation::If(conditional) => conditional.binding,
+            TemplateOperation::Loop(repetition) => repetition.binding,
+            TemplateOperation::Call(call) => call.binding,
+            TemplateOperation::Fetch(fetch) => fetch.binding,
+            TemplateOperation::Route(route) => route.binding,
+            _ => None
+        }.filter(|index| *index < frame.bindings.len());
+        if let Some(index) = region {
+            output.push_str(&format!("<!--ws:{}-->", Binding::anchor(frame.page, index)));
+        }
+        let start = output.len();
+        match operation {
+            TemplateOperation::Print(print) => output.push_str(&Interpreter::value(&print.content, frame).to_text()),
+            TemplateOperation::Insert(insert) => {
+                let text = Interpreter::value(&insert.content, frame).to_text();
+                match insert.binding.and_then(|index| frame.bindings.get(index)) {
+                    Some(Binding { kind: BindingKind::Text, .. }) => {
+                        let anchor = Binding::anchor(frame.page, insert.binding.unwrap());
+                        output.push_str(&format!("<!--ws:{}-->{}<!--/ws:{}-->", anchor, escape_html(&text), anchor));
+                    },
+                    _ => output.push_str(&escape_html(&text))
+                }
+                match insert.binding.and_then(|index| frame.bindings.get(index)) {
+                    Some(Binding { kind: BindingKind::Attribute { parts, .. }, .. }) => {
+                        let value = parts.iter().map(|part| match part {
+                            AttributePart::Text(text) => text.clone(),
+                            AttributePart::Value(data) => Interpreter::value(data, frame).to_text()
+                        }).collect();
+                        self.bound.insert(insert.binding.unwrap(), value);
+                    },
+                    Some(_) => {
+                        self.bound.insert(insert.binding.unwrap(), text);
+                    },
+                    None => ()
+                }
+            },
+            TemplateOperation::Set(set) => {
+                let value = Interpreter::evaluate(&set.value, frame);
+                self.define(frame, &set.target.id, value);
+            },
+            TemplateOperation::If(conditional) => {
+                let branch = if Interpreter::evaluate(&conditional.condition, frame).is_empty() {
+                    &conditional.alternative
+                } else {
+                    &conditional.operations
+                };
+                self.render_scoped(branch, HashMap::new(), frame, source, output)?;
+            },
+            TemplateOperation::Loop(repetition) => {
+                if let Json::Array(items) = Interpreter::value(&repetition.items, frame) {
+                    for item in items {
+                        let mut variables = HashMap::new();
+                        variables.insert(repetition.item.id.clone(), item);
+                        self.render_scoped(&repetition.operations, variables, frame, source, output)?;
+                    }
+                }
+            },
+            TemplateOperation::Block(block) => self.render_scoped(&block.operations, HashMap::new(), frame, source, output)?,
+            TemplateOperation::Call(call) => {
+                let template = self.get_template(&Interpreter::value(&call.template, frame).to_text())?;
+                let params = call.params.iter()
+                    .map(|param| (param.name.clone(), Interpreter::value(&param.value, frame)))
+                    .collect();
+                let mut callee = Frame::new(params);
+                callee.route = template.active_route(&self.path).map(|route| route.index);
+                callee.slots = Some((&call.slots, Box::new(frame.clone())));
+                self.render_operations(&template.operations, &mut callee, source, output)?;
+            },
+            TemplateOperation::Slot(slot) => {
+                if let Some((slots, caller)) = &frame.slots {
+                    if let Some(content) = slots.iter().find(|content| content.name == slot.name) {
+                        let mut caller = caller.as_ref().clone();
+                        self.render_scoped(&content.operations, HashMap::new(), &mut caller, source, output)?;
+                    }
+                }
+            },
+            TemplateOperation::Fetch(fetch) => self.render_fetch(fetch, frame, source, output)?,
+            TemplateOperation::Subscribe(subscribe) => {
+                let value = self.subscribe(subscribe, frame, source);
+                self.define(frame, &subscribe.target.id, value);
+            },
+            TemplateOperation::On(listener) => self.render_listener(listener, frame, output),
+            TemplateOperation::Bind(bind) => {
+                let value = frame.lookup(&bind.target);
+                let key = repetition_key(bind.index, |key| self.inputs.contains_key(key));
+                output.push_str(&format!("data-ws-bind=\"{}\" value=\"{}\"", key, escape_html(&value.to_text())));
+                self.inputs.insert(key, (bind, value));
+            },
+            TemplateOperation::Validate(validate) => self.render_validation(validate, frame),
+            TemplateOperation::Route(route) if frame.route == Some(route.index) => {
+                let template = self.get_template(&route.template)?;
+                let params = route.matches(&self.path).unwrap_or_default().into_iter()
+                    .map(|(name, value)| (name, Json::String(value)))
+                    .collect();
+                let mut routed = Frame::new(params);
+                routed.route = template.active_route(&self.path).map(|route| route.index);
+                self.render_operations(&template.operations, &mut routed, source, output)?;
+            },
+            TemplateOperation::Route(_) => ()
+        }
+
+        if let Some(index) = region {
+            self.bound.insert(index, output[start..].to_string());
+            output.push_str(&format!("<!--/ws:{}-->", Binding::anchor(frame.page, index)));
+        }
+        Ok(())
+    }
+
+    /// # Renders attribute marking the element of a listener
+    /// The response of its latest request, if any, is bound to the target.
+    fn render_listener(&mut self, listener: &'m EventOperation, frame: &mut Frame<'m>, output: &mut String) {
+        let key = repetition_key(listener.index, |key| self.listeners.contains_key(key));
+        output.push_str(&format!("data-ws-on-{}=\"{}\"", listener.event, key));
+        self.listeners.insert(key, (listener, frame.clone()));
+
+        if let EventAction::Request(fetch) = &listener.action {
+            let response = self.triggered.get(&fetch.index)
+                .and_then(|request| self.responses.get(request))
+                .and_then(|page| page.response.as_ref());
+            if let Some(response) = response {
+                let value = parse_response(response);
+                frame.responded.insert(fetch.index, value.is_ok());
+                if let Some(target) = &fetch.target {
+                    self.define(frame, &target.id, value.unwrap_or(Json::Null));
+                }
+            }
+        }
+    }
+
+    /// # Records validation and defines its error variable
+    /// The error is the message of the first violated rule, once the value has been written by a form control
+    /// or a request has been blocked. Otherwise it is null, so untouched forms do not show errors.
+    fn render_validation(&mut self, validate: &'m ValidateOperation, frame: &mut Frame<'m>) {
+        self.validations.push((validate, frame.clone()));
+        if let Some(error) = &validate.error {
+            let target = &validate.target;
+            let touched = self.submitted || self.written.iter().any(|(written, _)| target.contains(written) || written.contains(target));
+            let message = match violated_rule(&validate.rules, &frame.lookup(target)) {
+                Some(message) if touched => Json::String(message),
+                _ => Json::Null
+            };
+            self.define(frame, &error.id, message);
+        }
+    }
+
+    /// Defines variable, keeping the values written by form controls
+    fn define(&self, frame: &mut Frame<'m>, id: &str, value: Json) {
+        frame.define(id, value);
+        for (path, value) in &self.written {
+            if path.id == id {
+                frame.assign(path, value.clone());
+            }
+        }
+    }
+
+    /// # Sends request and renders the branch matching its state
+    /// * pending, or waiting for a request it depends on: `{LOADING}`
+    /// * a request it depends on failed, or it sends data and a validation rule is violated: nothing
+    /// * failed (status outside of 200-299 or invalid JSON): `{ERROR e}` with `e.status` and `e.message`
+    /// * empty response: `{EMPTY}`, if given
+    /// * otherwise the response is bound to the target and the content is rendered
+    fn render_fetch(&mut self, fetch: &'m FetchOperation, frame: &mut Frame<'m>, source: &mut dyn DataSource, output: &mut String) -> Result<(), RuntimeError> {
+        let dependencies: Vec<Option<&bool>> = fetch.dependencies.iter().map(|index| frame.responded.get(index)).collect();
+        if dependencies.contains(&Some(&false)) {
+            return Ok(());
+        }
+
+        // requests sending data wait until the values validated before them are valid
+        if fetch.method.sends_body() && !self.is_valid() {
+            return Ok(());
+        }
+        let response = if dependencies.contains(&None) {
+            None
+        } else {
+            let request = self.build_request(fetch, frame);
+            self.send(fetch, request, source)
+        };
+        let response = match response {
+            Some(response) => response,
+            None => {
+                if let Some(loading) = &fetch.loading {
+                    self.render_scoped(loading, HashMap::new(), frame, source, output)?;
+                }
+                return Ok(());
+            }
+        };
+
+        let value = parse_response(&response);
+        frame.responded.insert(fetch.index, value.is_ok());
+        match value {
+            Ok(value) => {
+                let empty = value.is_empty();
+                if let Some(target) = &fetch.target {
+                    self.define(frame, &target.id, value);
+                }
+                match &fetch.empty {
+                    Some(operations) if empty => self.render_scoped(operations, HashMap::new(), frame, source, output),
+                    _ => self.render_operations(&fetch.operations, frame, source, output)
+                }
+            },
+            Err(error) => match &fetch.error {
+                Some(state) => {
+                    let mut variables = HashMap::new();
+                    variables.insert(state.variable.id.clone(), error);
+                    self.render_scoped(&state.operations, variables, frame, source, output)
+                },
+                None => Ok(())
+            }
+        }
+    }
+
+    /// # Sends request, unless it is already known
+    /// * requests of the page are sent once, identical requests share the response (or wait for it)
+    /// * refreshed requests are sent again, keeping their previous response until the new one arrives
+    /// * fresh responses of requests with `CACHE` are taken from the cache
+    /// * other cached responses having an `ETag` are revalidated using `If-None-Match`
+    fn send(&mut self, fetch: &FetchOperation, request: Request, source: &mut dyn DataSource) -> Option<Response> {
+        let refreshing = match self.responses.get(&request) {
+            Some(page) if page.pending => return page.response.clone(),
+            Some(page) if page.stale || fetch.refresh.is_some_and(|interval| self.now - page.sent >= interval) => true,
+            Some(page) => return page.response.clone(),
+            None => false
+        };
+
+        let mut sent = request.clone();
+        if let Some(entry) = self.cache.get(&request) {
+            if !refreshing && fetch.cache.is_some_and(|duration| self.now - entry.stored < duration) {
+                let response = Some(entry.response.clone());
+                self.responses.insert(request, PageRequest { response: response.clone(), pending: false, sent: entry.stored, target: fetch.target.as_ref().map(|target| target.id.clone()), stale: false });
+                return response;
+            }
+            if let Some(etag) = entry.response.header("ETag") {
+                sent.headers.push(("If-None-Match".to_string(), etag.to_string()));
+            }
+        }
+
+        let received = source.fetch(&sent).map(|response| self.store(&request, response));
+        let previous = self.responses.remove(&request).and_then(|page| page.response);
+        let page = PageRequest {
+            pending: received.is_none(),
+            response: received.or(previous),
+            sent: self.now,
+            target: fetch.target.as_ref().map(|target| target.id.clone()),
+            stale: false
+        };
+        let response = page.response.clone();
+        self.responses.insert(request, page);
+        response
+    }
+
+    /// # Caches successful responses of `GET` requests
+    /// `304 Not Modified` refreshes the cached response and is replaced by it.
+    fn store(&mut self, request: &Request, response: Response) -> Response {
+        if request.method != HttpMethod::GET {
+            return response;
+        }
+        if response.status == 304 {
+            if let Some(entry) = self.cache.get_mut(request) {
+                entry.stored = self.now;
+                return entry.response.clone();
+            }
+        }
+        if (200..300).contains(&response.status) {
+            self.cache.insert(request.clone(), CacheEntry { response: response.clone(), stored: self.now });
+        }
+        response
+    }
+
+    /// # Opens stream, unless it is already open, and returns the value of the subscribed variable
+    /// The latest message (`REPLACE`) or an array of all messages since the subscription (`APPEND`).
+    fn subscribe(&mut self, subscribe: &SubscribeOperation, frame: &Frame, source: &mut dyn DataSource) -> Json {
+        let mut url = Interpreter::value(&subscribe.from, frame).to_text();
+        if url.starts_with('/') {
+            let base_url = match subscribe.transport {
+                Transport::WebSocket => self.base_url.replacen("http", "ws", 1),
+                Transport::EventSource => self.base_url.clone()
+            };
+            url = format!("{}{}", base_url, url);
+        }
+        let stream = Stream { transport: subscribe.transport, url };
+
+        let state = self.streams.entry(stream).or_insert_with_key(|stream| {
+            source.subscribe(stream);
+            StreamState { latest: Json::Null, messages: Vec::new(), appending: false }
+        });
+        match subscribe.mode {
+            StreamMode::Replace => state.latest.clone(),
+            StreamMode::Append => {
+                state.appending = true;
+                Json::Array(state.messages.clone())
+            }
+        }
+    }
+
+    fn build_request(&self, fetch: &FetchOperation, frame: &Frame) -> Request {
+        let mut url = Interpreter::value(&fetch.from, frame).to_text();
+        if url.starts_with('/') {
+            url = format!("{}{}", self.base_url, url);
+        }
+        for (index, param) in fetch.query.iter().enumerate() {
+            let separator = if index == 0 && !url.contains('?') { '?' } else { '&' };
+            let value = Interpreter::value(&param.value, frame).to_text();
+            url = format!("{}{}{}={}", url, separator, url_encode(&param.name), url_encode(&value));
+        }
+
+        let body = fetch.body.as_ref().map(|body| match body {
+            RequestBody::Data(data) => match Interpreter::value(data, frame) {
+                value @ (Json::Object(_)|Json::Array(_)) => value.serialize(),
+                value => value.to_text()
+            },
+            RequestBody::Form(id) => {
+                let fields = self.forms.get(id).cloned().unwrap_or_default();
+                Json::Object(fields.into_iter().map(|(name, value)| (name, Json::String(value))).collect()).serialize()
+            }
+        });
+
+        Request {
+            method: fetch.method,
+            url,
+            headers: fetch.headers.iter().map(|header| (header.name.clone(), Interpreter::value(&header.value, frame).to_text())).collect(),
+            body,
+            credentials: fetch.credentials
+        }
+    }
+
+    fn value(data: &Data, frame: &Frame) -> Json {
+        match data {
+            Data::Value(value) => Json::String(value.clone()),
+            Data::Variable(variable, _) => frame.lookup(variable)
+        }
+    }
+
+    /// # Evaluates expression
+    /// Comparisons are booleans, numbers are compared by value and everything else by its text (see `Comparison`).
+    fn evaluate(expression: &Expression, frame: &Frame) -> Json {
+        let value = Interpreter::sum(&expression.operands, frame);
+        let (comparison, operands) = match &expression.comparison {
+            Some(comparison) => comparison,
+            None => return value
+        };
+        let other = Interpreter::sum(operands, frame);
+        let order = match (as_number(&value), as_number(&other)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ if comparison.is_order() => None,
+            _ => Some(value.to_text().cmp(&other.to_text()))
+        };
+        Json::Bool(match (comparison, order) {
+            (_, None) => false,
+            (Comparison::Equal, Some(order)) => order.is_eq(),
+            (Comparison::NotEqual, Some(order)) => order.is_ne(),
+            (Comparison::Less, Some(order)) => order.is_lt(),
+            (Comparison::LessOrEqual, Some(order)) => order.is_le(),
+            (Comparison::Greater, Some(order)) => order.is_gt(),
+            (Comparison::GreaterOrEqual, Some(order)) => order.is_ge()
+        })
+    }
+
+    /// A single operand keeps its value, numbers are added and everything else is concatenated
+    fn sum(operands: &LinkedList<Operand>, frame: &Frame) -> Json {
+        let values: Vec<Json> = operands.iter().map(|operand| {
+            operand.filters.iter().fold(Interpreter::value(&operand.data, frame), |value, filter| apply_filter(filter, value))
+        }).collect();
+
+        if values.len() == 1 {
+            return values.into_iter().next().unwrap();
+        }
+        let numbers: Option<Vec<f64>> = values.iter().map(as_number).collect();
+        match numbers {
+            Some(numbers) => Json::Number(numbers.iter().sum()),
+            None => Json::String(values.iter().map(Json::to_text).collect())
+        }
+    }
+}
+
+/// # Key of a rendered listener or form control
+/// The key is the index of the operation, followed by `-n` for its n-th repetition, e.g. in a loop.
+/// Keys thereby stay the same outside of regions that are rendered again.
+fn repetition_key(index: usize, taken: impl Fn(&String) -> bool) -> String {
+    (0..)
+        .map(|n| if n == 0 { index.to_string() } else { format!("{}-{}", index, n) })
+        .find(|key| !taken(key))
+        .unwrap()
+}
+
+/// # Message of the first rule the value violates
+/// Values that are missing, blank, empty arrays or unchecked (`false`) only violate `REQUIRED`.
+/// Lengths count characters, numbers may be given as text.
+fn violated_rule(rules: &[ValidationRule], value: &Json) -> Option<String> {
+    let text = value.to_text();
+    let blank = match value {
+        Json::Null|Json::Bool(false) => true,
+        Json::Array(items) => items.is_empty(),
+        _ => text.trim().is_empty()
+    };
+    let length = text.chars().count();
+    rules.iter().find(|rule| match &rule.rule {
+        Rule::Required => blank,
+        _ if blank => false,
+        Rule::Email => !is_email(&text),
+        Rule::MinLength(min) => length < *min,
+        Rule::MaxLength(max) => length > *max,
+        Rule::Pattern(pattern) => !Pattern::parse(pattern).is_ok_and(|pattern| pattern.matches(&text)),
+        Rule::Min(min) => !as_number(value).is_some_and(|number| number >= *min),
+        Rule::Max(max) => !as_number(value).is_some_and(|number| number <= *max)
+    }).map(ValidationRule::message)
+}
+
+/// Address with a single `@`, a local part and a domain containing a dot, but no whitespace
+fn is_email(text: &str) -> bool {
+    match text.split_once('@') {
+        Some((local, domain)) => !local.is_empty()
+            && !domain.contains('@')
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+            && !text.contains(char::is_whitespace),
+        None => false
+    }
+}
+
+fn as_number(value: &Json) -> Option<f64> {
+    match value {
+        Json::Number(n) => Some(*n),
+        Json::String(s) => s.parse().ok(),
+        _ => None
+    }
+}
+
+fn apply_filter(filter: &Filter, value: Json) -> Json {
+    match filter {
+        Filter::Length => Json::Number(match &value {
+            Json::Array(items) => items.len(),
+            Json::Object(members) => members.len(),
+            Json::Null => 0,
+            value => value.to_text().chars().count()
+        } as f64),
+        Filter::Upper => Json::String(value.to_text().to_uppercase()),
+        Filter::Lower => Json::String(value.to_text().to_lowercase()),
+        Filter::Trim => Json::String(value.to_text().trim().to_string())
+    }
+}
+
+/// # Converts response into data or error
+/// Errors are objects with `status` and `message`, which is taken from the body (`message` member or text) if possible.
+fn parse_response(response: &Response) -> Result<Json, Json> {
+    let error = |message: String| Json::Object(vec![
+        ("status".to_string(), Json::Number(response.status as f64)),
+        ("message".to_string(), Json::String(message))
+    ]);
+
+    if !(200..300).contains(&response.status) {
+        let message = match Json::parse(&response.body) {
+            Ok(body) => body.get("message").map(Json::to_text),
+            Err(_) => None
+        };
+        let message = message.unwrap_or_else(|| match response.body.trim() {
+            "" if response.status == 0 => "network failure".to_string(),
+            "" => format!("request failed with status {}", response.status),
+            body => body.to_string()
+        });
+        return Err(error(message));
+    }
+
+    if response.body.trim().is_empty() {
+        return Ok(Json::Null);
+    }
+    Json::parse(&response.body).map_err(|cause| error(format!("invalid JSON: {}", cause)))
+}
+
+/// Request as it is known to the page, without headers added for revalidation
+fn without_revalidation(request: &Request) -> Request {
+    let mut request = request.clone();
+    request.headers.retain(|(name, _)| !name.eq_ignore_ascii_case("If-None-Match"));
+    request
+}
+
+/// Percent-encodes value (RFC 3986), like the JavaScript host does for query parameters (see `ws_http_send`)
+pub fn url_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte))
+        }
+    }
+    encoded
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::{HashMap, LinkedList}, time::Duration};
+
+    use crate::compiler::{build_model, model::{CoreModel, HttpMethod}};
+    use crate::json::Json;
+    use super::{DataSource, HandlerCall, Interpreter, Patch, Request, Response, url_encode};
+
+    /// Answers requests by url, unknown urls are pending
+    struct MockSource {
+        responses: HashMap<String, Response>,
+        requests: Vec<Request>
+    }
+
+    impl MockSource {
+        fn new(responses: Vec<(&str, Response)>) -> MockSource {
+            Self {
+                responses: responses.into_iter().map(|(url, response)| (url.to_string(), response)).collect(),
+                requests: Vec::new()
+            }
+        }
+    }
+
+    impl DataSource for MockSource {
+        fn fetch(&mut self, request: &Request) -> Option<Response> {
+            self.requests.push(request.clone());
+            self.responses.get(&request.url).cloned()
+        }
+    }
+
+    /// Rendered HTML without the anchors of bound nodes
+    pub(super) fn strip_anchors(html: String) -> String {
+        let mut stripped = String::new();
+        let mut rest = html.as_str();
+        let markers = [("<!--ws:", "-->"), ("<!--/ws:", "-->"), (" data-ws-anchor=\"", "\"")];
+        while let Some((start, (marker, end))) = markers.iter().filter_map(|marker| rest.find(marker.0).map(|start| (start, marker))).min() {
+            stripped.push_str(&rest[..start]);
+            rest = &rest[start + marker.len()..];
+            rest = &rest[rest.find(end).unwrap() + end.len()..];
+        }
+        stripped.push_str(rest);
+        stripped
+    }
+
+    fn model(sources: Vec<(&str, &'static str)>) -> CoreModel {
+        let sources: LinkedList<(String, &'static str)> = sources.into_iter().map(|(id, source)| (id.to_string(), source)).collect();
+        build_model(sources, &mut LinkedList::new()).ok().unwrap()
+    }
+
+    #[test]
+    fn render_templates() {
+        let model = model(vec![
+            ("index.ws", "{IMPORT './card.ws' AS card}{GET shop FROM '/api/shop'}{SET count = shop.items | length}{SET name = shop.name | upper}\
+                <h1>{name}: {count}</h1>{FOR item IN shop.items}{INSERT card PARAMS title=item.name}{FILL price}{item.price} EUR{END}{END}{END}\
+                {IF shop.open}open{ELSE}closed{END}"),
+            ("card.ws", "{PARAMS title}<li>{title}{SLOT price}</li>")
+        ]);
+        let mut source = MockSource::new(vec![("/api/shop", Response::ok("{\"name\": \"Tom & Co\", \"open\": false, \"items\": [{\"name\": \"Tea\", \"price\": 3}, {\"name\": \"Cake\", \"price\": 2.5}]}"))]);
+
+        let html = strip_anchors(Interpreter::new(&model).render("index.ws", &mut source).unwrap());
+        assert_eq!(html, "<h1>TOM &amp; CO: 2</h1><li>Tea3 EUR</li><li>Cake2.5 EUR</li>closed");
+    }
+
+    #[test]
+    fn render_comparisons() {
+        let model = model(vec![("index.ws", "{GET shop FROM '/api/shop'}{FOR item IN shop.items}\
+            {IF item.price >= 3}{item.name} {END}{IF item.price < 3}cheap {END}{IF item.name != 'Tea'}not tea {END}{IF item.name == 'Tea'}tea {END}\
+            {END}{IF shop.items | length > 1}many{END}{IF shop.name < 1}never{END}")]);
+        let mut source = MockSource::new(vec![("/api/shop", Response::ok("{\"name\": \"Tom\", \"items\": [{\"name\": \"Tea\", \"price\": 3}, {\"name\": \"Cake\", \"price\": \"2.5\"}]}"))]);
+
+        // numbers given as text are compared by value, orders of text never hold
+        let html = strip_anchors(Interpreter::new(&model).render("index.ws", &mut source).unwrap());
+        assert_eq!(html, "Tea tea cheap not tea many");
+    }
+
+    #[test]
+    fn render_paths() {
+        let model = model(vec![("index.ws", "{GET user FROM '/api/user'}<p>{user.orders[1].id}, {user['first name']}, {user?.address?.city}, {user.orders.0.id}</p>\
+            <input {BIND input TO user.orders[0].id}>")]);
+        let mut source = MockSource::new(vec![("/api/user", Response::ok("{\"first name\": \"Ada\", \"address\": null, \"orders\": [{\"id\": 7}, {\"id\": 8}]}"))]);
+        let mut interpreter = Interpreter::new(&model);
+
+        // properties named like indices are no items of arrays
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>8, Ada, , </p><input data-ws-bind=\"0\" value=\"7\">");
+        interpreter.input("0", "9").unwrap();
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>8, Ada, , </p><input data-ws-bind=\"0\" value=\"9\">");
+    }
+
+    #[test]
+    fn render_fetch_states() {
+        let model = model(vec![("index.ws", "{GET items FROM '/api/items'}<ul>{FOR item IN items}<li>{item}</li>{END}</ul>{LOADING}<p>loading</p>{EMPTY}<p>no items</p>{ERROR e}<p>{e.status}: {e.message}</p>{END}")]);
+        let render = |response: Option<Response>| {
+            let mut source = MockSource::new(response.into_iter().map(|response| ("/api/items", response)).collect());
+            strip_anchors(Interpreter::new(&model).render("index.ws", &mut source).unwrap())
+        };
+
+        assert_eq!(render(None), "<p>loading</p>");
+        assert_eq!(render(Some(Response::ok("[\"a\", \"b\"]"))), "<ul><li>a</li><li>b</li></ul>");
+        assert_eq!(render(Some(Response::ok("[]"))), "<p>no items</p>");
+        assert_eq!(render(Some(Response::new(404, "{\"message\": \"not found\"}"))), "<p>404: not found</p>");
+        assert_eq!(render(Some(Response::new(500, ""))), "<p>500: request failed with status 500</p>");
+        assert_eq!(render(Some(Response::new(0, ""))), "<p>0: network failure</p>");
+        assert!(render(Some(Response::ok("[1,"))).starts_with("<p>200: invalid JSON"));
+    }
+
+    #[test]
+    fn build_requests() {
+        let model = model(vec![("index.ws", "{GET session FROM '/api/session'}\
+            {GET user FROM '/api/user' PARAMS id=session.id, q='a b&c' HEADERS Authorization=session.token WITH credentials}\
+            {POST session.user TO 'https://other.org/save' AS saved}{PUT FORM 'signup' TO '/api/signup'}")]);
+        let mut source = MockSource::new(vec![("https://api.example.com/api/session", Response::ok("{\"id\": 7, \"token\": \"t\", \"user\": {\"name\": \"Ann\"}}"))]);
+
+        let mut interpreter = Interpreter::new(&model);
+        interpreter.set_base_url("https://api.example.com");
+        interpreter.set_form("signup", vec![("email".to_string(), "a@b.c".to_string())]);
+        strip_anchors(interpreter.render("index.ws", &mut source).unwrap());
+
+        let user = &source.requests[1];
+        assert_eq!(user.url, "https://api.example.com/api/user?id=7&q=a%20b%26c");
+        assert_eq!(user.headers, vec![("Authorization".to_string(), "t".to_string())]);
+        assert!(user.credentials);
+
+        let save = &source.requests[2];
+        assert_eq!((save.method, save.url.as_str(), save.body.as_deref()), (HttpMethod::POST, "https://other.org/save", Some("{\"name\":\"Ann\"}")));
+        assert_eq!(source.requests[3].body.as_deref(), Some("{\"email\":\"a@b.c\"}"));
+        assert_eq!(url_encode("ü/"), "%C3%BC%2F");
+    }
+
+    #[test]
+    fn render_regions_as_responses_arrive() {
+        let model = model(vec![("index.ws", "{GET session FROM '/api/session'}{SET url = '/api/users/' + session.id}\
+            {GET user FROM url}<p>{user.name}</p>{LOADING}<p>loading user</p>{END}\
+            {GET news FROM '/api/news'}<ul>{FOR n IN news}<li>{n}</li>{END}</ul>{LOADING}<p>loading news</p>{END}")]);
+        let mut source = MockSource::new(Vec::new());
+        let mut interpreter = Interpreter::new(&model);
+
+        // independent requests are pending at the same time, the dependent one waits for the session
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>loading user</p><p>loading news</p>");
+        let urls: Vec<&str> = source.requests.iter().map(|request| request.url.as_str()).collect();
+        assert_eq!(urls, vec!["/api/session", "/api/news"]);
+
+        let session = source.requests[0].clone();
+        interpreter.receive(&session, Response::ok("{\"id\": 7}"));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>loading user</p><p>loading news</p>");
+        assert_eq!(source.requests.len(), 3);
+        assert_eq!(source.requests[2].url, "/api/users/7");
+
+        let news = source.requests[1].clone();
+        interpreter.receive(&news, Response::ok("[\"a\"]"));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>loading user</p><ul><li>a</li></ul>");
+
+        let user = source.requests[2].clone();
+        interpreter.receive(&user, Response::ok("{\"name\": \"Ann\"}"));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>Ann</p><ul><li>a</li></ul>");
+        // responses are kept, nothing is sent twice
+        assert_eq!(source.requests.len(), 3);
+
+        // requests depending on a failed request are not sent
+        let mut source = MockSource::new(vec![("/api/session", Response::new(500, ""))]);
+        assert_eq!(strip_anchors(Interpreter::new(&model).render("index.ws", &mut source).unwrap()), "<p>loading news</p>");
+        assert_eq!(source.requests.len(), 2);
+    }
+
+    #[test]
+    fn refresh_requests() {
+        let model = model(vec![("index.ws", "{GET prices FROM '/api/prices' REFRESH EVERY 10s}<p>{prices.eur}</p>{GET news FROM '/api/news'}<p>{news}</p>")]);
+        let mut source = MockSource::new(vec![("/api/prices", Response::ok("{\"eur\": 1}")), ("/api/news", Response::ok("\"a\""))]);
+        let mut interpreter = Interpreter::new(&model);
+        let urls = |source: &MockSource| source.requests.iter().map(|request| request.url.clone()).collect::<Vec<String>>();
+
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>1</p><p>a</p>");
+        interpreter.advance(Duration::from_secs(5));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>1</p><p>a</p>");
+        assert_eq!(source.requests.len(), 2);
+
+        // only the request due for refreshing is sent again
+        source.responses.insert("/api/prices".to_string(), Response::ok("{\"eur\": 2}"));
+        source.responses.insert("/api/news".to_string(), Response::ok("\"b\""));
+        interpreter.advance(Duration::from_secs(5));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>2</p><p>a</p>");
+        assert_eq!(urls(&source), vec!["/api/prices", "/api/news", "/api/prices"]);
+
+        // manual refresh
+        interpreter.refresh("news");
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>2</p><p>b</p>");
+        assert_eq!(urls(&source)[3], "/api/news");
+
+        // previous data is shown until the refreshed response arrives
+        source.responses.remove("/api/prices");
+        interpreter.advance(Duration::from_secs(10));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>2</p><p>b</p>");
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>2</p><p>b</p>");
+        assert_eq!(source.requests.len(), 5);
+        let prices = source.requests[4].clone();
+        interpreter.receive(&prices, Response::ok("{\"eur\": 3}"));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>3</p><p>b</p>");
+
+        // only the nodes depending on the refreshed request are patched
+        source.responses.insert("/api/news".to_string(), Response::ok("\"c\""));
+        interpreter.refresh("news");
+        assert_eq!(interpreter.update("index.ws", &mut source).unwrap(), vec![Patch::Text { binding: 1, text: "c".to_string() }]);
+        assert_eq!(urls(&source)[5], "/api/news");
+    }
+
+    #[test]
+    fn deduplicate_requests() {
+        let model = model(vec![
+            ("index.ws", "{IMPORT './user.ws' AS user}{INSERT user}{INSERT user}{GET user FROM '/api/user' CACHE 1m}{user.name}"),
+            ("user.ws", "{GET user FROM '/api/user'}<b>{user.name}</b>{LOADING}...{END}")
+        ]);
+        let mut source = MockSource::new(Vec::new());
+        let mut interpreter = Interpreter::new(&model);
+
+        // identical requests in flight are sent once
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "......");
+        assert_eq!(source.requests.len(), 1);
+
+        let user = source.requests[0].clone();
+        interpreter.receive(&user, Response::ok("{\"name\": \"Ann\"}").with_header("ETag", "\"a\""));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<b>Ann</b><b>Ann</b>Ann");
+        assert_eq!(source.requests.len(), 1);
+
+        // revalidation may be answered later as well
+        interpreter.advance(Duration::from_secs(60));
+        interpreter.reload();
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "......");
+        let revalidation = source.requests[1].clone();
+        assert_eq!(revalidation.headers, vec![("If-None-Match".to_string(), "\"a\"".to_string())]);
+        interpreter.receive(&revalidation, Response::new(304, ""));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<b>Ann</b><b>Ann</b>Ann");
+        assert_eq!(source.requests.len(), 2);
+    }
+    #[test]
+    fn patch_bound_nodes() {
+        let model = model(vec![("index.ws", "{GET user FROM '/api/user' REFRESH EVERY 10s}<a href=\"/users/{user.id}\" class=\"link\">{user.name}</a>\
+            {SET count = user.tags | length}<p>{count} tags</p>{IF user.admin}<b>{user.name}</b>{END}")]);
+        let mut source = MockSource::new(vec![("/api/user", Response::ok("{\"id\": 1, \"name\": \"Ann\", \"tags\": [\"a\"], \"admin\": false}"))]);
+        let mut interpreter = Interpreter::new(&model);
+
+        // bound text nodes and regions are enclosed in comments, elements name their bound attributes
+        assert_eq!(interpreter.render("index.ws", &mut source).unwrap(), "<a href=\"/users/1\" class=\"link\" data-ws-anchor=\"index.ws:0\">\
+            <!--ws:index.ws:1-->Ann<!--/ws:index.ws:1--></a><p><!--ws:index.ws:2-->1<!--/ws:index.ws:2--> tags</p><!--ws:index.ws:3--><!--/ws:index.ws:3-->");
+        assert_eq!(interpreter.update("index.ws", &mut source).unwrap(), vec![]);
+
+        // the text inside of the shown branch is part of the region
+        source.responses.insert("/api/user".to_string(), Response::ok("{\"id\": 1, \"name\": \"B & B\", \"tags\": [\"a\"], \"admin\": true}"));
+        interpreter.advance(Duration::from_secs(10));
+        assert_eq!(interpreter.update("index.ws", &mut source).unwrap(), vec![
+            Patch::Text { binding: 1, text: "B & B".to_string() },
+            Patch::Region { binding: 3, html: "<b><!--ws:index.ws:4-->B &amp; B<!--/ws:index.ws:4--></b>".to_string() }
+        ]);
+
+        source.responses.insert("/api/user".to_string(), Response::ok("{\"id\": 2, \"name\": \"B & B\", \"tags\": [], \"admin\": true}"));
+        interpreter.refresh("user");
+        assert_eq!(interpreter.update("index.ws", &mut source).unwrap(), vec![
+            Patch::Attribute { binding: 0, name: "href".to_string(), value: "/users/2".to_string() },
+            Patch::Text { binding: 2, text: "0".to_string() }
+        ]);
+    }
+    #[test]
+    fn dispatch_events() {
+        let model = model(vec![("index.ws", "{GET items FROM '/api/items'}<button {ON click CALL refresh items}>Reload</button>\
+            <ul>{FOR item IN items}<li {ON click CALL select item.id}>{item.name}</li>{END}</ul>\
+            <form {ON submit POST form TO '/api/create' AS created}></form>{IF created}<p>Created {created.name}</p>{END}")]);
+        let mut source = MockSource::new(vec![("/api/items", Response::ok("[{\"id\": 1, \"name\": \"A\"}]")), ("/api/create", Response::new(201, "{\"name\": \"B\"}"))]);
+        let mut interpreter = Interpreter::new(&model);
+
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<button data-ws-on-click=\"0\">Reload</button>\
+            <ul><li data-ws-on-click=\"1\">A</li></ul><form data-ws-on-submit=\"2\"></form>");
+
+        // handlers of the host get the values visible to the listener
+        let call = interpreter.dispatch("1", vec![], &mut source).unwrap();
+        assert_eq!(call, Some(HandlerCall { handler: "select".to_string(), argument: Json::Number(1.0) }));
+
+        interpreter.dispatch("2", vec![("name".to_string(), "B".to_string())], &mut source).unwrap();
+        assert_eq!(source.requests[1].body.as_deref(), Some("{\"name\":\"B\"}"));
+        assert_eq!(interpreter.update("index.ws", &mut source).unwrap(), vec![Patch::Region { binding: 1, html: "<p>Created <!--ws:index.ws:2-->B<!--/ws:index.ws:2--></p>".to_string() }]);
+
+        source.responses.insert("/api/items".to_string(), Response::ok("[{\"id\": 1, \"name\": \"A\"}, {\"id\": 2, \"name\": \"B\"}]"));
+        assert_eq!(interpreter.dispatch("0", vec![], &mut source).unwrap(), None);
+        assert_eq!(interpreter.update("index.ws", &mut source).unwrap(), vec![
+            Patch::Region { binding: 0, html: "<li data-ws-on-click=\"1\">A</li><li data-ws-on-click=\"1-1\">B</li>".to_string() }
+        ]);
+        assert_eq!(source.requests.len(), 3);
+        // repetitions of a listener are numbered, listeners outside of the loop keep their keys
+        let call = interpreter.dispatch("1-1", vec![], &mut source).unwrap();
+        assert_eq!(call.unwrap().argument, Json::Number(2.0));
+        let err = interpreter.dispatch("9", vec![], &mut source).err().unwrap();
+        assert_eq!((err.name.as_str(), err.cause.as_str()), ("ERR_UNKNOWN_LISTENER", "listener '9' has not been rendered"));
+    }
+
+    #[test]
+    fn bind_form_controls() {
+        let model = model(vec![("index.ws", "{GET user FROM '/api/user'}<form {ON submit PUT user TO '/api/user'}>\
+            <input {BIND input TO user.email}><input {BIND change TO user.age}></form><p>{user.email}</p>")]);
+        let mut source = MockSource::new(vec![("/api/user", Response::ok("{\"email\": \"a@b.c\", \"age\": 30}"))]);
+        let mut interpreter = Interpreter::new(&model);
+
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<form data-ws-on-submit=\"0\">\
+            <input data-ws-bind=\"0\" value=\"a@b.c\"><input data-ws-bind=\"1\" value=\"30\"></form><p>a@b.c</p>");
+
+        interpreter.input("0", "<x>@y.z").unwrap();
+        interpreter.input("1", "31").unwrap();
+        assert_eq!(interpreter.update("index.ws", &mut source).unwrap(), vec![Patch::Text { binding: 0, text: "<x>@y.z".to_string() }]);
+        assert!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()).contains("value=\"&lt;x&gt;@y.z\""));
+
+        // the bound variable is sent with its changes
+        interpreter.dispatch("0", vec![], &mut source).unwrap();
+        assert_eq!(source.requests[1].body.as_deref(), Some("{\"email\":\"<x>@y.z\",\"age\":31}"));
+        assert_eq!(interpreter.input("2", "").err().unwrap().name, "ERR_UNKNOWN_INPUT");
+    }
+
+    #[test]
+    fn validate_form_controls() {
+        let signup = model(vec![("index.ws", "{GET user FROM '/api/user'}<form {ON submit POST user TO '/api/signup'}>\
+            <input {BIND input TO user.email}>{VALIDATE user.email REQUIRED 'Enter your email' EMAIL AS emailError}\
+            <input {BIND input TO user.age}>{VALIDATE user.age MIN 18 MAX 130 AS ageError}\
+            <input {BIND input TO user.zip}>{VALIDATE user.zip PATTERN '\\d{5}' AS zipError}</form>\
+            <p>{emailError}</p><p>{ageError}</p><p>{zipError}</p>")]);
+        let mut source = MockSource::new(vec![("/api/user", Response::ok("{\"email\": \"\", \"age\": 30, \"zip\": \"\"}"))]);
+        let mut interpreter = Interpreter::new(&signup);
+        let errors = |html: String| html.split("</form>").nth(1).unwrap().to_string();
+
+        // untouched values do not show errors, until a request is blocked
+        assert_eq!(errors(strip_anchors(interpreter.render("index.ws", &mut source).unwrap())), "<p></p><p></p><p></p>");
+        interpreter.dispatch("0", vec![], &mut source).unwrap();
+        assert_eq!(source.requests.len(), 1);
+        assert!(interpreter.update("index.ws", &mut source).unwrap().contains(&Patch::Text { binding: 0, text: "Enter your email".to_string() }));
+
+        interpreter.input("0", "a@b").unwrap();
+        interpreter.input("1", "12").unwrap();
+        interpreter.input("2", "8907").unwrap();
+        assert_eq!(errors(strip_anchors(interpreter.render("index.ws", &mut source).unwrap())), "<p>Invalid email address</p><p>At least 18</p><p>Invalid format</p>");
+        interpreter.dispatch("0", vec![], &mut source).unwrap();
+        assert_eq!(source.requests.len(), 1);
+
+        interpreter.input("0", "a@b.de").unwrap();
+        interpreter.input("1", "40").unwrap();
+        interpreter.input("2", "89073").unwrap();
+        assert_eq!(errors(strip_anchors(interpreter.render("index.ws", &mut source).unwrap())), "<p></p><p></p><p></p>");
+        interpreter.dispatch("0", vec![], &mut source).unwrap();
+        assert_eq!(source.requests[1].body.as_deref(), Some("{\"email\":\"a@b.de\",\"age\":40,\"zip\":\"89073\"}"));
+
+        // requests of the page sending data wait for valid values as well
+        let page = model(vec![("index.ws", "{GET user FROM '/api/user'}{VALIDATE user.name REQUIRED}{POST user TO '/api/users'}")]);
+        let mut source = MockSource::new(vec![("/api/user", Response::ok("{\"name\": \" \"}"))]);
+        strip_anchors(Interpreter::new(&page).render("index.ws", &mut source).unwrap());
+        assert_eq!(source.requests.len(), 1);
+    }
+
+    #[test]
+    fn route_between_templates() {
+        let model = model(vec![
+            ("index.ws", "{IMPORT './home.ws' AS home}{IMPORT './user.ws' AS user}{IMPORT './404.ws' AS missing}\
+                <a href=\"/users/1\" {ON click CALL navigate '/users/2'}>User</a>\
+                <main>{ROUTE '/' USE home}{ROUTE '/users/:id' USE user}{ROUTE FALLBACK USE missing}</main>"),
+            ("home.ws", "<h1>Home</h1>"),
+            ("user.ws", "{PARAMS id}{SET url = '/api/users/' + id}{GET user FROM url}<h1>{user.name}</h1>"),
+            ("404.ws", "<h1>Not found</h1>")
+        ]);
+        let mut source = MockSource::new(vec![("/api/users/1", Response::ok("{\"name\": \"Ada\"}")), ("/api/users/2", Response::ok("{\"name\": \"Bob\"}"))]);
+        let mut interpreter = Interpreter::new(&model);
+        let main = |html: String| html.split("<main>").nth(1).unwrap().to_string();
+
+        assert_eq!(main(strip_anchors(interpreter.render("index.ws", &mut source).unwrap())), "<h1>Home</h1></main>");
+        assert!(interpreter.intercepts("/users/1?tab=posts"));
+        assert!(!interpreter.intercepts("https://example.com/users/1"));
+        assert!(!interpreter.intercepts("/about"));
+
+        // only the regions of the routes are patched
+        interpreter.navigate("/users/1?tab=posts");
+        assert_eq!(interpreter.update("index.ws", &mut source).unwrap(), vec![
+            Patch::Region { binding: 0, html: String::new() },
+            Patch::Region { binding: 1, html: "<h1>Ada</h1>".to_string() }
+        ]);
+        interpreter.dispatch("0", vec![], &mut source).unwrap();
+        assert_eq!(main(strip_anchors(interpreter.render("index.ws", &mut source).unwrap())), "<h1>Bob</h1></main>");
+        interpreter.navigate("/users/2/posts");
+        assert_eq!(main(strip_anchors(interpreter.render("index.ws", &mut source).unwrap())), "<h1>Not found</h1></main>");
+
+        assert!(interpreter.back());
+        assert!(interpreter.back());
+        assert_eq!(main(strip_anchors(interpreter.render("index.ws", &mut source).unwrap())), "<h1>Ada</h1></main>");
+        assert!(interpreter.back());
+        assert!(!interpreter.back());
+    }
+}
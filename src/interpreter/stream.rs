@@ -0,0 +1,472 @@
+use std::{collections::hash_map::RandomState, hash::{BuildHasher, Hasher}, io::{BufRead, BufReader, Read, Write}, net::TcpStream, sync::mpsc::{self, Receiver, Sender}, thread, time::Duration};
+
+use crate::compiler::model::Transport;
+use super::{Stream, http::split_url};
+
+/// Longest message accepted from a WebSocket, counting all of its fragments. Longer messages close the stream
+const MAX_MESSAGE_LENGTH: usize = 16 * 1024 * 1024;
+
+/**
+ * Event of an open stream
+ */
+#[derive(Debug, PartialEq)]
+pub enum StreamEvent {
+    /// text of a message, usually JSON
+    Message(String),
+    /// the stream ended, e.g. closed by the server or failed to connect
+    Closed(String)
+}
+
+/**
+ * Receives messages of WebSockets (`ws://`) and server-sent events (`http://`).
+ * Every stream is read by a thread of its own, events of all streams are collected in order of arrival.
+ */
+pub struct StreamClient {
+    sender: Sender<(Stream, StreamEvent)>,
+    receiver: Receiver<(Stream, StreamEvent)>
+}
+
+impl StreamClient {
+    pub fn new() -> StreamClient {
+        let (sender, receiver) = mpsc::channel();
+        Self { sender, receiver }
+    }
+
+    pub fn connect(&mut self, stream: &Stream) {
+        let stream = stream.clone();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let send = |event| sender.send((stream.clone(), event)).is_ok();
+            let result = match stream.transport {
+                Transport::WebSocket => read_websocket(&stream.url, send),
+                Transport::EventSource => read_event_source(&stream.url, send)
+            };
+            let reason = result.err().unwrap_or_else(|| "closed by server".to_string());
+            let _ = sender.send((stream, StreamEvent::Closed(reason)));
+        });
+    }
+
+    /// Next event of any stream, `None` if nothing arrived within `timeout`
+    pub fn next_event(&self, timeout: Duration) -> Option<(Stream, StreamEvent)> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+}
+
+/// # Reads messages of a WebSocket (RFC 6455)
+/// Fragmented messages are joined, pings are answered. Returns once the server closes the connection.
+fn read_websocket(url: &str, send: impl Fn(StreamEvent) -> bool) -> Result<(), String> {
+    let (host, path) = split_url(url, "ws://")?;
+    let mut writer = TcpStream::connect(host).map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(writer.try_clone().map_err(|err| err.to_string())?);
+
+    let key = base64(&random_bytes::<16>());
+    let handshake = format!("GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n", path, host, key);
+    writer.write_all(handshake.as_bytes()).map_err(|err| err.to_string())?;
+
+    let (status, headers) = read_head(&mut reader)?;
+    if status != 101 {
+        return Err(format!("handshake failed with status {}", status));
+    }
+    let accept = headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("Sec-WebSocket-Accept")).map(|(_, value)| value.as_str());
+    if accept != Some(accept_key(&key).as_str()) {
+        return Err("handshake failed, invalid Sec-WebSocket-Accept".to_string());
+    }
+    read_messages(&mut reader, &mut writer, send)
+}
+
+/// Reads frames of an open WebSocket until it is closed, joining fragments of messages and answering pings
+fn read_messages(reader: &mut impl Read, writer: &mut impl Write, send: impl Fn(StreamEvent) -> bool) -> Result<(), String> {
+    let mut message = Vec::new();
+    loop {
+        // fragments are not read if they do not fit into the rest of the message
+        let (fin, opcode, payload) = read_frame(reader, MAX_MESSAGE_LENGTH - message.len())?;
+        match opcode {
+            0..=2 => {
+                message.extend_from_slice(&payload);
+                if fin {
+                    let text = String::from_utf8(std::mem::take(&mut message)).map_err(|_| "invalid UTF-8 in message")?;
+                    if !send(StreamEvent::Message(text)) {
+                        return Ok(());
+                    }
+                }
+            },
+            8 => {
+                let _ = writer.write_all(&client_frame(8, &payload));
+                return Ok(());
+            },
+            9 => writer.write_all(&client_frame(10, &payload)).map_err(|err| err.to_string())?,
+            _ => ()
+        }
+    }
+}
+
+/// Reads frame, returns whether it is the final fragment, its opcode and its (unmasked) payload.
+/// Data frames longer than `limit` are not read, control frames are limited to 125 bytes (RFC 6455).
+fn read_frame(reader: &mut impl Read, limit: usize) -> Result<(bool, u8, Vec<u8>), String> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head).map_err(|err| err.to_string())?;
+    let length = match head[1] & 0x7f {
+        126 => {
+            let mut length = [0; 2];
+            reader.read_exact(&mut length).map_err(|err| err.to_string())?;
+            u16::from_be_bytes(length) as u64
+        },
+        127 => {
+            let mut length = [0; 8];
+            reader.read_exact(&mut length).map_err(|err| err.to_string())?;
+            u64::from_be_bytes(length)
+        },
+        length => length as u64
+    };
+    let limit = if head[0] & 0x08 != 0 { 125 } else { limit };
+    if length > limit as u64 {
+        return Err(format!("frame of {} bytes exceeds {} bytes", length, limit));
+    }
+    let mut mask = [0; 4];
+    if head[1] & 0x80 != 0 {
+        reader.read_exact(&mut mask).map_err(|err| err.to_string())?;
+    }
+    let mut payload = vec![0; length as usize];
+    reader.read_exact(&mut payload).map_err(|err| err.to_string())?;
+    for (index, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[index % 4];
+    }
+    Ok((head[0] & 0x80 != 0, head[0] & 0x0f, payload))
+}
+
+/// Final frame with masked payload, as clients must mask all frames they send
+fn client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        length @ 0..=125 => frame.push(0x80 | length as u8),
+        length @ 126..=0xffff => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        },
+        length => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+    let mask = random_bytes::<4>();
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(index, byte)| byte ^ mask[index % 4]));
+    frame
+}
+
+/// # Reads server-sent events
+/// `data:` lines of an event are joined by line breaks, comments and other fields are ignored.
+/// Returns once the server closes the connection.
+fn read_event_source(url: &str, send: impl Fn(StreamEvent) -> bool) -> Result<(), String> {
+    let (host, path) = split_url(url, "http://")?;
+    let mut writer = TcpStream::connect(host).map_err(|err| err.to_string())?;
+    let mut reader = BufReader::new(writer.try_clone().map_err(|err| err.to_string())?);
+
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nAccept: text/event-stream\r\nCache-Control: no-cache\r\n\r\n", path, host);
+    writer.write_all(request.as_bytes()).map_err(|err| err.to_string())?;
+    let (status, _) = read_head(&mut reader)?;
+    if status != 200 {
+        return Err(format!("request failed with status {}", status));
+    }
+
+    let mut data: Vec<String> = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        if line.is_empty() {
+            if !data.is_empty() && !send(StreamEvent::Message(std::mem::take(&mut data).join("\n"))) {
+                return Ok(());
+            }
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Reads status line and headers of an HTTP response
+fn read_head(reader: &mut impl BufRead) -> Result<(u16, Vec<(String, String)>), String> {
+    let mut status = None;
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+            return Err("connection closed during handshake".to_string());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok((status.ok_or("invalid status line")?, headers));
+        }
+        match status {
+            None => status = Some(line.split(' ').nth(1).and_then(|status| status.parse().ok()).ok_or("invalid status line")?),
+            Some(_) => if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+    }
+}
+
+/// `Sec-WebSocket-Accept` expected for a `Sec-WebSocket-Key`
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key).as_bytes()))
+}
+
+/// # Bytes which are unpredictable enough for handshake keys and masks
+/// Every `RandomState` hashes with other keys, which the standard library seeds from the randomness of the system.
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    for chunk in bytes.chunks_mut(8) {
+        let hash = RandomState::new().build_hasher().finish().to_le_bytes();
+        chunk.copy_from_slice(&hash[..chunk.len()]);
+    }
+    bytes
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                text.push(ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for index in 0..16 {
+            words[index] = u32::from_be_bytes([block[4 * index], block[4 * index + 1], block[4 * index + 2], block[4 * index + 3]]);
+        }
+        for index in 16..80 {
+            words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (index, word) in words.iter().enumerate() {
+            let (f, k) = match index {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6)
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (index, value) in state.iter().enumerate() {
+        digest[4 * index..4 * index + 4].copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::LinkedList, io::{BufRead, BufReader, Write}, net::{TcpListener, TcpStream}, sync::mpsc::{self, Receiver}, thread, time::Duration};
+
+    use crate::{compiler::{build_model, model::Transport}, interpreter::{Interpreter, Stream, http::HttpSource, tests::strip_anchors}};
+    use super::{MAX_MESSAGE_LENGTH, StreamClient, StreamEvent, accept_key, base64, random_bytes, read_frame, read_messages, sha1};
+
+    /// Unmasked frame, as sent by servers
+    fn server_frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![if fin { 0x80 } else { 0 } | opcode];
+        if payload.len() < 126 {
+            frame.push(payload.len() as u8);
+        } else {
+            frame.push(126);
+            frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        }
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /**
+     * Local stand-in for a streaming server:
+     * `/prices` is a WebSocket, which echoes the payload of the client's pong as a message,
+     * `/events` sends server-sent events.
+     * The outcome of every connection is sent to the returned receiver, so failures are reported by the test itself.
+     */
+    fn start_server() -> (String, Receiver<Result<(), String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for connection in listener.incoming() {
+                let sender = sender.clone();
+                let result = connection.map_err(|err| err.to_string());
+                thread::spawn(move || sender.send(result.and_then(serve)));
+            }
+        });
+        (address, receiver)
+    }
+
+    fn serve(mut connection: TcpStream) -> Result<(), String> {
+        let mut reader = BufReader::new(connection.try_clone().map_err(|err| err.to_string())?);
+        let mut head = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).map_err(|err| err.to_string())?;
+            if line.trim_end().is_empty() {
+                break;
+            }
+            head.push(line.trim_end().to_string());
+        }
+
+        let mut write = |bytes: &[u8]| connection.write_all(bytes).map_err(|err| err.to_string());
+        if head.first().is_some_and(|line| line.starts_with("GET /prices ")) {
+            let key = head.iter().find_map(|line| line.strip_prefix("Sec-WebSocket-Key: ")).ok_or("handshake without key")?;
+            write(format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept_key(key)).as_bytes())?;
+            write(&server_frame(false, 1, b"{\"eur\": "))?;
+            write(&server_frame(true, 0, b"1}"))?;
+            write(&server_frame(true, 9, b"ping"))?;
+            let (_, opcode, payload) = read_frame(&mut reader, MAX_MESSAGE_LENGTH)?;
+            if opcode != 10 {
+                return Err(format!("expected pong, got opcode {}", opcode));
+            }
+            let echo = String::from_utf8(payload).map_err(|err| err.to_string())?;
+            write(&server_frame(true, 1, format!("{{\"eur\": 2, \"echo\": \"{}\", \"padding\": \"{}\"}}", echo, "x".repeat(200)).as_bytes()))?;
+            write(&server_frame(true, 8, &[]))?;
+            match read_frame(&mut reader, MAX_MESSAGE_LENGTH)?.1 {
+                8 => Ok(()),
+                opcode => Err(format!("expected close, got opcode {}", opcode))
+            }
+        } else {
+            write(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n")?;
+            write(b"data: {\"n\": 1}\n\n: comment\nevent: update\ndata: {\"n\":\ndata: 2}\n\n")
+        }
+    }
+
+    /// Outcomes of the next `count` connections to the server
+    fn served(results: &Receiver<Result<(), String>>, count: usize) -> Vec<Result<(), String>> {
+        (0..count).map(|_| results.recv_timeout(Duration::from_secs(5)).unwrap_or(Err("connection was not served".to_string()))).collect()
+    }
+
+    /// Events until all `count` streams are closed
+    fn collect_events(client: &StreamClient, count: usize) -> Vec<(Stream, StreamEvent)> {
+        let mut events = Vec::new();
+        while events.iter().filter(|(_, event)| matches!(event, StreamEvent::Closed(_))).count() < count {
+            events.push(client.next_event(Duration::from_secs(5)).expect("stream should send events"));
+        }
+        events
+    }
+
+    #[test]
+    fn encode_handshake() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(&sha1(b"abc")), "qZk+NkcGgWq6PiVxeFDCbJzQ2J0=");
+        // example of RFC 6455
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        // keys and masks differ between calls
+        assert_ne!(random_bytes::<16>(), random_bytes::<16>());
+    }
+
+    #[test]
+    fn limit_frame_length() {
+        let mut frame = vec![0x81, 127];
+        frame.extend_from_slice(&(MAX_MESSAGE_LENGTH as u64 + 1).to_be_bytes());
+        assert!(read_frame(&mut frame.as_slice(), MAX_MESSAGE_LENGTH).is_err());
+        frame = vec![0x81, 127];
+        frame.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert!(read_frame(&mut frame.as_slice(), MAX_MESSAGE_LENGTH).is_err());
+        assert_eq!(read_frame(&mut server_frame(true, 1, b"ok").as_slice(), MAX_MESSAGE_LENGTH), Ok((true, 1, b"ok".to_vec())));
+    }
+
+    #[test]
+    fn limit_fragmented_messages() {
+        let read = |frames: Vec<u8>, written: &mut Vec<u8>| {
+            let messages = RefCell::new(Vec::new());
+            let result = read_messages(&mut frames.as_slice(), written, |event| {
+                messages.borrow_mut().push(event);
+                true
+            });
+            (result, messages.into_inner())
+        };
+
+        // fragments are joined, pings between them are answered
+        let mut frames = server_frame(false, 1, b"{\"eur\": ");
+        frames.extend(server_frame(true, 9, b"ping"));
+        frames.extend(server_frame(true, 0, b"1}"));
+        frames.extend(server_frame(true, 8, &[]));
+        let mut written = Vec::new();
+        assert_eq!(read(frames, &mut written), (Ok(()), vec![StreamEvent::Message("{\"eur\": 1}".to_string())]));
+        assert_eq!(read_frame(&mut written.as_slice(), MAX_MESSAGE_LENGTH), Ok((true, 10, b"ping".to_vec())));
+
+        // the fragment exceeding the rest of the message is not read
+        let mut frames = server_frame(false, 1, b"{\"eur\": ");
+        frames.extend_from_slice(&[0x80, 127]);
+        frames.extend_from_slice(&(MAX_MESSAGE_LENGTH as u64 - 7).to_be_bytes());
+        let error = format!("frame of {} bytes exceeds {} bytes", MAX_MESSAGE_LENGTH - 7, MAX_MESSAGE_LENGTH - 8);
+        assert_eq!(read(frames, &mut Vec::new()), (Err(error), vec![]));
+    }
+
+    #[test]
+    fn receive_messages() {
+        let (address, results) = start_server();
+        let mut client = StreamClient::new();
+        client.connect(&Stream { transport: Transport::WebSocket, url: format!("ws://{}/prices", address) });
+
+        let events: Vec<StreamEvent> = collect_events(&client, 1).into_iter().map(|(_, event)| event).collect();
+        assert_eq!(events[0], StreamEvent::Message("{\"eur\": 1}".to_string()));
+        assert!(matches!(&events[1], StreamEvent::Message(text) if text.starts_with("{\"eur\": 2, \"echo\": \"ping\"")));
+        assert_eq!(events[2], StreamEvent::Closed("closed by server".to_string()));
+        assert_eq!(served(&results, 1), vec![Ok(())]);
+
+        client.connect(&Stream { transport: Transport::EventSource, url: format!("http://{}/events", address) });
+        let events: Vec<StreamEvent> = collect_events(&client, 1).into_iter().map(|(_, event)| event).collect();
+        assert_eq!(events, vec![StreamEvent::Message("{\"n\": 1}".to_string()), StreamEvent::Message("{\"n\":\n2}".to_string()), StreamEvent::Closed("closed by server".to_string())]);
+        assert_eq!(served(&results, 1), vec![Ok(())]);
+
+        client.connect(&Stream { transport: Transport::WebSocket, url: "ws://127.0.0.1:1/".to_string() });
+        assert!(matches!(client.next_event(Duration::from_secs(5)), Some((_, StreamEvent::Closed(_)))));
+    }
+
+    #[test]
+    fn render_subscriptions() {
+        let (address, results) = start_server();
+        let mut sources = LinkedList::new();
+        sources.push_back(("index.ws".to_string(), "{SUBSCRIBE prices FROM '/prices'}{SUBSCRIBE events FROM SSE '/events' APPEND}\
+            <p>{prices.eur}</p><ul>{FOR event IN events}<li>{event.n}</li>{END}</ul>"));
+        let model = build_model(sources, &mut LinkedList::new()).ok().unwrap();
+
+        let mut source = HttpSource::new();
+        let mut interpreter = Interpreter::new(&model);
+        interpreter.set_base_url(&format!("http://{}", address));
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p></p><ul></ul>");
+
+        for (stream, event) in collect_events(source.streams(), 2) {
+            if let StreamEvent::Message(text) = event {
+                interpreter.message(&stream, &text).unwrap();
+            }
+        }
+        // streams are opened once
+        assert_eq!(strip_anchors(interpreter.render("index.ws", &mut source).unwrap()), "<p>2</p><ul><li>1</li><li>2</li></ul>");
+        assert!(source.streams().next_event(Duration::from_millis(100)).is_none());
+        assert_eq!(served(&results, 2), vec![Ok(()), Ok(())]);
+
+        let stream = Stream { transport: Transport::EventSource, url: format!("http://{}/events", address) };
+        assert_eq!(interpreter.message(&stream, "{").err().unwrap().name, "ERR_INVALID_MESSAGE");
+    }
+}
@@ -0,0 +1,356 @@
+/**
+ * Step of a path into a JSON value, paths are only followed by the reference runtime
+ */
+#[cfg(test)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key<'k> {
+    /// member of an object
+    Member(&'k str),
+    /// item of an array
+    Item(usize)
+}
+
+/**
+ * JSON value, used for data fetched at runtime
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// members in order of appearance
+    Object(Vec<(String, Json)>)
+}
+
+impl Json {
+    /// # Parses JSON text
+    /// Errors describe the problem and the byte offset it was found at.
+    pub fn parse(source: &str) -> Result<Json, String> {
+        let mut parser = JsonParser { source: source.as_bytes(), index: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.index < parser.source.len() {
+            return Err(format!("unexpected content after value at {}", parser.index));
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            Json::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+            _ => None
+        }
+    }
+
+    /// # Follows path of keys, e.g. `[Member("address"), Member("city")]` or `[Member("items"), Item(0)]`
+    /// Members are only found in objects and items only in arrays, `Member("0")` is no item.
+    #[cfg(test)]
+    pub fn get_path(&self, path: &[Key]) -> Option<&Json> {
+        path.iter().try_fold(self, |value, key| match (value, key) {
+            (Json::Object(_), Key::Member(name)) => value.get(name),
+            (Json::Array(items), Key::Item(index)) => items.get(*index),
+            _ => None
+        })
+    }
+
+    /// # Replaces value at path, e.g. `[Member("address"), Member("city")]`
+    /// Missing members are added, existing items of arrays are replaced.
+    /// Arrays are left unchanged by members and missing items, other values in the way of members are replaced by objects
+    /// and left unchanged by items.
+    #[cfg(test)]
+    pub fn set_path(&mut self, path: &[Key], value: Json) {
+        let (key, rest) = match path.split_first() {
+            Some(first) => first,
+            None => {
+                *self = value;
+                return;
+            }
+        };
+        let name = match (&mut *self, key) {
+            (Json::Array(items), Key::Item(index)) => {
+                if let Some(item) = items.get_mut(*index) {
+                    item.set_path(rest, value);
+                }
+                return;
+            },
+            (Json::Array(_), Key::Member(_))|(_, Key::Item(_)) => return,
+            (_, Key::Member(name)) => *name
+        };
+        if !matches!(self, Json::Object(_)) {
+            *self = Json::Object(Vec::new());
+        }
+        if let Json::Object(members) = self {
+            let index = match members.iter().position(|(member, _)| member == name) {
+                Some(index) => index,
+                None => {
+                    members.push((name.to_string(), Json::Null));
+                    members.len() - 1
+                }
+            };
+            members[index].1.set_path(rest, value);
+        }
+    }
+
+    /// Null, false, 0, empty strings, arrays and objects
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Json::Null => true,
+            Json::Bool(b) => !b,
+            Json::Number(n) => *n == 0.0,
+            Json::String(s) => s.is_empty(),
+            Json::Array(items) => items.is_empty(),
+            Json::Object(members) => members.is_empty()
+        }
+    }
+
+    /// Text of the value as it is inserted into a page
+    #[cfg(test)]
+    pub fn to_text(&self) -> String {
+        match self {
+            Json::Null => String::new(),
+            Json::String(s) => s.clone(),
+            _ => self.serialize()
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        match self {
+            Json::Null => "null".to_string(),
+            Json::Bool(b) => b.to_string(),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => format!("{}", *n as i64),
+            Json::Number(n) => n.to_string(),
+            Json::String(s) => {
+                let mut text = String::from("\"");
+                for c in s.chars() {
+                    match c {
+                        '"' => text.push_str("\\\""),
+                        '\\' => text.push_str("\\\\"),
+                        '\n' => text.push_str("\\n"),
+                        '\r' => text.push_str("\\r"),
+                        '\t' => text.push_str("\\t"),
+                        c if (c as u32) < 0x20 => text.push_str(&format!("\\u{:04x}", c as u32)),
+                        c => text.push(c)
+                    }
+                }
+                text.push('"');
+                text
+            },
+            Json::Array(items) => format!("[{}]", items.iter().map(|item| item.serialize()).collect::<Vec<String>>().join(",")),
+            Json::Object(members) => format!("{{{}}}", members.iter()
+                .map(|(name, value)| format!("{}:{}", Json::String(name.clone()).serialize(), value.serialize()))
+                .collect::<Vec<String>>().join(","))
+        }
+    }
+
+    /// Serializes like `serialize`, members and items are written on lines of their own, indented by `indent`
+    pub fn serialize_pretty(&self, indent: usize) -> String {
+        let inner = " ".repeat(indent + 2);
+        match self {
+            Json::Array(items) if !items.is_empty() => format!("[\n{}\n{}]", items.iter()
+                .map(|item| format!("{}{}", inner, item.serialize_pretty(indent + 2)))
+                .collect::<Vec<String>>().join(",\n"), " ".repeat(indent)),
+            Json::Object(members) if !members.is_empty() => format!("{{\n{}\n{}}}", members.iter()
+                .map(|(name, value)| format!("{}{}: {}", inner, Json::String(name.clone()).serialize(), value.serialize_pretty(indent + 2)))
+                .collect::<Vec<String>>().join(",\n"), " ".repeat(indent)),
+            _ => self.serialize()
+        }
+    }
+}
+
+struct JsonParser<'s> {
+    source: &'s [u8],
+    index: usize
+}
+
+impl<'s> JsonParser<'s> {
+    fn skip_whitespace(&mut self) {
+        while self.index < self.source.len() && matches!(self.source[self.index], b' '|b'\t'|b'\n'|b'\r') {
+            self.index += 1;
+        }
+    }
+
+    fn current(&self) -> Option<u8> {
+        self.source.get(self.index).copied()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.current() {
+            Some(found) if found == c => {
+                self.index += 1;
+                Ok(())
+            },
+            Some(found) => Err(format!("expected '{}' but found '{}' at {}", c as char, found as char, self.index)),
+            None => Err(format!("expected '{}' but source ended", c as char))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.current() {
+            Some(b'{') => self.parse_object(),
+            Some(b'[') => self.parse_array(),
+            Some(b'"') => Ok(Json::String(self.parse_string()?)),
+            Some(b't') => self.parse_literal("true", Json::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Json::Bool(false)),
+            Some(b'n') => self.parse_literal("null", Json::Null),
+            Some(b'-'|b'0'..=b'9') => self.parse_number(),
+            Some(c) => Err(format!("unexpected '{}' at {}", c as char, self.index)),
+            None => Err("unexpected end of source".to_string())
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        if self.source[self.index..].starts_with(literal.as_bytes()) {
+            self.index += literal.len();
+            Ok(value)
+        } else {
+            Err(format!("unexpected word at {}", self.index))
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let begin = self.index;
+        while let Some(b'-'|b'+'|b'.'|b'e'|b'E'|b'0'..=b'9') = self.current() {
+            self.index += 1;
+        }
+        let text = std::str::from_utf8(&self.source[begin..self.index]).unwrap();
+        text.parse::<f64>().map(Json::Number).map_err(|_| format!("invalid number '{}' at {}", text, begin))
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            match self.current() {
+                Some(b'"') => {
+                    self.index += 1;
+                    return String::from_utf8(bytes).map_err(|_| "invalid UTF-8 in string".to_string());
+                },
+                Some(b'\\') => {
+                    self.index += 1;
+                    let escaped = match self.current() {
+                        Some(b'n') => '\n',
+                        Some(b't') => '\t',
+                        Some(b'r') => '\r',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'u') => {
+                            let hex = self.source.get(self.index + 1..self.index + 5)
+                                .and_then(|hex| std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .ok_or(format!("invalid unicode escape at {}", self.index))?;
+                            self.index += 4;
+                            char::from_u32(hex).unwrap_or('\u{fffd}')
+                        },
+                        Some(c) => c as char,
+                        None => return Err("unexpected end of source in string".to_string())
+                    };
+                    self.index += 1;
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buffer).as_bytes());
+                },
+                Some(c) => {
+                    self.index += 1;
+                    bytes.push(c);
+                },
+                None => return Err("unexpected end of source in string".to_string())
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.current() == Some(b']') {
+            self.index += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.current() {
+                Some(b',') => self.index += 1,
+                _ => {
+                    self.expect(b']')?;
+                    return Ok(Json::Array(items));
+                }
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.current() == Some(b'}') {
+            self.index += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.parse_string()?;
+            self.expect(b':')?;
+            members.push((name, self.parse_value()?));
+            self.skip_whitespace();
+            match self.current() {
+                Some(b',') => self.index += 1,
+                _ => {
+                    self.expect(b'}')?;
+                    return Ok(Json::Object(members));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Json, Key::{Item, Member}};
+
+    #[test]
+    fn parse_and_serialize() {
+        let json = Json::parse(" {\"name\": \"Ann \\\"A\\\"\", \"age\": 42, \"tags\": [\"a\", true, null], \"pos\": {\"x\": -1.5e1}} ").unwrap();
+
+        assert_eq!(json.get_path(&[Member("name")]), Some(&Json::String("Ann \"A\"".to_string())));
+        assert_eq!(json.get_path(&[Member("tags"), Item(1)]), Some(&Json::Bool(true)));
+        assert_eq!(json.get_path(&[Member("pos"), Member("x")]), Some(&Json::Number(-15.0)));
+        assert_eq!(json.get_path(&[Member("pos"), Member("y")]), None);
+        assert_eq!(json.get_path(&[Member("tags"), Member("1")]), None);
+        assert_eq!(json.serialize(), "{\"name\":\"Ann \\\"A\\\"\",\"age\":42,\"tags\":[\"a\",true,null],\"pos\":{\"x\":-15}}");
+        assert_eq!(Json::parse("\"\\u00fc\"").unwrap().to_text(), "ü");
+
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("1 2").is_err());
+        assert!(Json::parse("").is_err());
+    }
+
+    #[test]
+    fn serialize_pretty() {
+        let json = Json::parse("{\"a\": [1, {\"b\": []}], \"c\": {}}").unwrap();
+        assert_eq!(json.serialize_pretty(0), "{\n  \"a\": [\n    1,\n    {\n      \"b\": []\n    }\n  ],\n  \"c\": {}\n}");
+    }
+
+    #[test]
+    fn set_paths() {
+        let mut json = Json::parse("{\"name\": \"Ann\", \"address\": {\"city\": \"Ulm\"}, \"tags\": [], \"items\": [{\"n\": 1}]}").unwrap();
+        json.set_path(&[Member("address"), Member("city")], Json::String("Bonn".to_string()));
+        json.set_path(&[Member("address"), Member("zip")], Json::Number(53111.0));
+        json.set_path(&[Member("tags"), Member("first")], Json::Bool(true));
+        json.set_path(&[Member("items"), Item(0), Member("n")], Json::Number(2.0));
+        json.set_path(&[Member("items"), Item(1), Member("n")], Json::Number(3.0));
+        json.set_path(&[Member("items"), Member("0"), Member("n")], Json::Number(4.0));
+        json.set_path(&[Member("name"), Item(0)], Json::Null);
+        assert_eq!(json.serialize(), "{\"name\":\"Ann\",\"address\":{\"city\":\"Bonn\",\"zip\":53111},\"tags\":[],\"items\":[{\"n\":2}]}");
+
+        let mut json = Json::Null;
+        json.set_path(&[Member("email")], Json::String("a@b.c".to_string()));
+        assert_eq!(json.serialize(), "{\"email\":\"a@b.c\"}");
+    }
+}
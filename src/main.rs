@@ -1,11 +1,261 @@
 mod strings;
+// CompilationError carries its source view and is returned by value throughout the compiler
+#[allow(clippy::result_large_err)]
 mod compiler;
+mod json;
+mod pattern;
+mod schema;
+mod infer;
+// reference runtime of the generated clients, only run by the tests
+#[cfg(test)]
+mod interpreter;
 
-use compiler::components::scanner::InputComponent;
+use std::{collections::LinkedList, fs, io, path::{Path, PathBuf}, thread, time::Duration};
+
+use compiler::CompilationError;
+use compiler::components::{checker::CheckerComponent, outputcomponent::{OutputComponent, chunk_name}, scanner::InputComponent, splitter::SplitterComponent, structcomponent::StructComponent};
+use compiler::config::{Config, Environment};
+use compiler::model::CoreModel;
+use compiler::sources::SourceDatabase;
 use strings::{sourceview::SourceView};
 use compiler::output::ConsoleOutput;
 
+const USAGE: &str = "usage: websorcery compile <templates> [<environment>] [<output>]\n       websorcery watch <templates> [<environment>] [<output>]\n       websorcery infer <templates> <fixtures> [<schemas>]\n       websorcery structs <templates> [<output>]";
+
+/// Project configuration, read from the working directory (see `Config`)
+const CONFIG_FILE: &str = "websorcery.conf";
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("compile") => compile_command(&args[1..]),
+        Some("watch") => watch_command(&args[1..]),
+        Some("infer") => infer_command(&args[1..]),
+        Some("structs") => structs_command(&args[1..]),
+        Some(command) => Err(format!("command '{}' does not exist\n{}", command, USAGE)),
+        None => {
+            demo();
+            Ok(())
+        }
+    };
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    }
+}
+
+/// # Compiles the templates into C modules
+/// The settings of the environment are read from `websorcery.conf`, the first configured environment is used if none is given.
+/// The core module is written to `<output>/core.c`. If the environment enables chunks, every other module
+/// is written next to it, e.g. `<output>/pages_user_ws.c` (see `SplitterComponent`).
+fn compile_command(args: &[String]) -> Result<(), String> {
+    let (templates, environment, output) = match args {
+        [templates] => (templates, None, "build"),
+        [templates, environment] => (templates, Some(environment.as_str()), "build"),
+        [templates, environment, output] => (templates, Some(environment.as_str()), output.as_str()),
+        _ => return Err(USAGE.to_string())
+    };
+    let (templates, output) = (Path::new(templates), Path::new(output));
+    let config = read_config()?;
+    let environment = select_environment(&config, environment)?;
+
+    let mut files = Vec::new();
+    read_templates(templates, &mut files).map_err(|err| format!("cannot read templates in '{}': {}", templates.display(), err))?;
+    let model = compiler::build_model(template_sources(templates, &files), &mut LinkedList::new()).map_err(report)?;
+
+    let modules = write_modules(&model, environment, output)?;
+    println!("{}: {} template(s) -> {} module(s) in {}", environment.name, model.templates.len(), modules, output.display());
+    Ok(())
+}
+
+/// # Compiles the templates again whenever they change
+/// Takes the arguments of `compile`. The templates are read every second and kept in a `SourceDatabase`, so only
+/// changed templates are parsed again. Errors are reported and the previous modules are kept until they are fixed.
+fn watch_command(args: &[String]) -> Result<(), String> {
+    let (templates, environment, output) = match args {
+        [templates] => (templates, None, "build"),
+        [templates, environment] => (templates, Some(environment.as_str()), "build"),
+        [templates, environment, output] => (templates, Some(environment.as_str()), output.as_str()),
+        _ => return Err(USAGE.to_string())
+    };
+    let (templates, output) = (Path::new(templates), Path::new(output));
+    let config = read_config()?;
+    let environment = select_environment(&config, environment)?;
+
+    let mut database = SourceDatabase::new();
+    let mut ids: Vec<String> = Vec::new();
+    loop {
+        let mut files = Vec::new();
+        read_templates(templates, &mut files).map_err(|err| format!("cannot read templates in '{}': {}", templates.display(), err))?;
+        let sources = template_sources(templates, &files);
+
+        let removed: Vec<String> = ids.iter().filter(|id| !sources.iter().any(|(source, _)| source == *id)).cloned().collect();
+        let changed = sources.iter().any(|(id, content)| database.get(id) != Some(*content));
+        if changed || !removed.is_empty() {
+            for id in removed.iter() {
+                database.remove(id);
+            }
+            ids = sources.iter().map(|(id, _)| id.clone()).collect();
+            for (id, content) in sources {
+                database.set(id, content);
+            }
+
+            match database.build(&mut LinkedList::new()) {
+                Ok(model) => {
+                    let modules = write_modules(&model, environment, output)?;
+                    println!("{}: {} of {} template(s) parsed -> {} module(s) in {}",
+                        environment.name, database.reparsed().len(), model.templates.len(), modules, output.display());
+                },
+                Err(err) => eprintln!("{}", report(err))
+            }
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+/// # Writes the C modules of the model, returns their number
+/// The core module is written to `<output>/core.c`. If the environment enables chunks, every other module
+/// is written next to it (see `chunk_name`).
+fn write_modules(model: &CoreModel, environment: &Environment, output: &Path) -> Result<usize, String> {
+    let modules: Vec<(String, String)> = if environment.chunks {
+        let chunks = SplitterComponent::new().split(model);
+        chunks.iter().map(|chunk| {
+            let mut component = OutputComponent::new();
+            component.set_base_url(&environment.base_url);
+            component.set_chunks(&chunks);
+            component.generate_chunk(model, chunk);
+            (chunk_name(chunk), component.finish())
+        }).collect()
+    } else {
+        let mut component = OutputComponent::new();
+        component.set_base_url(&environment.base_url);
+        component.generate_output(model);
+        vec![("core".to_string(), component.finish())]
+    };
+
+    fs::create_dir_all(output).map_err(|err| format!("cannot create directory '{}': {}", output.display(), err))?;
+    for (name, code) in modules.iter() {
+        let file = output.join(format!("{}.c", name));
+        fs::write(&file, code).map_err(|err| format!("cannot write module '{}': {}", file.display(), err))?;
+    }
+    Ok(modules.len())
+}
+
+fn read_config() -> Result<Config, String> {
+    let content = fs::read_to_string(CONFIG_FILE).map_err(|err| format!("cannot read configuration '{}': {}", CONFIG_FILE, err))?;
+    Config::parse(&content).map_err(report)
+}
+
+/// Environment of the given name or the first configured one
+fn select_environment<'c>(config: &'c Config, name: Option<&str>) -> Result<&'c Environment, String> {
+    match name {
+        Some(name) => config.get_environment(name).map_err(report),
+        None => config.environments.front().ok_or(format!("no environment is configured in '{}'", CONFIG_FILE))
+    }
+}
+
+/// # Infers schemas of all GET sources from sample responses
+/// Samples of a source are read from the fixtures (see `infer::read_samples`), its schema is written to
+/// `<schemas>/<path>.schema.json`, where it can be referenced by the request using `SCHEMA`.
+fn infer_command(args: &[String]) -> Result<(), String> {
+    let (templates, fixtures, schemas) = match args {
+        [templates, fixtures] => (templates, fixtures, "schemas"),
+        [templates, fixtures, schemas] => (templates, fixtures, schemas.as_str()),
+        _ => return Err(USAGE.to_string())
+    };
+    let (templates, fixtures, schemas) = (Path::new(templates), Path::new(fixtures), Path::new(schemas));
+
+    let mut files = Vec::new();
+    read_templates(templates, &mut files).map_err(|err| format!("cannot read templates in '{}': {}", templates.display(), err))?;
+    let model = compiler::build_model(template_sources(templates, &files), &mut LinkedList::new()).map_err(report)?;
+
+    for url in infer::get_sources(&model) {
+        let path = infer::source_path(&url);
+        let samples = infer::read_samples(fixtures, &path).map_err(|err| format!("cannot read samples of '{}': {}", url, err))?;
+        let schema = match infer::infer_schema(&samples)? {
+            Some(schema) => schema,
+            None => {
+                println!("{}: no samples in '{}'", url, fixtures.join(&path).display());
+                continue;
+            }
+        };
+        let file = schemas.join(format!("{}.schema.json", path));
+        fs::create_dir_all(file.parent().unwrap_or(schemas))
+            .and_then(|_| fs::write(&file, schema.to_json().serialize_pretty(0) + "\n"))
+            .map_err(|err| format!("cannot write schema '{}': {}", file.display(), err))?;
+        println!("{}: {} sample(s) -> {}", url, samples.len(), file.display());
+    }
+    Ok(())
+}
+
+/// # Generates Rust structs of the data fetched with a schema
+/// The schemas and samples referenced by the requests are read relative to the templates. The paths of the templates
+/// are checked against them first, so the templates only use fields the structs have (see `StructComponent`).
+fn structs_command(args: &[String]) -> Result<(), String> {
+    let (templates, output) = match args {
+        [templates] => (templates, "structs.rs"),
+        [templates, output] => (templates, output.as_str()),
+        _ => return Err(USAGE.to_string())
+    };
+    let (templates, output) = (Path::new(templates), Path::new(output));
+
+    let mut files = Vec::new();
+    read_templates(templates, &mut files).map_err(|err| format!("cannot read templates in '{}': {}", templates.display(), err))?;
+    let model = compiler::build_model(template_sources(templates, &files), &mut LinkedList::new()).map_err(report)?;
+
+    // missing files are reported by the checker
+    let mut schemas: Vec<(String, String)> = Vec::new();
+    for reference in model.templates.iter().flat_map(|template| template.requests()).filter_map(|fetch| fetch.schema.as_ref()) {
+        if !schemas.iter().any(|(id, _)| *id == reference.id) {
+            if let Ok(content) = fs::read_to_string(templates.join(&reference.id)) {
+                schemas.push((reference.id.clone(), content));
+            }
+        }
+    }
+    let schema_files = || schemas.iter().map(|(id, content)| (id.clone(), content.as_str())).collect::<LinkedList<_>>();
+
+    let mut checker = CheckerComponent::new(schema_files());
+    checker.check(&model);
+    checker.finish().map_err(report)?;
+    let mut structs = StructComponent::new(schema_files());
+    structs.generate(&model);
+    let code = structs.finish().map_err(report)?;
+
+    fs::write(output, code).map_err(|err| format!("cannot write structs '{}': {}", output.display(), err))?;
+    println!("{} schema(s) -> {}", schemas.len(), output.display());
+    Ok(())
+}
+
+/// Id (path relative to the directory, separated by `/`) and content of the templates
+fn template_sources<'f>(directory: &Path, files: &'f [(PathBuf, String)]) -> LinkedList<(String, &'f str)> {
+    files.iter().map(|(file, content)| {
+        let id = file.strip_prefix(directory).unwrap_or(file).components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>().join("/");
+        (id, content.as_str())
+    }).collect()
+}
+
+fn report(err: CompilationError) -> String {
+    format!("{}\n\n{}", err.get_header(), err.get_body())
+}
+
+/// Reads all templates (`*.ws`) in the directory and its subdirectories
+fn read_templates(directory: &Path, files: &mut Vec<(PathBuf, String)>) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(directory)?.map(|entry| entry.map(|entry| entry.path())).collect::<io::Result<_>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            read_templates(&entry, files)?;
+        } else if entry.extension().is_some_and(|extension| extension == "ws") {
+            let content = fs::read_to_string(&entry)?;
+            files.push((entry, content));
+        }
+    }
+    Ok(())
+}
+
+fn demo() {
     println!("Welcome to WebSorcery \u{1F468}");
     let source = SourceView::from_string("{}");
 
@@ -18,11 +268,8 @@ fn main() {
 
     if !status {
         
-        match input_comp.get_error() {
-            Some(err) => {
-                println!("{}\n\n{}", err.get_header(), err.get_body());
-            },
-            None => {}
+        if let Some(err) = input_comp.get_error() {
+            println!("{}\n\n{}", err.get_header(), err.get_body());
         }
     } else {
         println!("{:#?}", input_comp.tmodel);
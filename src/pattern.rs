@@ -0,0 +1,458 @@
+/**
+ * Regular expression of a validation rule, supporting the common subset of JavaScript's syntax as the
+ * `pattern` attribute of HTML inputs compiles it: literals, `.`, classes like `[a-z0-9_]` or `[^ ]`,
+ * escapes `\d \w \s \D \W \S \n \t \xHH \uHHHH` and escaped symbols, groups with alternatives `(a|b)`,
+ * quantifiers `* + ? {n} {n,} {n,m}` and assertions `^ $ \b \B`.
+ * Backreferences, lookarounds and named groups are rejected.
+ * Like the `pattern` attribute, the whole text must match.
+ *
+ * Patterns are compiled into a program which is run on all possible positions at once,
+ * so matching takes linear time in the length of the text, e.g. for `(a*)*b`.
+ * The compiler only checks patterns and the size of their programs, the programs are run by the reference runtime.
+ */
+#[derive(Debug)]
+#[cfg_attr(not(test), allow(dead_code))]
+pub struct Pattern {
+    program: Vec<Instruction>
+}
+
+type Sequence = Vec<Piece>;
+
+#[derive(Debug)]
+enum Node {
+    Char(char),
+    /// any character except line breaks
+    Any,
+    Class { ranges: Vec<(char, char)>, negated: bool },
+    Group(Vec<Sequence>),
+    Assertion(Assertion)
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(not(test), allow(dead_code))]
+enum Assertion {
+    Start,
+    End,
+    /// `\b`, or `\B` if negated
+    WordBoundary { negated: bool }
+}
+
+/**
+ * Node repeated between `min` and `max` times, `None` is unbounded
+ */
+#[derive(Debug)]
+struct Piece {
+    node: Node,
+    min: usize,
+    max: Option<usize>
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(not(test), allow(dead_code))]
+enum Instruction {
+    Char(char),
+    Any,
+    Class { ranges: Vec<(char, char)>, negated: bool },
+    Assertion(Assertion),
+    /// continues at both positions
+    Split(usize, usize),
+    Jump(usize),
+    Match
+}
+
+const DIGITS: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+const SPACE: &[(char, char)] = &[(' ', ' '), ('\t', '\r'), ('\u{a0}', '\u{a0}'), ('\u{1680}', '\u{1680}'), ('\u{2000}', '\u{200a}'),
+    ('\u{2028}', '\u{2029}'), ('\u{202f}', '\u{202f}'), ('\u{205f}', '\u{205f}'), ('\u{3000}', '\u{3000}'), ('\u{feff}', '\u{feff}')];
+/// characters `.` does not match
+#[cfg(test)]
+const LINE_BREAKS: &[char] = &['\n', '\r', '\u{2028}', '\u{2029}'];
+/// characters that may be escaped to match themselves
+const SYNTAX: &str = "^$\\.*+?()[]{}|/";
+/// instructions of a compiled pattern, repeating groups like `((a{100}){100}){100}` exceeds it
+const MAX_PROGRAM: usize = 10000;
+
+impl Pattern {
+    /// Parses pattern, errors describe the first invalid part
+    pub fn parse(source: &str) -> Result<Pattern, String> {
+        let mut reader = Reader { chars: source.chars().collect(), pos: 0 };
+        let alternatives = reader.alternatives(0)?;
+        let mut program = Vec::new();
+        compile_alternatives(&alternatives, &mut program)?;
+        program.push(Instruction::Match);
+        Ok(Pattern { program })
+    }
+
+    /// Whether the whole text matches
+    #[cfg(test)]
+    pub fn matches(&self, text: &str) -> bool {
+        let input: Vec<char> = text.chars().collect();
+        // position each instruction was added at last, so every instruction runs once per position
+        let mut added = vec![usize::MAX; self.program.len()];
+        let mut current = Vec::new();
+        self.add(&mut current, &mut added, 0, &input, 0);
+        for (pos, c) in input.iter().copied().enumerate() {
+            let mut next = Vec::new();
+            for pc in current {
+                let matched = match &self.program[pc] {
+                    Instruction::Char(expected) => *expected == c,
+                    Instruction::Any => !LINE_BREAKS.contains(&c),
+                    Instruction::Class { ranges, negated } => in_ranges(ranges, c) != *negated,
+                    _ => false
+                };
+                if matched {
+                    self.add(&mut next, &mut added, pc + 1, &input, pos + 1);
+                }
+            }
+            current = next;
+        }
+        current.iter().any(|pc| matches!(self.program[*pc], Instruction::Match))
+    }
+
+    /// Adds the instructions reading the next character or matching, following jumps and assertions from `pc`
+    #[cfg(test)]
+    fn add(&self, threads: &mut Vec<usize>, added: &mut [usize], pc: usize, input: &[char], pos: usize) {
+        let mut pending = vec![pc];
+        while let Some(pc) = pending.pop() {
+            if added[pc] == pos {
+                continue;
+            }
+            added[pc] = pos;
+            match &self.program[pc] {
+                Instruction::Jump(target) => pending.push(*target),
+                Instruction::Split(first, second) => pending.extend([*second, *first]),
+                Instruction::Assertion(assertion) => if holds(*assertion, input, pos) {
+                    pending.push(pc + 1);
+                },
+                _ => threads.push(pc)
+            }
+        }
+    }
+}
+
+struct Reader {
+    chars: Vec<char>,
+    pos: usize
+}
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    /// Alternatives until the end of the pattern or of the enclosing group
+    fn alternatives(&mut self, depth: usize) -> Result<Vec<Sequence>, String> {
+        let mut alternatives = vec![Vec::new()];
+        while let Some(c) = self.peek() {
+            match c {
+                '|' => {
+                    self.pos += 1;
+                    alternatives.push(Vec::new());
+                },
+                ')' if depth > 0 => break,
+                ')' => return Err("unmatched ')'".to_string()),
+                _ => {
+                    let node = self.node(depth)?;
+                    let (min, max) = self.quantifier()?;
+                    alternatives.last_mut().unwrap().push(Piece { node, min, max });
+                }
+            }
+        }
+        Ok(alternatives)
+    }
+
+    fn node(&mut self, depth: usize) -> Result<Node, String> {
+        match self.next().unwrap() {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Assertion(Assertion::Start)),
+            '$' => Ok(Node::Assertion(Assertion::End)),
+            '(' => {
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                } else if self.peek() == Some('?') {
+                    return Err("lookarounds and named groups are not supported".to_string());
+                }
+                let alternatives = self.alternatives(depth + 1)?;
+                match self.next() {
+                    Some(')') => Ok(Node::Group(alternatives)),
+                    _ => Err("missing ')'".to_string())
+                }
+            },
+            '[' => self.class(),
+            '\\' => self.escape(false),
+            c @ ('*'|'+'|'?'|'{') => Err(format!("nothing to repeat before '{}'", c)),
+            c => Ok(Node::Char(c))
+        }
+    }
+
+    /// Escape after `\`, either a class like `\d`, an assertion like `\b` or an escaped character.
+    /// Inside of classes, `\b` is a backspace and `\-` a hyphen.
+    fn escape(&mut self, in_class: bool) -> Result<Node, String> {
+        let (ranges, negated) = match self.next() {
+            Some('d') => (DIGITS, false),
+            Some('D') => (DIGITS, true),
+            Some('w') => (WORD, false),
+            Some('W') => (WORD, true),
+            Some('s') => (SPACE, false),
+            Some('S') => (SPACE, true),
+            Some('b') if in_class => return Ok(Node::Char('\u{8}')),
+            Some('-') if in_class => return Ok(Node::Char('-')),
+            Some(c @ ('b'|'B')) => return Ok(Node::Assertion(Assertion::WordBoundary { negated: c == 'B' })),
+            Some('n') => return Ok(Node::Char('\n')),
+            Some('r') => return Ok(Node::Char('\r')),
+            Some('t') => return Ok(Node::Char('\t')),
+            Some('f') => return Ok(Node::Char('\u{c}')),
+            Some('v') => return Ok(Node::Char('\u{b}')),
+            Some('0') if !self.peek().is_some_and(|c| c.is_ascii_digit()) => return Ok(Node::Char('\0')),
+            Some('c') if self.peek().is_some_and(|c| c.is_ascii_alphabetic()) => {
+                let letter = self.next().unwrap();
+                return Ok(Node::Char(char::from(letter as u8 % 32)));
+            },
+            Some('x') => return self.code_point(2).map(Node::Char),
+            Some('u') if self.peek() == Some('{') => {
+                let end = self.chars[self.pos..].iter().position(|c| *c == '}').ok_or("missing '}'")?;
+                let digits: String = self.chars[self.pos + 1..self.pos + end].iter().collect();
+                self.pos += end + 1;
+                return u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32).map(Node::Char)
+                    .ok_or_else(|| format!("invalid escape '\\u{{{}}}'", digits));
+            },
+            Some('u') => return self.code_point(4).map(Node::Char),
+            Some(c @ '1'..='9') => return Err(format!("backreferences like '\\{}' are not supported", c)),
+            Some(c) if SYNTAX.contains(c) => return Ok(Node::Char(c)),
+            Some(c) => return Err(format!("invalid escape '\\{}'", c)),
+            None => return Err("pattern ends with '\\'".to_string())
+        };
+        Ok(Node::Class { ranges: ranges.to_vec(), negated })
+    }
+
+    /// Character of `\x` or `\u` escapes, given by `length` hex digits
+    fn code_point(&mut self, length: usize) -> Result<char, String> {
+        let digits: String = self.chars[self.pos..].iter().take(length).collect();
+        let escape = if length == 2 { 'x' } else { 'u' };
+        if digits.len() != length || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid escape '\\{}{}'", escape, digits));
+        }
+        self.pos += length;
+        u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32).ok_or_else(|| format!("invalid escape '\\{}{}'", escape, digits))
+    }
+
+    /// Class after `[`, e.g. `[a-z_]` or `[^@]`
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = Vec::new();
+        loop {
+            let first = match self.next() {
+                Some(']') if !ranges.is_empty() => return Ok(Node::Class { ranges, negated }),
+                Some('\\') => match self.escape(true)? {
+                    Node::Class { ranges: escaped, negated: false } => {
+                        ranges.extend(escaped);
+                        continue;
+                    },
+                    Node::Char(c) => c,
+                    _ => return Err("negated classes like '\\D' cannot be used inside of '[...]'".to_string())
+                },
+                Some(c) => c,
+                None => return Err("missing ']'".to_string())
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|c| *c != ']') {
+                self.pos += 1;
+                let last = self.next().unwrap();
+                if last < first {
+                    return Err(format!("invalid range '{}-{}'", first, last));
+                }
+                ranges.push((first, last));
+            } else {
+                ranges.push((first, first));
+            }
+        }
+    }
+
+    /// Quantifier following a node, `(1, Some(1))` if there is none
+    fn quantifier(&mut self) -> Result<(usize, Option<usize>), String> {
+        let quantifier = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                let end = self.chars[self.pos..].iter().position(|c| *c == '}').ok_or("missing '}'")?;
+                let bounds: String = self.chars[self.pos + 1..self.pos + end].iter().collect();
+                self.pos += end;
+                let invalid = || format!("invalid quantifier '{{{}}}'", bounds);
+                match bounds.split_once(',') {
+                    Some((min, "")) => (min.parse().map_err(|_| invalid())?, None),
+                    Some((min, max)) => {
+                        let (min, max): (usize, usize) = (min.parse().map_err(|_| invalid())?, max.parse().map_err(|_| invalid())?);
+                        if max < min {
+                            return Err(invalid());
+                        }
+                        (min, Some(max))
+                    },
+                    None => {
+                        let count = bounds.parse().map_err(|_| invalid())?;
+                        (count, Some(count))
+                    }
+                }
+            },
+            _ => return Ok((1, Some(1)))
+        };
+        self.pos += 1;
+        // lazy quantifiers match the same whole texts
+        if self.peek() == Some('?') {
+            self.pos += 1;
+        }
+        Ok(quantifier)
+    }
+}
+
+/// Appends the instructions of the alternatives, the first one that matches continues after all of them
+fn compile_alternatives(alternatives: &[Sequence], program: &mut Vec<Instruction>) -> Result<(), String> {
+    let mut jumps = Vec::new();
+    for (index, sequence) in alternatives.iter().enumerate() {
+        let split = program.len();
+        let last = index == alternatives.len() - 1;
+        if !last {
+            push(program, Instruction::Split(split + 1, 0))?;
+        }
+        for piece in sequence {
+            compile_piece(piece, program)?;
+        }
+        if !last {
+            jumps.push(program.len());
+            push(program, Instruction::Jump(0))?;
+            program[split] = Instruction::Split(split + 1, program.len());
+        }
+    }
+    let end = program.len();
+    for jump in jumps {
+        program[jump] = Instruction::Jump(end);
+    }
+    Ok(())
+}
+
+/// Appends the node `min` times, followed by optional repetitions or a loop if it is unbounded
+fn compile_piece(piece: &Piece, program: &mut Vec<Instruction>) -> Result<(), String> {
+    for _ in 0..piece.min {
+        compile_node(&piece.node, program)?;
+    }
+    match piece.max {
+        None => {
+            let split = program.len();
+            push(program, Instruction::Split(split + 1, 0))?;
+            compile_node(&piece.node, program)?;
+            push(program, Instruction::Jump(split))?;
+            program[split] = Instruction::Split(split + 1, program.len());
+        },
+        Some(max) => {
+            let mut splits = Vec::new();
+            for _ in piece.min..max {
+                splits.push(program.len());
+                push(program, Instruction::Split(0, 0))?;
+                compile_node(&piece.node, program)?;
+            }
+            let end = program.len();
+            for split in splits {
+                program[split] = Instruction::Split(split + 1, end);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn compile_node(node: &Node, program: &mut Vec<Instruction>) -> Result<(), String> {
+    match node {
+        Node::Char(c) => push(program, Instruction::Char(*c)),
+        Node::Any => push(program, Instruction::Any),
+        Node::Class { ranges, negated } => push(program, Instruction::Class { ranges: ranges.clone(), negated: *negated }),
+        Node::Group(alternatives) => compile_alternatives(alternatives, program),
+        Node::Assertion(assertion) => push(program, Instruction::Assertion(*assertion))
+    }
+}
+
+fn push(program: &mut Vec<Instruction>, instruction: Instruction) -> Result<(), String> {
+    if program.len() >= MAX_PROGRAM {
+        return Err("pattern is too large".to_string());
+    }
+    program.push(instruction);
+    Ok(())
+}
+
+#[cfg(test)]
+fn in_ranges(ranges: &[(char, char)], c: char) -> bool {
+    ranges.iter().any(|(first, last)| *first <= c && c <= *last)
+}
+
+#[cfg(test)]
+fn holds(assertion: Assertion, input: &[char], pos: usize) -> bool {
+    match assertion {
+        Assertion::Start => pos == 0,
+        Assertion::End => pos == input.len(),
+        Assertion::WordBoundary { negated } => {
+            let word = |pos: Option<usize>| pos.and_then(|pos| input.get(pos)).is_some_and(|c| in_ranges(WORD, *c));
+            (word(pos.checked_sub(1)) != word(Some(pos))) != negated
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pattern;
+
+    #[test]
+    fn match_patterns() {
+        let matches = |pattern: &str, text: &str| Pattern::parse(pattern).unwrap().matches(text);
+
+        assert!(matches("[a-z]+", "abc"));
+        assert!(!matches("[a-z]+", "abc1"));
+        assert!(matches("\\d{5}", "89073"));
+        assert!(!matches("\\d{5}", "8907"));
+        assert!(matches("^\\+?[0-9 ]{6,}$", "+49 731 123"));
+        assert!(matches("(ab|c)*d", "abcabd"));
+        assert!(!matches("(ab|c)*d", "abad"));
+        assert!(matches("a.c\\.?", "a-c."));
+        assert!(matches("[^@\\s]+@[^@\\s]+", "a@b"));
+        assert!(!matches("[^@\\s]+@[^@\\s]+", "a b@c"));
+        assert!(matches("(a?){2}b", "b"));
+        assert!(matches("x{2,3}?", "xxx"));
+
+        // escapes of JavaScript
+        assert!(matches("a\\nb\\t", "a\nb\t"));
+        assert!(!matches("a\\nb", "anb"));
+        assert!(matches("\\x41\\u00e4\\u{1F600}\\cJ", "A\u{e4}\u{1F600}\n"));
+        assert!(matches("[\\b\\-]+", "\u{8}-"));
+        assert!(matches(".*\\bend\\b.*", "the end."));
+        assert!(!matches(".*\\bend\\b.*", "the ending"));
+        assert!(matches("\\s", "\u{a0}"));
+        assert!(!matches(".", "\r"));
+
+        // nested repetitions do not take exponential time
+        assert!(!matches("(a*)*b", &"a".repeat(5000)));
+        assert!(matches("(a|aa)+", &"a".repeat(5000)));
+        assert!(matches("(a?){3}b", "ab"));
+        assert!(matches("(|a)+b", "aab"));
+    }
+
+    #[test]
+    fn reject_invalid_patterns() {
+        assert_eq!(Pattern::parse("(a").err().unwrap(), "missing ')'");
+        assert_eq!(Pattern::parse("a)").err().unwrap(), "unmatched ')'");
+        assert_eq!(Pattern::parse("[a-").err().unwrap(), "missing ']'");
+        assert_eq!(Pattern::parse("[z-a]").err().unwrap(), "invalid range 'z-a'");
+        assert_eq!(Pattern::parse("*a").err().unwrap(), "nothing to repeat before '*'");
+        assert_eq!(Pattern::parse("a{3,1}").err().unwrap(), "invalid quantifier '{3,1}'");
+        assert_eq!(Pattern::parse("a\\").err().unwrap(), "pattern ends with '\\'");
+        assert_eq!(Pattern::parse("\\q").err().unwrap(), "invalid escape '\\q'");
+        assert_eq!(Pattern::parse("\\xZ1").err().unwrap(), "invalid escape '\\xZ1'");
+        assert_eq!(Pattern::parse("(a)\\1").err().unwrap(), "backreferences like '\\1' are not supported");
+        assert_eq!(Pattern::parse("(?=a)").err().unwrap(), "lookarounds and named groups are not supported");
+        assert_eq!(Pattern::parse("((a{100}){100}){100}").err().unwrap(), "pattern is too large");
+    }
+}
@@ -0,0 +1,470 @@
+use crate::json::Json;
+
+/**
+ * Shape of JSON data, read from a JSON Schema or inferred from sample data.
+ * Supported keywords of JSON Schema are `type`, `properties`, `required`, `additionalProperties`, `items`, `anyOf`, `oneOf`,
+ * `enum` and `const`. Keywords next to `anyOf` and `oneOf` apply to each of the alternatives.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schema {
+    /// data of unknown shape, e.g. the empty schema `{}`
+    Any,
+    Null,
+    Boolean,
+    Number,
+    /// numbers without fraction, samples are numbers even if they have none
+    Integer,
+    String,
+    Array(Box<Schema>),
+    /// properties in order of declaration, objects which are not `closed` may contain further properties of any shape
+    Object { properties: Vec<Property>, closed: bool },
+    /// data matching one of the schemas, e.g. `"type": ["string", "null"]`, empty if nothing matches (like items of `[]`)
+    Union(Vec<Schema>)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub schema: Schema,
+    pub required: bool
+}
+
+/**
+ * Reason why a path cannot be followed into data of a schema
+ */
+#[derive(Debug, PartialEq)]
+pub enum PathError {
+    /// closed object has no property of that name, contains the names of all properties
+    UnknownField(Vec<String>),
+    /// data of this kind has no properties, e.g. a string
+    NoFields(String)
+}
+
+impl Schema {
+    /// # Reads JSON Schema
+    /// Errors describe the first unsupported or invalid part.
+    pub fn parse(schema: &Json) -> Result<Schema, String> {
+        match schema {
+            Json::Bool(true) => return Ok(Schema::Any),
+            Json::Bool(false) => return Ok(Schema::Union(Vec::new())),
+            Json::Object(_) => {},
+            _ => return Err("schemas must be objects or booleans".to_string())
+        }
+        if schema.get("$ref").is_some() {
+            return Err("references ('$ref') are not supported, write the referenced schema in place".to_string());
+        }
+        if schema.get("allOf").is_some() {
+            return Err("'allOf' is not supported, write the combined schema in place".to_string());
+        }
+
+        if let Some((keyword, alternatives)) = ["anyOf", "oneOf"].iter().find_map(|keyword| schema.get(keyword).map(|value| (*keyword, value))) {
+            return match alternatives {
+                Json::Array(alternatives) => Ok(Schema::union(alternatives.iter()
+                    .map(|alternative| Schema::parse(&Schema::combine(schema, keyword, alternative)))
+                    .collect::<Result<_, _>>()?)),
+                _ => Err("'anyOf' and 'oneOf' must be arrays of schemas".to_string())
+            };
+        }
+        if let Some(value) = schema.get("const") {
+            return Ok(Schema::from_sample(value));
+        }
+        if let Some(values) = schema.get("enum") {
+            return match values {
+                Json::Array(values) => Ok(Schema::union(values.iter().map(Schema::from_sample).collect())),
+                _ => Err("'enum' must be an array of values".to_string())
+            };
+        }
+
+        let types = match schema.get("type") {
+            Some(Json::String(name)) => vec![name.as_str()],
+            Some(Json::Array(names)) => names.iter().map(|name| match name {
+                Json::String(name) => Ok(name.as_str()),
+                _ => Err("'type' must contain names of types".to_string())
+            }).collect::<Result<_, _>>()?,
+            Some(_) => return Err("'type' must be a name of a type or an array of names".to_string()),
+            None if schema.get("properties").is_some() => vec!["object"],
+            None if schema.get("items").is_some() => vec!["array"],
+            // schemas without type allow any data
+            None => return Ok(Schema::Any)
+        };
+        let schemas = types.into_iter().map(|name| match name {
+            "null" => Ok(Schema::Null),
+            "boolean" => Ok(Schema::Boolean),
+            "number" => Ok(Schema::Number),
+            "integer" => Ok(Schema::Integer),
+            "string" => Ok(Schema::String),
+            "array" => Ok(Schema::Array(Box::new(match schema.get("items") {
+                Some(items) => Schema::parse(items)?,
+                None => Schema::Any
+            }))),
+            "object" => Schema::parse_object(schema),
+            _ => Err(format!("type '{}' does not exist", name))
+        }).collect::<Result<_, _>>()?;
+        Ok(Schema::union(schemas))
+    }
+
+    /// # Schema of an alternative along with the keywords next to `keyword`
+    /// Properties and required properties of both are joined, other keywords of the alternative replace those next to it.
+    fn combine(schema: &Json, keyword: &str, alternative: &Json) -> Json {
+        let (Json::Object(members), Json::Object(own)) = (schema, alternative) else {
+            return alternative.clone();
+        };
+        let mut combined: Vec<(String, Json)> = members.iter().filter(|(name, _)| name != keyword).cloned().collect();
+        for (name, value) in own {
+            match (combined.iter_mut().find(|(existing, _)| existing == name), value) {
+                (Some((_, Json::Object(properties))), Json::Object(other)) if name == "properties" => {
+                    properties.retain(|(property, _)| !other.iter().any(|(name, _)| name == property));
+                    properties.extend(other.iter().cloned());
+                },
+                (Some((_, Json::Array(required))), Json::Array(other)) if name == "required" => required.extend(other.iter().cloned()),
+                (Some((_, existing)), value) => *existing = value.clone(),
+                (None, value) => combined.push((name.clone(), value.clone()))
+            }
+        }
+        Json::Object(combined)
+    }
+
+    fn parse_object(schema: &Json) -> Result<Schema, String> {
+        let closed = matches!(schema.get("additionalProperties"), Some(Json::Bool(false)));
+        let required: Vec<&str> = match schema.get("required") {
+            Some(Json::Array(names)) => names.iter().filter_map(|name| match name {
+                Json::String(name) => Some(name.as_str()),
+                _ => None
+            }).collect(),
+            Some(_) => return Err("'required' must be an array of property names".to_string()),
+            None => Vec::new()
+        };
+        let properties = match schema.get("properties") {
+            Some(Json::Object(properties)) => properties,
+            Some(_) => return Err("'properties' must be an object".to_string()),
+            None if closed => return Ok(Schema::Object { properties: Vec::new(), closed }),
+            // objects without declared properties may contain anything
+            None => return Ok(Schema::Any)
+        };
+        let properties = properties.iter().map(|(name, property)| Ok(Property {
+            name: name.clone(),
+            schema: Schema::parse(property).map_err(|cause| format!("property '{}': {}", name, cause))?,
+            required: required.contains(&name.as_str())
+        })).collect::<Result<_, String>>()?;
+        Ok(Schema::Object { properties, closed })
+    }
+
+    /// # Infers schema from sample data
+    /// All properties of objects are required and objects are closed, items of arrays are merged (see `merge`).
+    pub fn from_sample(sample: &Json) -> Schema {
+        match sample {
+            Json::Null => Schema::Null,
+            Json::Bool(_) => Schema::Boolean,
+            Json::Number(_) => Schema::Number,
+            Json::String(_) => Schema::String,
+            Json::Array(items) => Schema::Array(Box::new(items.iter()
+                .map(Schema::from_sample)
+                .fold(Schema::Union(Vec::new()), Schema::merge))),
+            Json::Object(members) => Schema::Object {
+                properties: members.iter().map(|(name, value)| Property {
+                    name: name.clone(),
+                    schema: Schema::from_sample(value),
+                    required: true
+                }).collect(),
+                closed: true
+            }
+        }
+    }
+
+    /// # Schema of data matching either of both schemas
+    /// Objects are merged into one object, properties missing on one of them are optional. The object is closed if both are.
+    /// Arrays are merged into one array, whose items match the items of both. Integers merged with numbers are numbers.
+    pub fn merge(self, other: Schema) -> Schema {
+        let mut members = self.members();
+        for member in other.members() {
+            match members.iter().position(|existing| existing.is_numeric() && member.is_numeric() || existing.kind() == member.kind()) {
+                Some(index) => {
+                    let existing = std::mem::replace(&mut members[index], Schema::Any);
+                    members[index] = existing.merge_same_kind(member);
+                },
+                None => members.push(member)
+            }
+        }
+        Schema::union(members)
+    }
+
+    fn merge_same_kind(self, other: Schema) -> Schema {
+        match (self, other) {
+            (Schema::Array(items), Schema::Array(other)) => Schema::Array(Box::new(items.merge(*other))),
+            (Schema::Integer, Schema::Integer) => Schema::Integer,
+            (Schema::Integer|Schema::Number, Schema::Integer|Schema::Number) => Schema::Number,
+            (Schema::Object { mut properties, closed }, Schema::Object { properties: other, closed: other_closed }) => {
+                for property in properties.iter_mut() {
+                    property.required &= other.iter().any(|other| other.name == property.name);
+                }
+                for property in other {
+                    match properties.iter_mut().find(|existing| existing.name == property.name) {
+                        Some(existing) => {
+                            let schema = std::mem::replace(&mut existing.schema, Schema::Any);
+                            existing.schema = schema.merge(property.schema);
+                            existing.required &= property.required;
+                        },
+                        None => properties.push(Property { required: false, ..property })
+                    }
+                }
+                Schema::Object { properties, closed: closed && other_closed }
+            },
+            (schema, _) => schema
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Schema::Number|Schema::Integer)
+    }
+
+    /// Whether the data might be null
+    pub fn is_nullable(&self) -> bool {
+        match self {
+            Schema::Null => true,
+            Schema::Union(members) => members.contains(&Schema::Null),
+            _ => false
+        }
+    }
+
+    /// Alternatives of a union or the schema itself
+    fn members(self) -> Vec<Schema> {
+        match self {
+            Schema::Union(members) => members,
+            schema => vec![schema]
+        }
+    }
+
+    /// Union of the schemas, nested unions are flattened, `Any` absorbs all other schemas and `Number` absorbs `Integer`
+    pub fn union(schemas: Vec<Schema>) -> Schema {
+        let mut members: Vec<Schema> = Vec::new();
+        for member in schemas.into_iter().flat_map(Schema::members) {
+            if member == Schema::Any {
+                return Schema::Any;
+            }
+            if member == Schema::Number {
+                members.retain(|existing| *existing != Schema::Integer);
+            }
+            if !(members.contains(&member) || member == Schema::Integer && members.contains(&Schema::Number)) {
+                members.push(member);
+            }
+        }
+        match members.len() {
+            1 => members.pop().unwrap(),
+            _ => Schema::Union(members)
+        }
+    }
+
+    /// # Writes the schema as JSON Schema
+    /// Alternatives of different types are written as a list of types, e.g. `"type": ["object", "null"]`,
+    /// along with the properties of the object and the items of the array among them.
+    pub fn to_json(&self) -> Json {
+        let members = match self {
+            Schema::Any => return Json::Object(Vec::new()),
+            Schema::Union(members) if members.is_empty() => return Json::Bool(false),
+            Schema::Union(members) => members.iter().collect(),
+            schema => vec![schema]
+        };
+        let kinds: Vec<&str> = members.iter().map(|member| member.type_name()).collect();
+        if kinds.iter().enumerate().any(|(index, kind)| kinds[..index].contains(kind)) {
+            return Json::Object(vec![("anyOf".to_string(), Json::Array(members.iter().map(|member| member.to_json()).collect()))]);
+        }
+
+        let mut schema = vec![("type".to_string(), match kinds.as_slice() {
+            [kind] => Json::String(kind.to_string()),
+            kinds => Json::Array(kinds.iter().map(|kind| Json::String(kind.to_string())).collect())
+        })];
+        for member in members {
+            match member {
+                Schema::Array(items) => schema.push(("items".to_string(), items.to_json())),
+                Schema::Object { properties, closed } => {
+                    schema.push(("properties".to_string(), Json::Object(properties.iter()
+                        .map(|property| (property.name.clone(), property.schema.to_json()))
+                        .collect())));
+                    schema.push(("required".to_string(), Json::Array(properties.iter()
+                        .filter(|property| property.required)
+                        .map(|property| Json::String(property.name.clone()))
+                        .collect())));
+                    if *closed {
+                        schema.push(("additionalProperties".to_string(), Json::Bool(false)));
+                    }
+                },
+                _ => ()
+            }
+        }
+        Json::Object(schema)
+    }
+
+    /// Name of the type in JSON Schema, unions and `Any` do not have one
+    fn type_name(&self) -> &'static str {
+        match self {
+            Schema::Null => "null",
+            Schema::Boolean => "boolean",
+            Schema::Number => "number",
+            Schema::Integer => "integer",
+            Schema::String => "string",
+            Schema::Array(_) => "array",
+            Schema::Object { .. } => "object",
+            Schema::Any|Schema::Union(_) => ""
+        }
+    }
+
+    /// Name of the kind of data, e.g. `string` or `object or null`
+    pub fn kind(&self) -> String {
+        match self {
+            Schema::Any => "any value".to_string(),
+            Schema::Null => "null".to_string(),
+            Schema::Boolean => "boolean".to_string(),
+            Schema::Number => "number".to_string(),
+            Schema::Integer => "integer".to_string(),
+            Schema::String => "string".to_string(),
+            Schema::Array(_) => "array".to_string(),
+            Schema::Object { .. } => "object".to_string(),
+            Schema::Union(members) if members.is_empty() => "nothing".to_string(),
+            Schema::Union(members) => members.iter().map(Schema::kind).collect::<Vec<String>>().join(" or ")
+        }
+    }
+
+    /// # Schema of a property, arrays have none (not even properties named like indices)
+    /// Properties of values which might be null are looked up in the other alternatives, paths must enter them with `?.`.
+    /// Undeclared properties of objects which are not closed may have any shape.
+    pub fn field(&self, name: &str) -> Result<Schema, PathError> {
+        match self {
+            Schema::Any => Ok(Schema::Any),
+            Schema::Object { properties, closed } => match properties.iter().find(|property| property.name == name) {
+                Some(property) => Ok(property.schema.clone()),
+                None if !closed => Ok(Schema::Any),
+                None => Err(PathError::UnknownField(properties.iter().map(|property| property.name.clone()).collect()))
+            },
+            Schema::Union(members) => {
+                let present: Vec<&Schema> = members.iter().filter(|member| **member != Schema::Null).collect();
+                if present.is_empty() {
+                    return if members.is_empty() { Ok(Schema::Any) } else { Err(PathError::NoFields(self.kind())) };
+                }
+                Ok(Schema::union(present.into_iter().map(|member| member.field(name)).collect::<Result<_, _>>()?))
+            },
+            _ => Err(PathError::NoFields(self.kind()))
+        }
+    }
+
+    /// Schema of the items of an array, `None` if the data is no array
+    pub fn items(&self) -> Option<Schema> {
+        match self {
+            Schema::Any => Some(Schema::Any),
+            Schema::Array(items) => Some(items.as_ref().clone()),
+            Schema::Union(members) => {
+                let present: Vec<&Schema> = members.iter().filter(|member| **member != Schema::Null).collect();
+                if present.is_empty() {
+                    return if members.is_empty() { Some(Schema::Any) } else { None };
+                }
+                Some(Schema::union(present.into_iter().map(Schema::items).collect::<Option<_>>()?))
+            },
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::Json;
+    use super::{PathError, Property, Schema};
+
+    fn parse(schema: &str) -> Result<Schema, String> {
+        Schema::parse(&Json::parse(schema).unwrap())
+    }
+
+    fn property(name: &str, schema: Schema, required: bool) -> Property {
+        Property { name: name.to_string(), schema, required }
+    }
+
+    fn object(properties: Vec<Property>, closed: bool) -> Schema {
+        Schema::Object { properties, closed }
+    }
+
+    #[test]
+    fn parse_schemas() {
+        let user = parse(r#"{ "type": "object", "required": ["id"], "properties": {
+            "id": { "type": "integer" },
+            "name": { "type": ["string", "null"] },
+            "tags": { "type": "array", "items": { "enum": ["a", "b"] } }
+        } }"#).unwrap();
+        assert_eq!(user, object(vec![
+            property("id", Schema::Integer, true),
+            property("name", Schema::Union(vec![Schema::String, Schema::Null]), false),
+            property("tags", Schema::Array(Box::new(Schema::String)), false)
+        ], false));
+        // further properties are allowed unless they are excluded
+        assert_eq!(user.field("email"), Ok(Schema::Any));
+        let closed = parse(r#"{ "properties": { "id": { "type": "number" } }, "additionalProperties": false }"#).unwrap();
+        assert_eq!(closed.field("email"), Err(PathError::UnknownField(vec!["id".to_string()])));
+
+        assert_eq!(parse("{}").unwrap(), Schema::Any);
+        assert_eq!(parse(r#"{ "type": ["integer", "number", "null"] }"#).unwrap(), Schema::Union(vec![Schema::Number, Schema::Null]));
+        assert_eq!(Schema::Integer.merge(Schema::Union(vec![Schema::Number, Schema::Null])), Schema::Union(vec![Schema::Number, Schema::Null]));
+        assert_eq!(parse(r#"{ "anyOf": [{ "type": "number" }, {}] }"#).unwrap(), Schema::Any);
+        assert_eq!(parse(r#"{ "type": "date" }"#).err().unwrap(), "type 'date' does not exist");
+        assert_eq!(parse(r##"{ "properties": { "a": { "$ref": "#/x" } } }"##).err().unwrap(),
+            "property 'a': references ('$ref') are not supported, write the referenced schema in place");
+        assert_eq!(parse(r#"{ "allOf": [{ "type": "number" }] }"#).err().unwrap(), "'allOf' is not supported, write the combined schema in place");
+    }
+
+    #[test]
+    fn parse_alternatives_with_siblings() {
+        let schema = parse(r#"{ "type": "object", "properties": { "kind": { "type": "string" } }, "required": ["kind"], "additionalProperties": false,
+            "oneOf": [{ "properties": { "url": { "type": "string" } } }, { "properties": { "size": { "type": "number" } }, "required": ["size"] }] }"#).unwrap();
+        assert_eq!(schema, Schema::Union(vec![
+            object(vec![property("kind", Schema::String, true), property("url", Schema::String, false)], true),
+            object(vec![property("kind", Schema::String, true), property("size", Schema::Number, true)], true)
+        ]));
+
+        // alternatives replace other keywords of the schema
+        assert_eq!(parse(r#"{ "type": "string", "anyOf": [{ "type": "null" }, {}] }"#).unwrap(), Schema::Union(vec![Schema::Null, Schema::String]));
+    }
+
+    #[test]
+    fn infer_schemas_from_samples() {
+        let schema = Schema::from_sample(&Json::parse(r#"{ "items": [{ "id": 1, "note": "x" }, { "id": 2, "done": true }, { "id": null }], "empty": [] }"#).unwrap());
+        assert_eq!(schema, object(vec![
+            property("items", Schema::Array(Box::new(object(vec![
+                property("id", Schema::Union(vec![Schema::Number, Schema::Null]), true),
+                property("note", Schema::String, false),
+                property("done", Schema::Boolean, false)
+            ], true))), true),
+            property("empty", Schema::Array(Box::new(Schema::Union(Vec::new()))), true)
+        ], true));
+    }
+
+    #[test]
+    fn follow_paths() {
+        let schema = Schema::from_sample(&Json::parse(r#"{ "user": { "name": "x", "tags": ["a"] }, "next": null }"#).unwrap());
+        let user = schema.field("user").unwrap();
+
+        assert_eq!(user.field("name"), Ok(Schema::String));
+        assert_eq!(user.field("tags").unwrap().items(), Some(Schema::String));
+        assert_eq!(user.field("tags").unwrap().field("0"), Err(PathError::NoFields("array".to_string())));
+        assert_eq!(user.field("nmae"), Err(PathError::UnknownField(vec!["name".to_string(), "tags".to_string()])));
+        assert_eq!(user.field("name").unwrap().field("first"), Err(PathError::NoFields("string".to_string())));
+        assert_eq!(schema.field("next").unwrap().field("id"), Err(PathError::NoFields("null".to_string())));
+        assert_eq!(Schema::Union(vec![user.clone(), Schema::Null]).field("name"), Ok(Schema::String));
+
+        assert_eq!(user.field("tags").unwrap().items(), Some(Schema::String));
+        assert_eq!(user.items(), None);
+    }
+
+    #[test]
+    fn write_schemas() {
+        let samples = [r#"{ "id": 1, "address": { "city": "Ulm" }, "tags": [] }"#, r#"{ "id": 2, "address": null, "tags": ["a"], "note": "x" }"#];
+        let schema = samples.iter().map(|sample| Schema::from_sample(&Json::parse(sample).unwrap())).reduce(Schema::merge).unwrap();
+
+        assert_eq!(schema.to_json().serialize(), "{\"type\":\"object\",\"properties\":{\
+            \"id\":{\"type\":\"number\"},\
+            \"address\":{\"type\":[\"object\",\"null\"],\"properties\":{\"city\":{\"type\":\"string\"}},\"required\":[\"city\"],\"additionalProperties\":false},\
+            \"tags\":{\"type\":\"array\",\"items\":{\"type\":\"string\"}},\
+            \"note\":{\"type\":\"string\"}},\
+            \"required\":[\"id\",\"address\",\"tags\"],\"additionalProperties\":false}");
+        assert_eq!(Schema::parse(&schema.to_json()), Ok(schema));
+
+        let alternatives = Schema::Union(vec![object(Vec::new(), true), object(vec![property("a", Schema::Any, true)], false)]);
+        assert_eq!(Schema::parse(&alternatives.to_json()), Ok(alternatives));
+        assert_eq!(Schema::parse(&Schema::Union(Vec::new()).to_json()), Ok(Schema::Union(Vec::new())));
+    }
+}
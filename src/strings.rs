@@ -1,54 +1,39 @@
 
 pub mod stringview {
-    use std::fmt;
+    use std::sync::Arc;
 
     /**
      * StringView is used to operate on strings.
+     * The viewed string is shared, so views can be kept and sent to other threads independently of the source they come from.
      */
-    #[derive(PartialEq)]
-    #[derive(Eq)]
-    pub struct StringView<'this> {
-        pub original: &'this str,
+    #[derive(Clone, PartialEq, Eq)]
+    pub struct StringView {
+        pub original: Arc<str>,
         pub from: usize,
         pub to: usize
     }
 
-    impl<'this> Clone for StringView<'this> {
-        fn clone(&self) -> StringView<'this> {
-            Self {
-                original: self.original,
-                from: self.from,
-                to: self.to
-            }
-        }
-
-        fn clone_from(&mut self, source: &Self) {
-            self.original = source.original;
-            self.from = source.from;
-            self.to = source.to;
-        }
-    }
-
-    impl<'this> std::fmt::Debug for StringView<'this> {
+    impl std::fmt::Debug for StringView {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             write!(f, "{}", self.show_slice(4))
         }
     }
 
-    impl<'this> StringView<'this> {
-        pub fn from_string(s: &'this str, from: usize, to: usize) -> StringView<'this> {
+    impl StringView {
+        pub fn from_string(s: impl Into<Arc<str>>, from: usize, to: usize) -> StringView {
             StringView {
-                original: s,
+                original: s.into(),
                 from,
                 to
             }
         }
 
-        pub fn new(s: &'this str) -> StringView<'this> {
-            if s == "" {
+        pub fn new(s: impl Into<Arc<str>>) -> StringView {
+            let s: Arc<str> = s.into();
+            if s.is_empty() {
                 StringView {
                     to: 0,
-                    original: " ",
+                    original: Arc::from(" "),
                     from: 0,
                 }
             } else {
@@ -61,7 +46,7 @@ pub mod stringview {
             
         }
 
-        pub fn substring<'call>(&self, from: usize, to: usize) -> Result<StringView<'this>, String> {
+        pub fn substring(&self, from: usize, to: usize) -> Result<StringView, String> {
             let len = self.original.chars().count();
             let new_from = self.from + from;
             let new_to = self.from + to;
@@ -75,7 +60,7 @@ pub mod stringview {
                 Err(format!("substring tries to access string outside of bounds: {} > {}", new_to, self.to))
             } else {
                 Ok(StringView {
-                    original: self.original,
+                    original: self.original.clone(),
                     from: new_from,
                     to: new_to
                 })
@@ -85,12 +70,18 @@ pub mod stringview {
         pub fn show_slice(&self, margin: usize) -> String {
             let length = self.original.chars().count();
             let right = if self.to + margin > length {length} else {self.to + margin};
-            let left = if self.from > margin {self.from-margin} else {0};
-            format!("...{}[{}]{}...", &self.original[left..self.from], &self.original[self.from..=self.to], &self.original[self.to+1..right])
+            let left = self.from.saturating_sub(margin);
+            let (left, from, to, right) = (self.byte_index(left), self.byte_index(self.from), self.byte_index(self.to + 1), self.byte_index(right));
+            format!("...{}[{}]{}...", &self.original[left..from], &self.original[from..to], &self.original[to..right])
         }
 
-        pub fn cut(&'this self) -> &'this str {
-            &self.original[self.from..=self.to]
+        pub fn cut(&self) -> &str {
+            &self.original[self.byte_index(self.from)..self.byte_index(self.to + 1)]
+        }
+
+        /// Views count chars, this is the byte offset of the char at `index` in the original string
+        fn byte_index(&self, index: usize) -> usize {
+            self.original.char_indices().nth(index).map_or(self.original.len(), |(byte, _)| byte)
         }
 
         pub fn char_at(&self, index: usize) -> Option<char> {
@@ -100,10 +91,6 @@ pub mod stringview {
                self.original.chars().nth(self.from + index) 
             }
         }
-
-        pub fn is_empty(&self) -> bool {
-            self.to - self.from == 0
-        }
     }
 
 
@@ -115,7 +102,7 @@ pub mod stringview {
         fn new() {
             let s = StringView::new("Hallo");
 
-            assert_eq!(s.original, "Hallo");
+            assert_eq!(&*s.original, "Hallo");
             assert_eq!(s.from, 0);
             assert_eq!(s.to, "Hallo".chars().count()-1);
         }
@@ -124,7 +111,7 @@ pub mod stringview {
         fn from_string() {
             let s = StringView::from_string("Hallo", 1, 3);
             
-            assert_eq!(s.original, "Hallo");
+            assert_eq!(&*s.original, "Hallo");
             assert_eq!(s.from, 1);
             assert_eq!(s.to, 3);
         } 
@@ -137,6 +124,14 @@ pub mod stringview {
             assert_eq!(s, view.cut());
         }
 
+        #[test]
+        fn cut_chars() {
+            let view = StringView::new("Grüße {name}").substring(6, 11).unwrap();
+
+            assert_eq!(view.cut(), "{name}");
+            assert_eq!(view.show_slice(2), "...e [{name}]...");
+        }
+
         #[test]
         fn equals() {
             let s1 = StringView::from_string("Hallo", 1, 3);
@@ -160,6 +155,7 @@ pub mod stringview {
 }
 
 pub mod sourceview {
+    use std::sync::Arc;
 
     use super::stringview::StringView;
 
@@ -171,11 +167,11 @@ pub mod sourceview {
         pub fn move_pos(&mut self, c: char) {
             match c {
                 '\n' => {
-                    self.line = self.line + 1;
+                    self.line += 1;
                     self.column = 0;
                 },
                 _ => {
-                    self.column = self.column + 1;
+                    self.column += 1;
                 } 
             }
         }
@@ -187,14 +183,14 @@ pub mod sourceview {
         }
     }
 
-    pub struct SourceView<'t> {
-        pub view: StringView<'t>,
+    pub struct SourceView {
+        pub view: StringView,
         pub position: SourcePosition,
         pub cursor: i64,
         reached_end: bool
     } 
 
-    impl<'t> Clone for SourceView<'t> {
+    impl Clone for SourceView {
         fn clone(&self) -> Self {
             Self {
                 view: self.view.clone(),
@@ -211,9 +207,9 @@ pub mod sourceview {
         }
     }
     
-    impl<'this> SourceView<'this> {
+    impl SourceView {
 
-        pub fn from_string(source: &'this str) -> SourceView<'this> {
+        pub fn from_string(source: impl Into<Arc<str>>) -> SourceView {
             Self {
                 cursor: -1,
                 position: SourcePosition {column: 0, line: 0},
@@ -222,7 +218,7 @@ pub mod sourceview {
             }
         }
 
-        pub fn clone_ranged<'call>(&self, begin: usize, end: usize) -> SourceView<'this> {
+        pub fn clone_ranged(&self, begin: usize, end: usize) -> SourceView {
             let subview = self.view.substring(begin, end);
             match subview {
                 Ok(_view) => {
@@ -234,14 +230,7 @@ pub mod sourceview {
                     }
                 },
 
-                Err(s) => panic!(s)
-            }
-        }
-
-        pub fn show_part(&self, from: usize, to: usize) -> String {
-            match self.view.substring(from, to) {
-                Ok(view) => view.show_slice(5),
-                Err(err) => panic!(err)
+                Err(s) => panic!("{}", s)
             }
         }
 
@@ -253,18 +242,28 @@ pub mod sourceview {
             }
         }
 
-        pub fn reset(&mut self) {
-            self.cursor = -1;
-            self.reached_end = false;
+        /// Returns the char after the cursor without moving it
+        pub fn peek(&self) -> Option<char> {
+            self.view.char_at((self.cursor + 1) as usize)
+        }
+
+        /// Checks if the chars after the cursor match `pattern` (ASCII case is ignored) without moving it
+        pub fn peek_matches(&self, pattern: &str) -> bool {
+            pattern.chars().enumerate().all(|(i, p)| {
+                match self.view.char_at((self.cursor + 1) as usize + i) {
+                    Some(c) => c.eq_ignore_ascii_case(&p),
+                    None => false
+                }
+            })
         }
     }
 
-    impl<'t> Iterator for SourceView<'t> {
+    impl Iterator for SourceView {
         
         type Item = char;
 
         fn next(&mut self) -> Option<char> {
-            self.cursor = self.cursor + 1;
+            self.cursor += 1;
 
             match self.view.char_at(self.cursor as usize) {
                 Some(c) => {
@@ -274,7 +273,7 @@ pub mod sourceview {
 
                 None => {
                     if self.reached_end {
-                        self.cursor = self.cursor - 1;
+                        self.cursor -= 1;
                     }
                     self.reached_end = true;
                     None
@@ -285,6 +284,8 @@ pub mod sourceview {
     }
 
     #[cfg(test)]
+    // the tests match single patterns to fail on unexpected results and count by hand
+    #[allow(clippy::single_match, clippy::assign_op_pattern)]
     mod tests {
         use super::SourceView;
 